@@ -1,4 +1,77 @@
 use anchor_lang::prelude::*;
 
 #[constant]
-pub const SEED: &str = "anchor";
\ No newline at end of file
+pub const SEED: &str = "anchor";
+
+/// Maximum swap fee in basis points (100%)
+#[constant]
+pub const MAX_FEE_BPS: u16 = 10_000;
+
+/// Number of price observations kept in a pool's TWAP oracle ring buffer
+pub const OBSERVATION_CAPACITY: usize = 64;
+
+/// Minimum number of seconds between two stored oracle observations.
+/// With the capacity above this keeps roughly an hour of price history.
+#[constant]
+pub const OBSERVATION_INTERVAL: i64 = 60;
+
+/// Maximum number of pools a routed swap can go through
+#[constant]
+pub const MAX_ROUTE_LEGS: usize = 4;
+
+/// Number of remaining accounts describing each leg of a routed swap
+#[constant]
+pub const ROUTE_LEG_ACCOUNTS: usize = 7;
+
+/// Smallest StableSwap amplification coefficient
+#[constant]
+pub const MIN_AMP: u64 = 1;
+
+/// Largest StableSwap amplification coefficient
+#[constant]
+pub const MAX_AMP: u64 = 1_000_000;
+
+/// Shortest allowed amplification ramp, so LPs have time to react
+#[constant]
+pub const MIN_RAMP_DURATION: i64 = 86_400;
+
+/// Largest factor the amplification coefficient can change by in one ramp
+#[constant]
+pub const MAX_AMP_CHANGE: u64 = 10;
+
+/// Flash loan fee in basis points of the borrowed amount, left in the pool for LPs
+#[constant]
+pub const FLASH_LOAN_FEE_BPS: u16 = 9;
+
+/// LP tokens locked forever by the first deposit of a pool, which must also
+/// deposit at least this many units of each token
+#[constant]
+pub const MINIMUM_LIQUIDITY: u64 = 1_000;
+
+/// Seconds it takes the tracked price volatility of a pool to halve
+#[constant]
+pub const VOLATILITY_HALF_LIFE: i64 = 300;
+
+/// Share of the recent price movement added to a dynamic swap fee, in basis
+/// points (1000 = a 5% move raises the fee by 0.5%)
+#[constant]
+pub const VOLATILITY_FEE_BPS: u16 = 1_000;
+
+/// Tightest price band limit in basis points, so a limit can't freeze trading
+/// by rejecting every swap of a useful size
+#[constant]
+pub const MIN_PRICE_BAND_BPS: u16 = 10;
+
+/// Layout version of newly created `Config` accounts
+/// Accounts from before versioning read as version 0 once migrated
+#[constant]
+pub const CONFIG_VERSION: u8 = 1;
+
+/// Unused bytes at the end of `Config`, for future fields to take over
+/// without growing the account
+/// 128 when versioning was introduced, minus the fields carved out since
+pub const CONFIG_RESERVED_BYTES: usize = 128 - 1 - 32 - 2 - 2 - 2 - 8 - 8 - 8 - 1 - 1;
+
+/// Scale of a farm's accumulated rewards per staked LP token, so small
+/// emissions shared by a large stake don't round down to nothing
+pub const REWARD_PRECISION: u128 = 1_000_000_000_000;
@@ -0,0 +1,106 @@
+use anchor_lang::prelude::*;
+use constant_product_curve::{ConstantProduct, LiquidityPair, SwapResult};
+
+use crate::curves::Curve;
+use crate::errors::AmmError;
+use crate::math::{amount_in_for_exact_out, zap_swap_amount};
+
+/// Constant product (x * y = k) pricing, backed by the `constant_product_curve` crate
+pub struct ConstantProductCurve;
+
+impl Curve for ConstantProductCurve {
+    fn swap(&self, reserve_x: u64, reserve_y: u64, is_x: bool, amount_in: u64, fee: u16) -> Result<SwapResult> {
+        // The LP supply doesn't affect swap pricing, so it is left at zero
+        let mut curve = ConstantProduct::init(reserve_x, reserve_y, 0, fee, None)
+            .map_err(AmmError::from)?;
+
+        let pair = match is_x {
+            true => LiquidityPair::X,   // Swapping X for Y
+            false => LiquidityPair::Y,  // Swapping Y for X
+        };
+        Ok(curve.swap(pair, amount_in, 0).map_err(AmmError::from)?)
+    }
+
+    fn amount_in_for_exact_out(
+        &self,
+        reserve_x: u64,
+        reserve_y: u64,
+        is_x: bool,
+        amount_out: u64,
+        fee: u16,
+    ) -> Result<u64> {
+        match is_x {
+            true => amount_in_for_exact_out(reserve_x, reserve_y, amount_out, fee),
+            false => amount_in_for_exact_out(reserve_y, reserve_x, amount_out, fee),
+        }
+    }
+
+    fn deposit_amounts(&self, reserve_x: u64, reserve_y: u64, supply: u64, liquidity: u64) -> Result<(u64, u64)> {
+        let amounts = ConstantProduct::xy_deposit_amounts_from_l(
+            reserve_x,
+            reserve_y,
+            supply,
+            liquidity,
+            6,  // Precision for calculations
+        )
+        .map_err(AmmError::from)?;
+        Ok((amounts.x, amounts.y))
+    }
+
+    fn withdraw_amounts(&self, reserve_x: u64, reserve_y: u64, supply: u64, liquidity: u64) -> Result<(u64, u64)> {
+        let amounts = ConstantProduct::xy_withdraw_amounts_from_l(
+            reserve_x,
+            reserve_y,
+            supply,
+            liquidity,
+            6,  // Precision for calculations
+        )
+        .map_err(AmmError::from)?;
+        Ok((amounts.x, amounts.y))
+    }
+
+    fn zap_swap_amount(&self, reserve_x: u64, reserve_y: u64, is_x: bool, amount_in: u64, fee: u16) -> Result<u64> {
+        // Closed form, see `math::zap_swap_amount`
+        zap_swap_amount(if is_x { reserve_x } else { reserve_y }, amount_in, fee)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::MINIMUM_LIQUIDITY;
+
+    /// Net result for an attacker who seeds an empty pool, donates to the vaults to
+    /// inflate the LP price, lets a victim deposit, then withdraws all their LP
+    fn donation_attack_profit(locked: u64, seed: u64, donation: u64, victim_budget: u64) -> i128 {
+        let curve = ConstantProductCurve;
+
+        // First deposit of `seed` of each token for `seed` LP tokens, plus the locked ones
+        let (mut reserve_x, mut reserve_y) = (seed + donation, seed + donation);
+        let mut supply = seed + locked;
+
+        // The victim asks for as many LP tokens as their budget buys at the inflated price
+        let liquidity = (victim_budget as u128 * supply as u128 / reserve_x as u128) as u64;
+        if liquidity > 0 {
+            let (x, y) = curve.deposit_amounts(reserve_x, reserve_y, supply, liquidity).unwrap();
+            reserve_x += x;
+            reserve_y += y;
+            supply += liquidity;
+        }
+
+        let (x, y) = curve.withdraw_amounts(reserve_x, reserve_y, supply, seed).unwrap();
+        (x + y) as i128 - 2 * (seed + donation) as i128
+    }
+
+    #[test]
+    fn donation_attack_is_unprofitable() {
+        for donation in [10_000, 1_000_000, 1_000_000_000] {
+            for victim_budget in [donation / 2, donation, donation * 3 / 2, donation * 10] {
+                // Seeding with the smallest accepted deposit leaves the attacker half the
+                // supply, so half of the donation goes to LP tokens nobody can redeem
+                let profit = donation_attack_profit(MINIMUM_LIQUIDITY, MINIMUM_LIQUIDITY, donation, victim_budget);
+                assert!(profit <= -(donation as i128), "attacker made {profit}");
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use anchor_lang::prelude::*;
+use constant_product_curve::SwapResult;
+
+use crate::constants::MAX_FEE_BPS;
+use crate::errors::AmmError;
+use crate::math::amounts_for_liquidity;
+
+pub mod constant_product;
+pub use constant_product::*;
+
+pub mod stable_swap;
+pub use stable_swap::*;
+
+/// Pricing function of a pool, chosen once at `initialize`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum CurveType {
+    /// x * y = k, for uncorrelated pairs
+    ConstantProduct,
+    /// Curve-style StableSwap invariant, for pairs trading close to 1:1
+    StableSwap,
+}
+
+/// Swap and liquidity math shared by every curve type
+///
+/// Fees are always taken from the input as `floor(amount_in * fee / 10000)` and
+/// stay in the pool, so curves only differ in how the net input is priced.
+pub trait Curve {
+    /// Prices selling `amount_in` (fee included) of one side of the pool
+    ///
+    /// # Arguments
+    /// * `reserve_x` - Pool reserve of token X
+    /// * `reserve_y` - Pool reserve of token Y
+    /// * `is_x` - true if selling token X for Y, false if selling Y for X
+    /// * `amount_in` - Amount of input tokens, fee included
+    /// * `fee` - Swap fee in basis points
+    ///
+    /// # Returns
+    /// * `Result<SwapResult>` - Input to deposit, output to withdraw and fee charged
+    fn swap(&self, reserve_x: u64, reserve_y: u64, is_x: bool, amount_in: u64, fee: u16) -> Result<SwapResult>;
+
+    /// Smallest input (fee included) that buys `amount_out` of the other side
+    ///
+    /// # Arguments
+    /// * `reserve_x` - Pool reserve of token X
+    /// * `reserve_y` - Pool reserve of token Y
+    /// * `is_x` - true if selling token X for Y, false if selling Y for X
+    /// * `amount_out` - Exact amount of output tokens wanted
+    /// * `fee` - Swap fee in basis points
+    ///
+    /// # Returns
+    /// * `Result<u64>` - Required amount of input tokens
+    fn amount_in_for_exact_out(
+        &self,
+        reserve_x: u64,
+        reserve_y: u64,
+        is_x: bool,
+        amount_out: u64,
+        fee: u16,
+    ) -> Result<u64>;
+
+    /// Token amounts to deposit for `liquidity` LP tokens, rounded up
+    ///
+    /// # Returns
+    /// * `Result<(u64, u64)>` - Amounts of token X and Y
+    fn deposit_amounts(&self, reserve_x: u64, reserve_y: u64, supply: u64, liquidity: u64) -> Result<(u64, u64)> {
+        amounts_for_liquidity(liquidity, reserve_x, reserve_y, supply)
+    }
+
+    /// Token amounts released by burning `liquidity` LP tokens, rounded down
+    ///
+    /// # Returns
+    /// * `Result<(u64, u64)>` - Amounts of token X and Y
+    fn withdraw_amounts(&self, reserve_x: u64, reserve_y: u64, supply: u64, liquidity: u64) -> Result<(u64, u64)> {
+        require!(supply != 0, AmmError::NoLiquidityInPool);
+        require!(liquidity <= supply, AmmError::InsufficientBalance);
+
+        // Never exceeds the reserves since liquidity <= supply
+        let x = liquidity as u128 * reserve_x as u128 / supply as u128;
+        let y = liquidity as u128 * reserve_y as u128 / supply as u128;
+        Ok((x as u64, y as u64))
+    }
+
+    /// Portion of a single-sided deposit to swap so the rest matches the pool ratio
+    ///
+    /// Bisects on the swap amount, pricing each guess with `swap`. Curves with a
+    /// closed form should override this, it costs a swap per bit of `amount_in`.
+    ///
+    /// # Arguments
+    /// * `reserve_x` - Pool reserve of token X
+    /// * `reserve_y` - Pool reserve of token Y
+    /// * `is_x` - true if depositing token X, false if depositing token Y
+    /// * `amount_in` - Amount of the deposited token
+    /// * `fee` - Swap fee in basis points
+    ///
+    /// # Returns
+    /// * `Result<u64>` - Amount of the deposited token to swap
+    fn zap_swap_amount(&self, reserve_x: u64, reserve_y: u64, is_x: bool, amount_in: u64, fee: u16) -> Result<u64> {
+        let (reserve_in, reserve_out) = match is_x {
+            true => (reserve_x, reserve_y),
+            false => (reserve_y, reserve_x),
+        };
+
+        // Largest swap that still leaves enough input to pair with its output
+        let (mut low, mut high) = (0u64, amount_in);
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            let out = self.swap(reserve_x, reserve_y, is_x, mid, fee)?.withdraw as u128;
+            let left = (amount_in - mid) as u128;
+            match left * (reserve_out as u128 - out) >= out * (reserve_in as u128 + mid as u128) {
+                true => low = mid,
+                false => high = mid,
+            }
+        }
+
+        Ok(low)
+    }
+}
+
+/// Splits a gross swap input into the net amount priced by the curve and the fee
+///
+/// # Arguments
+/// * `amount_in` - Amount of input tokens, fee included
+/// * `fee` - Swap fee in basis points
+///
+/// # Returns
+/// * `Result<(u64, u64)>` - (net input, fee amount)
+pub fn take_fee(amount_in: u64, fee: u16) -> Result<(u64, u64)> {
+    require!(fee <= MAX_FEE_BPS, AmmError::InvalidFee);
+
+    // Never exceeds amount_in since fee <= MAX_FEE_BPS
+    let fee_amount = (amount_in as u128 * fee as u128 / MAX_FEE_BPS as u128) as u64;
+    Ok((amount_in - fee_amount, fee_amount))
+}
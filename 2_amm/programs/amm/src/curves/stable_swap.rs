@@ -0,0 +1,286 @@
+use anchor_lang::prelude::*;
+use constant_product_curve::SwapResult;
+
+use crate::curves::{take_fee, Curve};
+use crate::errors::AmmError;
+use crate::math::amount_with_fee;
+
+#[allow(clippy::all)]
+mod uint {
+    uint::construct_uint! {
+        /// 256-bit unsigned integer, the invariant math cubes the reserves
+        pub struct U256(4);
+    }
+}
+pub use self::uint::U256;
+
+/// Maximum number of Newton iterations when solving the invariant
+const MAX_ITERATIONS: usize = 255;
+
+/// Extra units of input `amount_in_for_exact_out` may add to make up for Newton rounding
+const MAX_EXACT_OUT_NUDGES: u64 = 4;
+
+/// Curve-style StableSwap pricing for a pair of tokens
+///
+/// Solves `4A(x + y) + D = 4AD + D³ / (4xy)`, which trades like a constant sum
+/// near balance and like a constant product far from it. The higher the
+/// amplification coefficient A, the deeper the pool around 1:1.
+pub struct StableSwapCurve {
+    /// Amplification coefficient in effect.
+    pub amp: u64,
+}
+
+impl StableSwapCurve {
+    /// A * n^n, the leverage term of the invariant for two tokens
+    fn ann(&self) -> U256 {
+        U256::from(self.amp) * 4
+    }
+
+    /// Computes the invariant D of the given reserves
+    ///
+    /// # Arguments
+    /// * `x` - Pool reserve of token X
+    /// * `y` - Pool reserve of token Y
+    ///
+    /// # Returns
+    /// * `Result<U256>` - Invariant D, equal to x + y for a balanced pool
+    pub fn invariant(&self, x: u64, y: u64) -> Result<U256> {
+        require!(x != 0 && y != 0, AmmError::NoLiquidityInPool);
+
+        let (x, y) = (U256::from(x), U256::from(y));
+        let sum = x + y;
+        let ann = self.ann();
+
+        // Newton's method from D = x + y, which is always above the root
+        let mut d = sum;
+        for _ in 0..MAX_ITERATIONS {
+            // D³ / (4xy)
+            let d_p = d * d / (x * 2) * d / (y * 2);
+            let d_prev = d;
+            d = (ann * sum + d_p * 2) * d / ((ann - 1) * d + d_p * 3);
+
+            if d.max(d_prev) - d.min(d_prev) <= U256::one() {
+                return Ok(d);
+            }
+        }
+
+        err!(AmmError::CurveError)
+    }
+
+    /// Solves the invariant for one reserve given the other one
+    ///
+    /// # Arguments
+    /// * `other` - New reserve of the other token
+    /// * `d` - Invariant of the pool
+    ///
+    /// # Returns
+    /// * `Result<u64>` - Reserve keeping the invariant at `d`
+    pub fn reserve_for(&self, other: u64, d: U256) -> Result<u64> {
+        require!(other != 0, AmmError::ZeroBalance);
+
+        let other = U256::from(other);
+        let ann = self.ann();
+        // c = D³ / (4 * other * A * n^n), b = other + D / (A * n^n)
+        let c = d * d / (other * 2) * d / (ann * 2);
+        let b = other + d / ann;
+
+        // Newton's method on y² + (b - D) y = c, starting from y = D
+        let mut y = d;
+        for _ in 0..MAX_ITERATIONS {
+            let y_prev = y;
+            y = (y * y + c) / (y * 2 + b - d);
+
+            if y.max(y_prev) - y.min(y_prev) <= U256::one() {
+                return u64::try_from(y).map_err(|_| AmmError::Overflow.into());
+            }
+        }
+
+        err!(AmmError::CurveError)
+    }
+}
+
+impl Curve for StableSwapCurve {
+    fn swap(&self, reserve_x: u64, reserve_y: u64, is_x: bool, amount_in: u64, fee: u16) -> Result<SwapResult> {
+        require!(amount_in != 0, AmmError::InvalidAmount);
+        let (reserve_in, reserve_out) = match is_x {
+            true => (reserve_x, reserve_y),
+            false => (reserve_y, reserve_x),
+        };
+
+        let (net, fee_amount) = take_fee(amount_in, fee)?;
+        let d = self.invariant(reserve_in, reserve_out)?;
+        let new_reserve_in = reserve_in.checked_add(net).ok_or(AmmError::Overflow)?;
+        let new_reserve_out = self.reserve_for(new_reserve_in, d)?;
+
+        // Round the output down by one unit to cover Newton's precision, in favour of the pool
+        let withdraw = reserve_out.saturating_sub(new_reserve_out).saturating_sub(1);
+
+        Ok(SwapResult {
+            deposit: amount_in,
+            withdraw,
+            fee: fee_amount,
+        })
+    }
+
+    fn amount_in_for_exact_out(
+        &self,
+        reserve_x: u64,
+        reserve_y: u64,
+        is_x: bool,
+        amount_out: u64,
+        fee: u16,
+    ) -> Result<u64> {
+        let (reserve_in, reserve_out) = match is_x {
+            true => (reserve_x, reserve_y),
+            false => (reserve_y, reserve_x),
+        };
+        require!(amount_out != 0, AmmError::InvalidAmount);
+        // The pool can never be fully drained
+        require!(amount_out < reserve_out, AmmError::InsufficientBalance);
+
+        let d = self.invariant(reserve_in, reserve_out)?;
+        let new_reserve_in = self.reserve_for(reserve_out - amount_out, d)?;
+        let net = (new_reserve_in as u128).saturating_sub(reserve_in as u128) + 1;
+        let amount_in = amount_with_fee(net, fee)?;
+
+        // Newton's method is only exact to a unit, nudge the input up until it buys enough
+        for nudge in 0..MAX_EXACT_OUT_NUDGES {
+            let amount_in = amount_in.checked_add(nudge).ok_or(AmmError::Overflow)?;
+            if self.swap(reserve_x, reserve_y, is_x, amount_in, fee)?.withdraw >= amount_out {
+                return Ok(amount_in);
+            }
+        }
+
+        err!(AmmError::CurveError)
+    }
+}
+
+/// Amplification coefficient at `now` along a linear ramp
+///
+/// # Arguments
+/// * `initial` - Coefficient at the start of the ramp
+/// * `target` - Coefficient at the end of the ramp
+/// * `start` - Unix timestamp the ramp started at
+/// * `stop` - Unix timestamp the ramp ends at
+/// * `now` - Current unix timestamp
+///
+/// # Returns
+/// * `u64` - Coefficient in effect
+pub fn ramped_amp(initial: u64, target: u64, start: i64, stop: i64, now: i64) -> u64 {
+    if now >= stop || stop <= start {
+        return target;
+    }
+    if now <= start {
+        return initial;
+    }
+
+    let elapsed = (now - start) as u128;
+    let duration = (stop - start) as u128;
+    // The step is at most |target - initial|, so the casts back can't truncate
+    match target >= initial {
+        true => initial + ((target - initial) as u128 * elapsed / duration) as u64,
+        false => initial - ((initial - target) as u128 * elapsed / duration) as u64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curves::ConstantProductCurve;
+
+    #[test]
+    fn invariant_of_balanced_pool_is_the_sum() {
+        for amp in [1, 100, 1_000_000] {
+            let curve = StableSwapCurve { amp };
+            let d = curve.invariant(1_000_000, 1_000_000).unwrap();
+            assert!(d.as_u64().abs_diff(2_000_000) <= 1);
+        }
+    }
+
+    #[test]
+    fn swaps_never_lower_the_invariant() {
+        let pools = [(1_000_000u64, 1_000_000u64), (1_000_000, 3_000_000), (u64::MAX / 4, u64::MAX / 3), (10, 1_000_000)];
+        for amp in [1, 10, 100, 5_000] {
+            let curve = StableSwapCurve { amp };
+            for (reserve_x, reserve_y) in pools {
+                for amount_in in [1, 1_000, reserve_x / 10, reserve_x] {
+                    let d = curve.invariant(reserve_x, reserve_y).unwrap();
+                    let result = curve.swap(reserve_x, reserve_y, true, amount_in, 0).unwrap();
+                    if result.withdraw == 0 {
+                        continue;
+                    }
+                    let d_after = curve
+                        .invariant(reserve_x + result.deposit, reserve_y - result.withdraw)
+                        .unwrap();
+                    assert!(d_after >= d);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn deeper_than_constant_product_near_peg() {
+        let (reserve_x, reserve_y) = (1_000_000_000u64, 1_000_000_000u64);
+        let amount_in = 100_000_000;
+
+        let constant_product = ConstantProductCurve
+            .swap(reserve_x, reserve_y, true, amount_in, 30)
+            .unwrap();
+        let stable = StableSwapCurve { amp: 100 }
+            .swap(reserve_x, reserve_y, true, amount_in, 30)
+            .unwrap();
+
+        assert_eq!(stable.fee, constant_product.fee);
+        assert!(stable.withdraw > constant_product.withdraw);
+        // Still never better than 1:1 after fees
+        assert!(stable.withdraw <= amount_in - stable.fee);
+    }
+
+    #[test]
+    fn exact_out_buys_enough() {
+        let curve = StableSwapCurve { amp: 200 };
+        let pools = [(1_000_000u64, 1_000_000u64), (5_000_000, 1_000_000), (1_000_000, 5_000_000)];
+        for (reserve_x, reserve_y) in pools {
+            for fee in [0, 4, 30] {
+                for amount_out in [1, 10, 1_000, reserve_y / 2, reserve_y - 10] {
+                    let amount_in = curve
+                        .amount_in_for_exact_out(reserve_x, reserve_y, true, amount_out, fee)
+                        .unwrap();
+                    let result = curve.swap(reserve_x, reserve_y, true, amount_in, fee).unwrap();
+                    assert!(result.withdraw >= amount_out);
+                    // Not overpaying by more than the rounding slack
+                    if amount_in > 3 {
+                        let less = curve.swap(reserve_x, reserve_y, true, amount_in - 3, fee).unwrap();
+                        assert!(less.withdraw <= amount_out);
+                    }
+                }
+            }
+        }
+        assert!(curve.amount_in_for_exact_out(1_000, 1_000, true, 1_000, 30).is_err());
+    }
+
+    #[test]
+    fn zap_bisection_balances_amounts() {
+        let curve = StableSwapCurve { amp: 100 };
+        let (reserve_x, reserve_y) = (1_000_000_000u64, 800_000_000u64);
+        for amount_in in [1_000u64, 1_000_000, 500_000_000] {
+            let swap = curve.zap_swap_amount(reserve_x, reserve_y, true, amount_in, 30).unwrap();
+            let out = curve.swap(reserve_x, reserve_y, true, swap, 30).unwrap().withdraw;
+
+            let (new_x, new_y) = (reserve_x + swap, reserve_y - out);
+            let wanted = out as u128 * new_x as u128 / new_y as u128;
+            let dust = ((amount_in - swap) as u128).abs_diff(wanted);
+            assert!(dust <= amount_in as u128 / 1_000 + 4, "dust {dust} for {amount_in}");
+        }
+    }
+
+    #[test]
+    fn amp_ramps_linearly() {
+        assert_eq!(ramped_amp(100, 200, 1_000, 2_000, 500), 100);
+        assert_eq!(ramped_amp(100, 200, 1_000, 2_000, 1_500), 150);
+        assert_eq!(ramped_amp(100, 200, 1_000, 2_000, 2_500), 200);
+        assert_eq!(ramped_amp(200, 100, 1_000, 2_000, 1_250), 175);
+        // A finished or empty ramp sits at the target
+        assert_eq!(ramped_amp(100, 200, 1_000, 1_000, 1_000), 200);
+    }
+}
@@ -39,6 +39,60 @@ pub enum AmmError {
     InsufficientBalance,
     #[msg("Zero balance.")]
     ZeroBalance,
+    #[msg("No pending authority transfer.")]
+    NoPendingAuthority,
+    #[msg("Not enough price history for the requested window.")]
+    OracleHistoryTooShort,
+    #[msg("Invalid swap route.")]
+    InvalidRoute,
+    #[msg("Invalid amplification coefficient.")]
+    InvalidAmp,
+    #[msg("Invalid amplification ramp.")]
+    InvalidRamp,
+    #[msg("Not supported by this pool's curve type.")]
+    InvalidCurveType,
+    #[msg("A flash loan is in progress.")]
+    FlashLoanActive,
+    #[msg("No flash loan to repay.")]
+    NoFlashLoan,
+    #[msg("Flash loan is not repaid in the same transaction.")]
+    FlashLoanNotRepaid,
+    #[msg("Flash loans can't be taken through CPI.")]
+    FlashLoanCpi,
+    #[msg("Both tokens of a pool must be different.")]
+    IdenticalMints,
+    #[msg("Mints of a canonical pool must be in ascending order.")]
+    MintsNotSorted,
+    #[msg("Mint has an extension the pool doesn't support.")]
+    UnsupportedMint,
+    #[msg("Pool still has liquidity or uncollected fees.")]
+    PoolNotEmpty,
+    #[msg("Config account is not in a layout this instruction can migrate.")]
+    InvalidConfigVersion,
+    #[msg("Trader is neither allowlisted nor attested by the gatekeeper.")]
+    TraderNotAllowed,
+    #[msg("Neither token of the pool is wrapped SOL.")]
+    NoWrappedSol,
+    #[msg("Referrer token account doesn't hold the input token of the swap.")]
+    InvalidReferrer,
+    #[msg("Swap moves the price beyond the pool's price band.")]
+    PriceBandExceeded,
+    #[msg("Price band limits are too tight or the per-swap limit exceeds the per-slot one.")]
+    InvalidPriceBand,
+    #[msg("Referrer token account is owned by the trader.")]
+    SelfReferral,
+    #[msg("Canonical pools can't have an authority.")]
+    CanonicalPoolAuthority,
+    #[msg("Fee tier of a canonical pool can't be changed.")]
+    CanonicalPoolFee,
+    #[msg("The pool's oracle and pool index must be closed along with it.")]
+    MissingPoolAccount,
+    #[msg("Wrapped SOL account must be empty to trade in native SOL.")]
+    WrappedSolNotEmpty,
+    #[msg("The pool's creator is unknown, so nobody can be refunded on close.")]
+    UnknownCreator,
+    #[msg("Account isn't the pool index of the pool's pair and fee tier.")]
+    InvalidPoolIndex,
 }
 
 impl From<CurveError> for AmmError {
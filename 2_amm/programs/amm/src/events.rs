@@ -0,0 +1,276 @@
+use anchor_lang::prelude::*;
+
+use crate::curves::CurveType;
+use crate::states::Config;
+
+/// Emitted when a new pool is created
+#[event]
+#[derive(Clone, Debug)]
+pub struct PoolInitialized {
+    /// Seed of the pool's config PDA.
+    pub seed: u64,
+    /// Config account of the pool.
+    pub config: Pubkey,
+    /// Mint of token X.
+    pub mint_x: Pubkey,
+    /// Mint of token Y.
+    pub mint_y: Pubkey,
+    /// Swap fee in basis points.
+    pub fee: u16,
+    /// Pricing function of the pool.
+    pub curve_type: CurveType,
+    /// StableSwap amplification coefficient (zero for constant product pools).
+    pub amp: u64,
+    /// Update authority, if any.
+    pub authority: Option<Pubkey>,
+}
+
+/// Emitted when liquidity is deposited into a pool
+#[event]
+#[derive(Clone, Debug)]
+pub struct LiquidityAdded {
+    /// Seed of the pool's config PDA.
+    pub seed: u64,
+    /// Depositor.
+    pub user: Pubkey,
+    /// Amount of token X deposited, as received by the vault.
+    pub amount_x: u64,
+    /// Amount of token Y deposited, as received by the vault.
+    pub amount_y: u64,
+    /// LP tokens minted to the depositor.
+    pub liquidity: u64,
+    /// Pool reserve of token X after the deposit.
+    pub reserve_x: u64,
+    /// Pool reserve of token Y after the deposit.
+    pub reserve_y: u64,
+}
+
+/// Emitted when liquidity is withdrawn from a pool
+#[event]
+#[derive(Clone, Debug)]
+pub struct LiquidityRemoved {
+    /// Seed of the pool's config PDA.
+    pub seed: u64,
+    /// Withdrawer.
+    pub user: Pubkey,
+    /// Amount of token X withdrawn, as sent by the vault.
+    pub amount_x: u64,
+    /// Amount of token Y withdrawn, as sent by the vault.
+    pub amount_y: u64,
+    /// LP tokens burned.
+    pub liquidity: u64,
+    /// Pool reserve of token X after the withdrawal.
+    pub reserve_x: u64,
+    /// Pool reserve of token Y after the withdrawal.
+    pub reserve_y: u64,
+}
+
+/// Emitted for every swap against a pool, including route legs and zaps
+#[event]
+#[derive(Clone, Debug)]
+pub struct Swapped {
+    /// Seed of the pool's config PDA.
+    pub seed: u64,
+    /// Trader.
+    pub user: Pubkey,
+    /// true if token X was sold for Y, false if Y was sold for X.
+    pub is_x: bool,
+    /// Amount of input tokens received by the pool, swap fee included.
+    pub amount_in: u64,
+    /// Amount of output tokens sent by the pool.
+    pub amount_out: u64,
+    /// Fee paid, in input tokens (LP, protocol and referral shares).
+    pub fee: u64,
+    /// Swap fee rate applied, in basis points.
+    pub fee_bps: u16,
+    /// Owner of the token account the referral share was paid to, if any.
+    pub referrer: Option<Pubkey>,
+    /// Share of `fee` paid to the referrer, in input tokens.
+    pub referral_fee: u64,
+    /// Pool reserve of token X after the swap.
+    pub reserve_x: u64,
+    /// Pool reserve of token Y after the swap.
+    pub reserve_y: u64,
+}
+
+/// Emitted when an admin instruction changes a pool's settings
+/// Carries the resulting settings rather than just the changed one
+#[event]
+#[derive(Clone, Debug)]
+pub struct ConfigUpdated {
+    /// Seed of the pool's config PDA.
+    pub seed: u64,
+    /// Update authority, if any.
+    pub authority: Option<Pubkey>,
+    /// Authority nominated by a transfer in progress, if any.
+    pub pending_authority: Option<Pubkey>,
+    /// Swap fee in basis points, when the fee is static.
+    pub fee: u16,
+    /// Whether the swap fee follows recent price movement.
+    pub dynamic_fee: bool,
+    /// Lowest dynamic swap fee in basis points.
+    pub min_fee: u16,
+    /// Highest dynamic swap fee in basis points.
+    pub max_fee: u16,
+    /// Protocol share of the swap fee in basis points of the fee.
+    pub protocol_fee: u16,
+    /// Referrer share of the swap fee in basis points of the fee.
+    pub referral_fee: u16,
+    /// Largest price move of a single swap in basis points, zero for no limit.
+    pub max_price_impact: u16,
+    /// Largest price move within one slot in basis points, zero for no limit.
+    pub max_slot_deviation: u16,
+    /// Whether the pool is locked.
+    pub locked: bool,
+    /// StableSwap amplification coefficient at the end of the current ramp.
+    pub target_amp: u64,
+    /// Unix timestamp the current amplification ramp ends at.
+    pub ramp_stop: i64,
+    /// Whether swaps and deposits are restricted to allowlisted traders.
+    pub permissioned: bool,
+    /// Key whose attestations admit traders, `Pubkey::default()` if none.
+    pub gatekeeper: Pubkey,
+}
+
+/// Emitted when accrued protocol fees are sent to the treasury
+#[event]
+#[derive(Clone, Debug)]
+pub struct ProtocolFeesCollected {
+    /// Seed of the pool's config PDA.
+    pub seed: u64,
+    /// Amount of token X collected.
+    pub amount_x: u64,
+    /// Amount of token Y collected.
+    pub amount_y: u64,
+}
+
+/// Emitted when a flash loan is repaid
+#[event]
+#[derive(Clone, Debug)]
+pub struct FlashLoanRepaid {
+    /// Seed of the pool's config PDA.
+    pub seed: u64,
+    /// Repayer.
+    pub user: Pubkey,
+    /// true if token X was borrowed, false for token Y.
+    pub is_x: bool,
+    /// Amount paid back, fee included.
+    pub repayment: u64,
+}
+
+/// Emitted when a config account is migrated to the current layout
+#[event]
+#[derive(Clone, Debug)]
+pub struct ConfigMigrated {
+    /// Seed of the pool's config PDA.
+    pub seed: u64,
+    /// Layout version before the migration.
+    pub from_version: u8,
+    /// Layout version after the migration.
+    pub to_version: u8,
+}
+
+/// Emitted when an empty pool is closed
+#[event]
+#[derive(Clone, Debug)]
+pub struct PoolClosed {
+    /// Seed of the pool's config PDA.
+    pub seed: u64,
+    /// Config account of the pool, now closed.
+    pub config: Pubkey,
+    /// Account the rent was returned to.
+    pub creator: Pubkey,
+}
+
+/// Emitted when a farm is created or its emission rate changes
+#[event]
+#[derive(Clone, Debug)]
+pub struct FarmUpdated {
+    /// Seed of the pool's config PDA.
+    pub seed: u64,
+    /// Farm account.
+    pub farm: Pubkey,
+    /// Mint rewards are paid in.
+    pub reward_mint: Pubkey,
+    /// Rewards emitted per second.
+    pub reward_per_second: u64,
+}
+
+/// Emitted when LP tokens are staked in a farm
+#[event]
+#[derive(Clone, Debug)]
+pub struct Staked {
+    /// Seed of the pool's config PDA.
+    pub seed: u64,
+    /// Staker.
+    pub user: Pubkey,
+    /// LP tokens staked.
+    pub amount: u64,
+    /// LP tokens staked in the farm afterwards, by everyone.
+    pub total_staked: u64,
+}
+
+/// Emitted when LP tokens are taken out of a farm
+#[event]
+#[derive(Clone, Debug)]
+pub struct Unstaked {
+    /// Seed of the pool's config PDA.
+    pub seed: u64,
+    /// Staker.
+    pub user: Pubkey,
+    /// LP tokens unstaked.
+    pub amount: u64,
+    /// LP tokens staked in the farm afterwards, by everyone.
+    pub total_staked: u64,
+    /// true for an emergency withdrawal, which forfeits the unpaid rewards.
+    pub emergency: bool,
+}
+
+/// Emitted when farm rewards are paid out
+#[event]
+#[derive(Clone, Debug)]
+pub struct RewardsHarvested {
+    /// Seed of the pool's config PDA.
+    pub seed: u64,
+    /// Staker.
+    pub user: Pubkey,
+    /// Rewards paid, as sent by the reward vault.
+    pub amount: u64,
+    /// Rewards still owed because the reward vault ran short.
+    pub rewards_owed: u64,
+}
+
+/// Emitted when a trader is added to or removed from a permissioned pool's allowlist
+#[event]
+#[derive(Clone, Debug)]
+pub struct AllowlistUpdated {
+    /// Seed of the pool's config PDA.
+    pub seed: u64,
+    /// Trader whose access changed.
+    pub user: Pubkey,
+    /// Whether the trader is now allowlisted.
+    pub allowed: bool,
+}
+
+impl From<&Config> for ConfigUpdated {
+    fn from(config: &Config) -> Self {
+        Self {
+            seed: config.seed,
+            authority: config.authority,
+            pending_authority: config.pending_authority,
+            fee: config.fee,
+            dynamic_fee: config.dynamic_fee,
+            min_fee: config.min_fee,
+            max_fee: config.max_fee,
+            protocol_fee: config.protocol_fee,
+            referral_fee: config.referral_fee,
+            max_price_impact: config.max_price_impact,
+            max_slot_deviation: config.max_slot_deviation,
+            locked: config.locked,
+            target_amp: config.target_amp,
+            ramp_stop: config.ramp_stop,
+            permissioned: config.permissioned,
+            gatekeeper: config.gatekeeper,
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::AmmError;
+use crate::events::ConfigUpdated;
+use crate::states::Config;
+
+/// Accounts required for accepting a pending authority transfer
+/// Second step of the two-step authority handover started by `transfer_authority`
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    /// The nominated authority accepting the transfer (must sign the transaction)
+    pub pending_authority: Signer<'info>,
+
+    /// The AMM pool configuration account
+    /// Mutable because the authority is replaced on acceptance
+    /// Uses PDA derived from "config" seed and config.seed
+    #[account(
+        mut,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> AcceptAuthority<'info> {
+    /// Completes the authority transfer to the signer
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn accept_authority(&mut self) -> Result<()> {
+        // Only the key nominated by the current authority can accept
+        let pending = self
+            .config
+            .pending_authority
+            .ok_or(AmmError::NoPendingAuthority)?;
+        require_keys_eq!(
+            pending,
+            self.pending_authority.key(),
+            AmmError::InvalidAuthority
+        );
+
+        self.config.authority = Some(pending);
+        self.config.pending_authority = None;
+        emit!(ConfigUpdated::from(&*self.config));
+        Ok(())
+    }
+}
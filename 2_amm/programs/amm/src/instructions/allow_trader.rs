@@ -0,0 +1,65 @@
+use anchor_lang::prelude::*;
+
+use crate::events::AllowlistUpdated;
+use crate::states::{AllowlistEntry, Config};
+
+/// Accounts required for admitting a trader to a permissioned pool
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct AllowTrader<'info> {
+    /// The update authority of the pool (must sign the transaction)
+    /// Mutable because they pay for the allowlist entry
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// The AMM pool configuration account the trader is admitted to
+    /// Uses PDA derived from "config" seed and config.seed
+    #[account(
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    /// The trader's allowlist entry, created by this instruction
+    /// Uses PDA derived from "allowlist" seed, config and user pubkeys
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"allowlist", config.key().as_ref(), user.as_ref()],
+        bump,
+        space = 8 + AllowlistEntry::INIT_SPACE,
+    )]
+    pub allowlist_entry: Account<'info, AllowlistEntry>,
+
+    /// System program for account creation
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> AllowTrader<'info> {
+    /// Adds a trader to the pool's allowlist
+    /// Entries can be created before the pool is permissioned and only take
+    /// effect once it is
+    ///
+    /// # Arguments
+    /// * `user` - Trader to admit
+    /// * `bumps` - Canonical bump values for PDAs (provided by Anchor)
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn allow_trader(&mut self, user: Pubkey, bumps: &AllowTraderBumps) -> Result<()> {
+        self.config.check_authority(self.authority.key)?;
+
+        self.allowlist_entry.set_inner(AllowlistEntry {
+            config: self.config.key(),
+            user,
+            bump: bumps.allowlist_entry,
+        });
+
+        emit!(AllowlistUpdated {
+            seed: self.config.seed,
+            user,
+            allowed: true,
+        });
+        Ok(())
+    }
+}
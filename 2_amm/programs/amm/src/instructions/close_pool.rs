@@ -0,0 +1,264 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        burn, close_account, transfer_checked, Burn, CloseAccount, Mint, TokenAccount,
+        TokenInterface, TransferChecked,
+    },
+};
+
+use crate::errors::AmmError;
+use crate::events::PoolClosed;
+use crate::states::{Config, Oracle, PoolIndex};
+
+/// Accounts required for closing an empty pool and returning its rent to the creator
+/// The LP mint stays: SPL Token mints can't be closed
+#[derive(Accounts)]
+pub struct ClosePool<'info> {
+    /// Whoever closes the pool (must sign the transaction)
+    /// Must be the authority, unless the pool has none
+    pub signer: Signer<'info>,
+
+    /// The account that created the pool, receiving the rent of every closed account
+    /// Mutable because its lamport balance increases
+    #[account(mut)]
+    pub creator: SystemAccount<'info>,
+
+    /// The mint account for token X in the trading pair
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The mint account for token Y in the trading pair
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The AMM pool configuration account, closed at the end of the instruction
+    /// Uses PDA derived from "config" seed and config.seed
+    #[account(
+        mut,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        has_one = mint_x,   // Ensures mint_x matches the one in config
+        has_one = mint_y,   // Ensures mint_y matches the one in config
+        has_one = creator,  // Rent only goes back to whoever paid it
+        close = creator,
+    )]
+    pub config: Account<'info, Config>,
+
+    /// The LP token mint of the pool
+    /// Mutable because the locked LP tokens are burned
+    /// Uses PDA derived from "lp" seed and config pubkey
+    #[account(
+        mut,
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump,
+        mint::token_program = token_program,
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+
+    /// Token account holding the LP tokens locked by the first deposit
+    /// Absent if the pool never had a deposit
+    #[account(
+        mut,
+        seeds = [b"locked_lp", config.key().as_ref()],
+        bump,
+        token::mint = mint_lp,
+        token::authority = config,
+        token::token_program = token_program,
+    )]
+    pub locked_lp: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// The vault that holds all deposited token X, closed by this instruction
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x,
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The vault that holds all deposited token Y, closed by this instruction
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y,
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Creator's token X account, receiving the reserves left behind the locked LP tokens
+    #[account(
+        mut,
+        token::mint = mint_x,
+        token::authority = creator,
+        token::token_program = token_program_x,
+    )]
+    pub creator_ata_x: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Creator's token Y account, receiving the reserves left behind the locked LP tokens
+    #[account(
+        mut,
+        token::mint = mint_y,
+        token::authority = creator,
+        token::token_program = token_program_y,
+    )]
+    pub creator_ata_y: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// TWAP price oracle of the pool, closed along with it
+    /// Required if the pool has one (`config.has_oracle`)
+    /// Uses PDA derived from "oracle" seed and config pubkey
+    #[account(
+        mut,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.bump,
+        close = creator,
+    )]
+    pub oracle: Option<Box<Account<'info, Oracle>>>,
+
+    /// Factory entry of a canonical pool, closed so the pair and fee tier can be reused
+    /// Required for canonical pools (`config.canonical`)
+    #[account(
+        mut,
+        has_one = config,
+        close = creator,
+    )]
+    pub pool_index: Option<Box<Account<'info, PoolIndex>>>,
+
+    /// Token program of the LP mint
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Token program of token X, SPL Token or Token-2022
+    pub token_program_x: Interface<'info, TokenInterface>,
+    /// Token program of token Y, SPL Token or Token-2022
+    pub token_program_y: Interface<'info, TokenInterface>,
+    /// Associated Token program for ATA validation
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> ClosePool<'info> {
+    /// Closes a pool nobody has liquidity in anymore
+    ///
+    /// Every LP token but the ones locked by the first deposit must have been
+    /// burned and protocol fees collected. The locked LP tokens belong to nobody,
+    /// so they are burned here and the few reserves backing them go to the
+    /// creator, leaving the vaults empty before they are closed.
+    ///
+    /// Token-2022 refuses to close an account holding withheld transfer fees,
+    /// so vaults of transfer-fee mints must be harvested to the mint first
+    /// (`HarvestWithheldTokensToMint` is permissionless), or the close fails.
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn close_pool(&mut self) -> Result<()> {
+        // Anyone may close a pool without authority, it can't be managed anyway
+        if self.config.authority.is_some() {
+            self.config.check_authority(self.signer.key)?;
+        }
+        // Migrated legacy pools without authority have nobody to refund
+        require!(self.config.creator != Pubkey::default(), AmmError::UnknownCreator);
+        require!(!self.config.flash_loan_active, AmmError::FlashLoanActive);
+        require!(
+            self.config.protocol_fees_x == 0 && self.config.protocol_fees_y == 0,
+            AmmError::PoolNotEmpty
+        );
+        // Leaving the oracle or the index behind would strand their rent and,
+        // for the index, keep pointing routers at a closed pool
+        require!(
+            (self.oracle.is_some() || !self.config.has_oracle)
+                && (self.pool_index.is_some() || !self.config.canonical),
+            AmmError::MissingPoolAccount
+        );
+
+        let locked = self.locked_lp.as_ref().map_or(0, |locked_lp| locked_lp.amount);
+        require!(self.mint_lp.supply == locked, AmmError::PoolNotEmpty);
+
+        let seed = self.config.seed.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[b"config", &seed, &[self.config.config_bump]]];
+
+        if let Some(locked_lp) = &self.locked_lp {
+            if locked > 0 {
+                let cpi_accounts = Burn {
+                    mint: self.mint_lp.to_account_info(),
+                    from: locked_lp.to_account_info(),
+                    authority: self.config.to_account_info(),  // Config PDA owns the locked LP
+                };
+                let cpi_context = CpiContext::new_with_signer(
+                    self.token_program.to_account_info(),
+                    cpi_accounts,
+                    signer_seeds,
+                );
+                burn(cpi_context, locked)?;
+            }
+            self.close_token_account(
+                locked_lp.to_account_info(),
+                self.token_program.to_account_info(),
+                signer_seeds,
+            )?;
+        }
+
+        for is_x in [true, false] {
+            let (amount, vault, destination, mint, decimals, token_program) = match is_x {
+                true => (
+                    self.vault_x.amount,
+                    self.vault_x.to_account_info(),
+                    self.creator_ata_x.to_account_info(),
+                    self.mint_x.to_account_info(),
+                    self.mint_x.decimals,
+                    self.token_program_x.to_account_info(),
+                ),
+                false => (
+                    self.vault_y.amount,
+                    self.vault_y.to_account_info(),
+                    self.creator_ata_y.to_account_info(),
+                    self.mint_y.to_account_info(),
+                    self.mint_y.decimals,
+                    self.token_program_y.to_account_info(),
+                ),
+            };
+            if amount > 0 {
+                let cpi_accounts = TransferChecked {
+                    from: vault.clone(),
+                    to: destination,
+                    mint,
+                    authority: self.config.to_account_info(),  // Config PDA signs the transfer
+                };
+                let cpi_context = CpiContext::new_with_signer(
+                    token_program.clone(),
+                    cpi_accounts,
+                    signer_seeds,
+                );
+                transfer_checked(cpi_context, amount, decimals)?;
+            }
+            self.close_token_account(vault, token_program, signer_seeds)?;
+        }
+
+        emit!(PoolClosed {
+            seed: self.config.seed,
+            config: self.config.key(),
+            creator: self.creator.key(),
+        });
+        Ok(())
+    }
+
+    /// Closes an empty token account owned by the config PDA, refunding the creator
+    ///
+    /// # Arguments
+    /// * `account` - Token account to close
+    /// * `token_program` - Token program owning the account
+    /// * `signer_seeds` - Seeds of the config PDA
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    fn close_token_account(
+        &self,
+        account: AccountInfo<'info>,
+        token_program: AccountInfo<'info>,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        let cpi_accounts = CloseAccount {
+            account,
+            destination: self.creator.to_account_info(),
+            authority: self.config.to_account_info(),  // Config PDA owns the account
+        };
+        close_account(CpiContext::new_with_signer(token_program, cpi_accounts, signer_seeds))
+    }
+}
@@ -0,0 +1,161 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::errors::AmmError;
+use crate::events::ProtocolFeesCollected;
+use crate::states::Config;
+
+/// Accounts required for collecting the protocol share of swap fees
+/// Moves accrued protocol fees out of the vaults into treasury accounts chosen by the authority
+#[derive(Accounts)]
+pub struct CollectProtocolFees<'info> {
+    /// The update authority of the pool (must sign the transaction)
+    pub authority: Signer<'info>,
+
+    /// The mint account for token X in the trading pair
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The mint account for token Y in the trading pair
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The AMM pool configuration account
+    /// Mutable because the accrued protocol fees are reset on collection
+    /// Uses PDA derived from "config" seed and config.seed
+    #[account(
+        mut,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        has_one = mint_x,  // Ensures mint_x matches the one in config
+        has_one = mint_y,  // Ensures mint_y matches the one in config
+    )]
+    pub config: Account<'info, Config>,
+
+    /// The vault that holds all deposited token X (and accrued X fees)
+    /// Mutable because protocol fees are transferred out of it
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x,
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The vault that holds all deposited token Y (and accrued Y fees)
+    /// Mutable because protocol fees are transferred out of it
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y,
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Treasury token account receiving the X protocol fees
+    #[account(
+        mut,
+        token::mint = mint_x,
+        token::token_program = token_program_x,
+    )]
+    pub treasury_x: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Treasury token account receiving the Y protocol fees
+    #[account(
+        mut,
+        token::mint = mint_y,
+        token::token_program = token_program_y,
+    )]
+    pub treasury_y: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Token program of token X, SPL Token or Token-2022
+    pub token_program_x: Interface<'info, TokenInterface>,
+    /// Token program of token Y, SPL Token or Token-2022
+    pub token_program_y: Interface<'info, TokenInterface>,
+    /// Associated Token program for ATA validation
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> CollectProtocolFees<'info> {
+    /// Transfers all accrued protocol fees to the treasury accounts
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn collect_protocol_fees(&mut self) -> Result<()> {
+        self.config.check_authority(self.authority.key)?;
+        // The vaults may be short of the accrued fees until the flash loan is repaid
+        require!(!self.config.flash_loan_active, AmmError::FlashLoanActive);
+
+        let (fees_x, fees_y) = (self.config.protocol_fees_x, self.config.protocol_fees_y);
+
+        // Reset accrued fees before moving the tokens out
+        self.config.protocol_fees_x = 0;
+        self.config.protocol_fees_y = 0;
+
+        if fees_x > 0 {
+            self.transfer_fees(true, fees_x)?;
+        }
+        if fees_y > 0 {
+            self.transfer_fees(false, fees_y)?;
+        }
+
+        emit!(ProtocolFeesCollected {
+            seed: self.config.seed,
+            amount_x: fees_x,
+            amount_y: fees_y,
+        });
+        Ok(())
+    }
+
+    /// Transfers fees from a vault to the matching treasury account
+    ///
+    /// # Arguments
+    /// * `is_x` - true for token X, false for token Y
+    /// * `amount` - Amount of tokens to transfer
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn transfer_fees(&mut self, is_x: bool, amount: u64) -> Result<()> {
+        // Select appropriate accounts based on token type
+        let (from, to, mint, decimals, cpi_program) = match is_x {
+            true => (
+                self.vault_x.to_account_info(),       // Transfer from vault X
+                self.treasury_x.to_account_info(),    // Transfer to X treasury
+                self.mint_x.to_account_info(),        // Token X mint
+                self.mint_x.decimals,                 // Token X decimals
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                self.vault_y.to_account_info(),       // Transfer from vault Y
+                self.treasury_y.to_account_info(),    // Transfer to Y treasury
+                self.mint_y.to_account_info(),        // Token Y mint
+                self.mint_y.decimals,                 // Token Y decimals
+                self.token_program_y.to_account_info(),
+            ),
+        };
+
+        // Set up transfer instruction accounts
+        let cpi_accounts = TransferChecked {
+            from,
+            to,
+            mint,
+            authority: self.config.to_account_info(),  // Config PDA signs the transfer
+        };
+
+        // Create signer seeds for config PDA
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"config",
+            &self.config.seed.to_le_bytes(),
+            &[self.config.config_bump],
+        ]];
+
+        // Create CPI context with PDA signer
+        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        // Execute the transfer with amount and decimal validation
+        transfer_checked(cpi_context, amount, decimals)
+    }
+}
@@ -0,0 +1,93 @@
+use anchor_lang::prelude::*;
+
+use crate::curves::CurveType;
+use crate::errors::AmmError;
+use crate::instructions::*;
+use crate::states::{Factory, PoolIndex};
+
+/// Accounts required for creating the canonical pool of a mint pair and fee tier
+/// Same pool accounts as `initialize`, plus the factory and the pair's index
+#[derive(Accounts)]
+#[instruction(seed: u64, fee: u16)]
+pub struct CreatePool<'info> {
+    /// The pool being created, with its config, LP mint and vaults
+    pub pool: Initialize<'info>,
+
+    /// The factory counting canonical pools
+    /// Uses PDA derived from "factory" seed
+    #[account(
+        mut,
+        seeds = [b"factory"],
+        bump = factory.bump,
+    )]
+    pub factory: Account<'info, Factory>,
+
+    /// Index pointing the pair and fee tier to the new pool
+    /// Uses PDA derived from "pool", both mints in ascending order and the fee,
+    /// so creating a second pool for the same pair and fee fails
+    #[account(
+        init,
+        payer = pool.admin,
+        seeds = [
+            b"pool",
+            pool.mint_x.key().as_ref(),
+            pool.mint_y.key().as_ref(),
+            fee.to_le_bytes().as_ref(),
+        ],
+        bump,
+        space = 8 + PoolIndex::INIT_SPACE,
+    )]
+    pub pool_index: Account<'info, PoolIndex>,
+
+    /// System program for account creation
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CreatePool<'info> {
+    /// Creates a pool and registers it as the canonical one of its pair and fee tier
+    ///
+    /// # Arguments
+    /// * `seed` - Unique identifier for the pool's config PDA
+    /// * `fee` - Trading fee in basis points, the pool's fee tier
+    /// * `authority` - Must be None, canonical pools are immutable
+    /// * `curve_type` - Pricing function of the pool, can't be changed later
+    /// * `amp` - StableSwap amplification coefficient (ignored for constant product pools)
+    /// * `bumps` - Canonical bump values for PDAs (provided by Anchor)
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn create_pool(
+        &mut self,
+        seed: u64,
+        fee: u16,
+        authority: Option<Pubkey>,
+        curve_type: CurveType,
+        amp: u64,
+        bumps: &CreatePoolBumps,
+    ) -> Result<()> {
+        // One ordering per pair, so X/Y and Y/X map to the same index
+        require!(
+            self.pool.mint_x.key() < self.pool.mint_y.key(),
+            AmmError::MintsNotSorted
+        );
+        // Routers trust the index, so nobody may retune or lock the pool it points to
+        require!(authority.is_none(), AmmError::CanonicalPoolAuthority);
+
+        self.pool
+            .initialize(seed, fee, authority, curve_type, amp, &bumps.pool)?;
+        self.pool.config.canonical = true;
+
+        self.pool_index.set_inner(PoolIndex {
+            config: self.pool.config.key(),
+            seed,
+            bump: bumps.pool_index,
+        });
+        self.factory.pool_count = self
+            .factory
+            .pool_count
+            .checked_add(1)
+            .ok_or(AmmError::Overflow)?;
+
+        Ok(())
+    }
+}
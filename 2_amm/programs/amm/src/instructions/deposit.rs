@@ -1,12 +1,17 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions as sysvar_instructions;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{mint_to, transfer_checked, Mint, MintTo, Token, TokenAccount, TransferChecked},
+    token_interface::{mint_to, transfer_checked, Mint, MintTo, TokenAccount, TokenInterface, TransferChecked},
 };
-use constant_product_curve::ConstantProduct;
 
-use crate::states::Config;
+use crate::constants::MINIMUM_LIQUIDITY;
+use crate::states::{AllowlistEntry, Config, Oracle};
 use crate::errors::AmmError;
+use crate::events::LiquidityAdded;
+use crate::native_sol::{native_sides, unwrap_sol, wrap_sol};
+use crate::permissions::check_trader;
+use crate::transfer_fee::{amount_received, amount_to_send};
 
 /// Accounts required for depositing liquidity into the AMM pool
 /// This struct defines all the accounts needed to perform a liquidity deposit operation
@@ -18,11 +23,11 @@ pub struct Deposit<'info> {
 
     /// The mint account for token X in the trading pair
     /// This is immutable as we only need to read mint information
-    pub mint_x: Account<'info, Mint>,
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
 
     /// The mint account for token Y in the trading pair
     /// This is immutable as we only need to read mint information
-    pub mint_y: Account<'info, Mint>,
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
 
     /// The AMM pool configuration account
     /// Contains pool settings, fees, and references to the token mints
@@ -35,6 +40,16 @@ pub struct Deposit<'info> {
     )]
     pub config: Account<'info, Config>,
 
+    /// TWAP price oracle of the pool
+    /// Mutable because every trade updates its price accumulators
+    /// Uses PDA derived from "oracle" seed and config pubkey
+    #[account(
+        mut,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.bump,
+    )]
+    pub oracle: Box<Account<'info, Oracle>>,
+
     /// The LP (Liquidity Provider) token mint
     /// Mutable because we need to mint new LP tokens to the user
     /// Uses PDA derived from "lp" seed and config pubkey
@@ -43,7 +58,7 @@ pub struct Deposit<'info> {
         seeds = [b"lp", config.key().as_ref()],
         bump = config.lp_bump
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
 
     /// The vault that holds all deposited token X
     /// Mutable because we're depositing tokens into it
@@ -52,9 +67,9 @@ pub struct Deposit<'info> {
         mut,
         associated_token::mint = mint_x,
         associated_token::authority = config,
-        associated_token::token_program = token_program
+        associated_token::token_program = token_program_x
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
 
     /// The vault that holds all deposited token Y
     /// Mutable because we're depositing tokens into it
@@ -63,29 +78,48 @@ pub struct Deposit<'info> {
         mut,
         associated_token::mint = mint_y,
         associated_token::authority = config,
-        associated_token::token_program = token_program
+        associated_token::token_program = token_program_y
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
     
     /// User's token account for token X
+    /// Will be created if it doesn't exist (the temporary wrapped SOL account
+    /// when trading native SOL), user pays for creation
     /// Mutable because we're transferring tokens from it
     #[account(
-        mut,
+        init_if_needed,
+        payer = user,
         associated_token::mint = mint_x,
         associated_token::authority = user,
-        associated_token::token_program = token_program
+        associated_token::token_program = token_program_x
     )]
-    pub user_ata_x: Account<'info, TokenAccount>,
+    pub user_ata_x: Box<InterfaceAccount<'info, TokenAccount>>,
 
     /// User's token account for token Y
+    /// Will be created if it doesn't exist (the temporary wrapped SOL account
+    /// when trading native SOL), user pays for creation
     /// Mutable because we're transferring tokens from it
     #[account(
-        mut,
+        init_if_needed,
+        payer = user,
         associated_token::mint = mint_y,
         associated_token::authority = user,
-        associated_token::token_program = token_program
+        associated_token::token_program = token_program_y
+    )]
+    pub user_ata_y: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The user's allowlist entry, only needed in permissioned pools
+    /// Uses PDA derived from "allowlist" seed, config and user pubkeys
+    #[account(
+        seeds = [b"allowlist", config.key().as_ref(), user.key().as_ref()],
+        bump = allowlist_entry.bump,
     )]
-    pub user_ata_y: Account<'info, TokenAccount>,
+    pub allowlist_entry: Option<Box<Account<'info, AllowlistEntry>>>,
+
+    /// Instructions sysvar, only needed to present a gatekeeper attestation
+    /// CHECK: address is checked against the sysvar id
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
 
     /// User's token account for LP tokens
     /// Will be created if it doesn't exist, user pays for creation
@@ -97,10 +131,28 @@ pub struct Deposit<'info> {
         associated_token::authority = user,
         associated_token::token_program = token_program
     )]
-    pub user_ata_lp: Account<'info, TokenAccount>,
+    pub user_ata_lp: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Token account holding the LP tokens locked forever by the first deposit
+    /// Owned by the config PDA, which never moves tokens out of it
+    /// Created on the first deposit, the user pays for creation
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"locked_lp", config.key().as_ref()],
+        bump,
+        token::mint = mint_lp,
+        token::authority = config,
+        token::token_program = token_program,
+    )]
+    pub locked_lp: Box<InterfaceAccount<'info, TokenAccount>>,
 
-    /// SPL Token program for token operations
-    pub token_program: Program<'info, Token>,
+    /// Token program of the LP mint
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Token program of token X, SPL Token or Token-2022
+    pub token_program_x: Interface<'info, TokenInterface>,
+    /// Token program of token Y, SPL Token or Token-2022
+    pub token_program_y: Interface<'info, TokenInterface>,
     /// Associated Token program for ATA operations
     pub associated_token_program: Program<'info, AssociatedToken>,
     /// System program for account creation
@@ -113,15 +165,26 @@ impl<'info> Deposit<'info> {
     /// For the first deposit (pool initialization):
     /// - Uses exact amounts provided by user (max_x, max_y)
     /// - Establishes the initial price ratio
+    /// - Locks `MINIMUM_LIQUIDITY` extra LP tokens forever, so the LP price can't
+    ///   be inflated from a dust-sized supply to steal from later depositors
     /// 
     /// For subsequent deposits:
     /// - Calculates proportional amounts based on current pool ratio
     /// - Maintains constant product invariant
     /// 
+    /// Reserves only ever count what the vaults receive: for Token-2022 mints with
+    /// a transfer fee, the user sends the required amounts plus the fee withheld.
+    /// 
+    /// In native SOL mode, the SOL deposited is wrapped into the user's wrapped
+    /// SOL account first, and that account is closed at the end so anything
+    /// left in it goes back to the user as lamports.
+    /// 
     /// # Arguments
     /// * `amount` - Amount of LP tokens to mint to the user
-    /// * `max_x` - Maximum amount of token X user is willing to deposit
-    /// * `max_y` - Maximum amount of token Y user is willing to deposit
+    /// * `max_x` - Maximum amount of token X user is willing to deposit, transfer fee included
+    /// * `max_y` - Maximum amount of token Y user is willing to deposit, transfer fee included
+    /// * `expiration` - Unix timestamp after which the deposit is rejected
+    /// * `native_sol` - Pay native SOL for the wrapped SOL side
     /// 
     /// # Returns
     /// * `Result<()>` - Ok if successful, error otherwise
@@ -130,40 +193,103 @@ impl<'info> Deposit<'info> {
         amount: u64, 
         max_x: u64,
         max_y: u64,
+        expiration: i64,
+        native_sol: bool,
     ) -> Result<()> { 
+        // Reject transactions that sat around long enough for the price to go stale
+        let now = Clock::get()?.unix_timestamp;
+        require!(now <= expiration, AmmError::OfferExpired);
         // Ensure the pool is not locked for deposits
         require!(self.config.locked == false, AmmError::PoolLocked);
+        // Vault balances are borrowed out while a flash loan is in progress
+        require!(!self.config.flash_loan_active, AmmError::FlashLoanActive);
+        // Permissioned pools only take liquidity from admitted users
+        check_trader(
+            &self.config,
+            self.user.key,
+            self.allowlist_entry.as_deref().map(|entry| &**entry),
+            self.instructions.as_deref(),
+        )?;
         // Ensure user is requesting to mint some LP tokens
         require!(amount != 0, AmmError::InvalidAmount);
 
+        // Pool reserves exclude protocol fees waiting in the vaults
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+
+        let mint_x = self.mint_x.to_account_info();
+        let mint_y = self.mint_y.to_account_info();
+
         // Calculate required token amounts based on whether this is first deposit
-        let (x, y) = match self.mint_lp.supply == 0 && self.vault_x.amount == 0 && self.vault_y.amount == 0 {
+        // (x, y) reach the vaults, (send_x, send_y) leave the user, transfer fees included
+        // Tokens sent to the vaults before the first deposit don't block it, they stay in the pool
+        let is_first_deposit = self.mint_lp.supply == 0;
+        let ((send_x, send_y), (x, y)) = match is_first_deposit {
             // First deposit: use exact amounts provided by user
             // This establishes the initial price ratio for the pool
-            true => (max_x, max_y), 
+            true => (
+                (max_x, max_y),
+                (amount_received(&mint_x, max_x)?, amount_received(&mint_y, max_y)?),
+            ),
             // Subsequent deposits: calculate proportional amounts to maintain pool ratio
             false => {
-                let amounts = ConstantProduct::xy_deposit_amounts_from_l(
-                    self.vault_x.amount, 
-                    self.vault_y.amount, 
-                    self.mint_lp.supply, 
-                    amount, 
-                    6  // Precision for calculations
-                ).unwrap();
-                (amounts.x, amounts.y)
+                let (x, y) = self.config.curve(now).deposit_amounts(
+                    reserve_x,
+                    reserve_y,
+                    self.mint_lp.supply,
+                    amount,
+                )?;
+                ((amount_to_send(&mint_x, x)?, amount_to_send(&mint_y, y)?), (x, y))
             }
         };
 
         // Slippage protection: ensure calculated amounts don't exceed user's maximum
-        require!(x <= max_x && y <= max_y, AmmError::SlippageExceeded );
+        require!(send_x <= max_x && send_y <= max_y, AmmError::SlippageExceeded );
+
+        // Wrap the SOL paid in, in native SOL mode
+        let (native_x, native_y) = native_sides(native_sol, &self.user_ata_x, &self.user_ata_y)?;
+        if native_x {
+            self.wrap_sol(true, send_x)?;
+        }
+        if native_y {
+            self.wrap_sol(false, send_y)?;
+        }
 
         // Transfer token X from user to vault
-        self.deposit_tokens(true, x)?;
+        self.deposit_tokens(true, send_x)?;
         // Transfer token Y from user to vault
-        self.deposit_tokens(false, y)?;
+        self.deposit_tokens(false, send_y)?;
+
+        if is_first_deposit {
+            // Reject dust pools, whose few LP tokens would each be worth a lot
+            require!(
+                amount >= MINIMUM_LIQUIDITY && x >= MINIMUM_LIQUIDITY && y >= MINIMUM_LIQUIDITY,
+                AmmError::LiquidityLessThanMinimum
+            );
+            // Lock the minimum liquidity so the supply never goes back to zero
+            self.mint_lp_tokens(self.locked_lp.to_account_info(), MINIMUM_LIQUIDITY)?;
+        }
 
         // Mint LP tokens to user as proof of liquidity provision
-        self.mint_lp_tokens(amount)
+        self.mint_lp_tokens(self.user_ata_lp.to_account_info(), amount)?;
+
+        // Hand any wrapped SOL left back as lamports
+        self.unwrap_sol(native_x, native_y)?;
+
+        // Record the post-deposit reserves in the TWAP oracle
+        self.oracle.update(reserve_x + x, reserve_y + y, now)?;
+
+        emit!(LiquidityAdded {
+            seed: self.config.seed,
+            user: self.user.key(),
+            amount_x: x,
+            amount_y: y,
+            liquidity: amount,
+            reserve_x: reserve_x + x,
+            reserve_y: reserve_y + y,
+        });
+        Ok(())
     }
 
     /// Transfers tokens from user's account to the appropriate vault
@@ -177,27 +303,28 @@ impl<'info> Deposit<'info> {
     pub fn deposit_tokens(&mut self, is_x: bool, amount: u64) -> Result<()> {
         // Select appropriate accounts based on token type
         let (
-            from,      // User's token account
-            to,        // Vault token account
-            mint,      // Token mint
-            decimals   // Token decimal places
+            from,        // User's token account
+            to,          // Vault token account
+            mint,        // Token mint
+            decimals,    // Token decimal places
+            cpi_program  // Token program of the mint
         ) = match is_x {
             true => (
                 self.user_ata_x.to_account_info(), 
                 self.vault_x.to_account_info(),
                 self.mint_x.to_account_info(),
-                self.mint_x.decimals
+                self.mint_x.decimals,
+                self.token_program_x.to_account_info(),
             ),
             false => (
                 self.user_ata_y.to_account_info(), 
                 self.vault_y.to_account_info(),
                 self.mint_y.to_account_info(),
-                self.mint_y.decimals
+                self.mint_y.decimals,
+                self.token_program_y.to_account_info(),
             ),
         };
 
-        let cpi_program = self.token_program.to_account_info();
-
         // Set up transfer instruction accounts
         let cpi_accounts = TransferChecked {
             from,
@@ -212,20 +339,21 @@ impl<'info> Deposit<'info> {
         transfer_checked(cpi_context, amount, decimals)
     }
 
-    /// Mints LP tokens to the user as receipt for their liquidity provision
+    /// Mints LP tokens as receipt for liquidity provision
     /// 
     /// # Arguments
+    /// * `to` - Token account receiving the LP tokens
     /// * `amount` - Amount of LP tokens to mint
     /// 
     /// # Returns
     /// * `Result<()>` - Ok if successful, error otherwise
-    pub fn mint_lp_tokens(&mut self, amount: u64) -> Result<()> {
+    pub fn mint_lp_tokens(&mut self, to: AccountInfo<'info>, amount: u64) -> Result<()> {
         let cpi_program = self.token_program.to_account_info();
 
         // Set up mint instruction accounts
         let cpi_accounts = MintTo {
             mint: self.mint_lp.to_account_info(),
-            to: self.user_ata_lp.to_account_info(),
+            to,
             authority: self.config.to_account_info(),  // Config PDA is mint authority
         };
 
@@ -244,4 +372,52 @@ impl<'info> Deposit<'info> {
         // Mint the LP tokens to user
         mint_to(cpi_context, amount)
     }
-}
\ No newline at end of file
+
+    /// Wraps native SOL of the user into their wrapped SOL account
+    ///
+    /// # Arguments
+    /// * `is_x` - true if token X is wrapped SOL, false if token Y is
+    /// * `amount` - Lamports to wrap
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    fn wrap_sol(&self, is_x: bool, amount: u64) -> Result<()> {
+        let (user_ata, token_program) = match is_x {
+            true => (self.user_ata_x.to_account_info(), self.token_program_x.to_account_info()),
+            false => (self.user_ata_y.to_account_info(), self.token_program_y.to_account_info()),
+        };
+        wrap_sol(
+            self.user.to_account_info(),
+            user_ata,
+            self.system_program.to_account_info(),
+            token_program,
+            amount,
+        )
+    }
+
+    /// Closes the user's wrapped SOL accounts, returning their balance and rent as lamports
+    ///
+    /// # Arguments
+    /// * `native_x` - Whether token X is traded in native SOL
+    /// * `native_y` - Whether token Y is traded in native SOL
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    fn unwrap_sol(&self, native_x: bool, native_y: bool) -> Result<()> {
+        if native_x {
+            unwrap_sol(
+                self.user.to_account_info(),
+                self.user_ata_x.to_account_info(),
+                self.token_program_x.to_account_info(),
+            )?;
+        }
+        if native_y {
+            unwrap_sol(
+                self.user.to_account_info(),
+                self.user_ata_y.to_account_info(),
+                self.token_program_y.to_account_info(),
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,294 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions::{
+    self as sysvar_instructions, get_instruction_relative, load_current_index_checked,
+    load_instruction_at_checked,
+};
+use anchor_lang::Discriminator;
+use anchor_spl::token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+use crate::errors::AmmError;
+use crate::events::FlashLoanRepaid;
+use crate::instruction::FlashRepay;
+use crate::math::flash_loan_fee;
+use crate::states::{Config, Oracle};
+use crate::transfer_fee::amount_to_send;
+
+/// Position of `config` in the `FlashLoan` accounts, used to match the repayment
+const CONFIG_ACCOUNT_INDEX: usize = 3;
+
+/// Accounts required for borrowing from and repaying to the pool vaults
+/// Used by both `flash_borrow` and `flash_repay`
+#[derive(Accounts)]
+pub struct FlashLoan<'info> {
+    /// The user who is borrowing or repaying (must sign the transaction)
+    pub user: Signer<'info>,
+
+    /// The mint account for token X in the trading pair
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The mint account for token Y in the trading pair
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The AMM pool configuration account
+    /// Mutable because it tracks the outstanding loan
+    /// Uses PDA derived from "config" seed and config.seed
+    #[account(
+        mut,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        has_one = mint_x,  // Ensures mint_x matches the one in config
+        has_one = mint_y,  // Ensures mint_y matches the one in config
+    )]
+    pub config: Account<'info, Config>,
+
+    /// TWAP price oracle of the pool
+    /// Mutable because a loan checkpoints the price before the vaults are lent out
+    /// Uses PDA derived from "oracle" seed and config pubkey
+    #[account(
+        mut,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.bump,
+    )]
+    pub oracle: Box<Account<'info, Oracle>>,
+
+    /// The vault that holds all deposited token X
+    /// Associated token account owned by the config PDA
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x,
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The vault that holds all deposited token Y
+    /// Associated token account owned by the config PDA
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y,
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// User's token account for token X
+    /// Receives borrowed X, or pays back an X loan
+    #[account(
+        mut,
+        token::mint = mint_x,
+        token::authority = user,
+        token::token_program = token_program_x,
+    )]
+    pub user_ata_x: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// User's token account for token Y
+    /// Receives borrowed Y, or pays back a Y loan
+    #[account(
+        mut,
+        token::mint = mint_y,
+        token::authority = user,
+        token::token_program = token_program_y,
+    )]
+    pub user_ata_y: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Instructions sysvar, used to check the loan is repaid later in the transaction
+    /// CHECK: address is checked against the sysvar id
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// Token program of token X, SPL Token or Token-2022
+    pub token_program_x: Interface<'info, TokenInterface>,
+    /// Token program of token Y, SPL Token or Token-2022
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FlashLoan<'info> {
+    /// Lends tokens from a vault until the end of the transaction
+    ///
+    /// The transaction must contain a later top-level `flash_repay` for this pool,
+    /// which returns the amount plus `FLASH_LOAN_FEE_BPS`. The fee stays in the
+    /// vault and grows the reserves, so it goes to LPs. Until the loan is repaid
+    /// the pool rejects swaps, deposits and withdrawals, so the borrowed funds
+    /// can't be used against the pool's own (temporarily skewed) reserves.
+    ///
+    /// # Arguments
+    /// * `is_x` - true to borrow token X, false to borrow token Y
+    /// * `amount` - Amount of tokens to borrow
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn flash_borrow(&mut self, is_x: bool, amount: u64) -> Result<()> {
+        // Ensure the pool is not locked
+        require!(!self.config.locked, AmmError::PoolLocked);
+        // One loan at a time per pool
+        require!(!self.config.flash_loan_active, AmmError::FlashLoanActive);
+        // Ensure user is borrowing a positive amount
+        require!(amount > 0, AmmError::InvalidAmount);
+
+        // Only LP liquidity can be lent, uncollected protocol fees stay in the vaults
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+        let reserve = if is_x { reserve_x } else { reserve_y };
+        require!(amount <= reserve, AmmError::InsufficientBalance);
+
+        self.check_repayment()?;
+
+        // Close the oracle's open interval at the real reserves, before they're lent out
+        self.oracle.update(reserve_x, reserve_y, Clock::get()?.unix_timestamp)?;
+
+        let repayment = amount
+            .checked_add(flash_loan_fee(amount)?)
+            .ok_or(AmmError::Overflow)?;
+        self.config.flash_loan_active = true;
+        self.config.flash_loan_is_x = is_x;
+        self.config.flash_loan_repayment = repayment;
+
+        self.withdraw_token(is_x, amount)
+    }
+
+    /// Repays the outstanding flash loan of the pool, fee included
+    ///
+    /// For mints with a transfer fee, the borrower pays that fee on both legs:
+    /// the vault must get back the full repayment.
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn flash_repay(&mut self) -> Result<()> {
+        require!(self.config.flash_loan_active, AmmError::NoFlashLoan);
+
+        let (is_x, repayment) = (self.config.flash_loan_is_x, self.config.flash_loan_repayment);
+        self.config.flash_loan_active = false;
+        self.config.flash_loan_repayment = 0;
+
+        // The vault must receive the whole repayment, any transfer fee comes on top
+        let mint = match is_x {
+            true => self.mint_x.to_account_info(),
+            false => self.mint_y.to_account_info(),
+        };
+        let amount = amount_to_send(&mint, repayment)?;
+        self.deposit_token(is_x, amount)?;
+
+        emit!(FlashLoanRepaid {
+            seed: self.config.seed,
+            user: self.user.key(),
+            is_x,
+            repayment,
+        });
+        Ok(())
+    }
+
+    /// Checks the transaction repays this pool after the current instruction
+    ///
+    /// Both halves must be top-level instructions: through CPI the sysvar would
+    /// describe the caller's instruction, not this one.
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if a matching `flash_repay` follows, error otherwise
+    fn check_repayment(&self) -> Result<()> {
+        let instructions = self.instructions.to_account_info();
+
+        let current = get_instruction_relative(0, &instructions)?;
+        require_keys_eq!(current.program_id, crate::ID, AmmError::FlashLoanCpi);
+
+        let current_index = load_current_index_checked(&instructions)? as usize;
+        let repaid = (current_index + 1..)
+            .map_while(|index| load_instruction_at_checked(index, &instructions).ok())
+            .any(|instruction| {
+                instruction.program_id == crate::ID
+                    && instruction.data.starts_with(FlashRepay::DISCRIMINATOR)
+                    && instruction
+                        .accounts
+                        .get(CONFIG_ACCOUNT_INDEX)
+                        .is_some_and(|meta| meta.pubkey == self.config.key())
+            });
+        require!(repaid, AmmError::FlashLoanNotRepaid);
+
+        Ok(())
+    }
+
+    /// Transfers tokens from the user's account to the appropriate vault
+    ///
+    /// # Arguments
+    /// * `is_x` - true for token X, false for token Y
+    /// * `amount` - Amount of tokens to transfer
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    fn deposit_token(&mut self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, decimals, token_program) = match is_x {
+            true => (
+                self.user_ata_x.to_account_info(),
+                self.vault_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                self.user_ata_y.to_account_info(),
+                self.vault_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+                self.token_program_y.to_account_info(),
+            ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            to,
+            mint,
+            authority: self.user.to_account_info(),  // User signs the transfer
+        };
+        let cpi_context = CpiContext::new(token_program, cpi_accounts);
+
+        transfer_checked(cpi_context, amount, decimals)
+    }
+
+    /// Transfers tokens from the appropriate vault to the user's account
+    ///
+    /// # Arguments
+    /// * `is_x` - true for token X, false for token Y
+    /// * `amount` - Amount of tokens to transfer
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    fn withdraw_token(&mut self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, decimals, token_program) = match is_x {
+            true => (
+                self.vault_x.to_account_info(),
+                self.user_ata_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                self.vault_y.to_account_info(),
+                self.user_ata_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+                self.token_program_y.to_account_info(),
+            ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            to,
+            mint,
+            authority: self.config.to_account_info(),  // Config PDA signs the transfer
+        };
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"config",
+            &self.config.seed.to_le_bytes(),
+            &[self.config.config_bump],
+        ]];
+        let cpi_context = CpiContext::new_with_signer(
+            token_program,
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(cpi_context, amount, decimals)
+    }
+}
@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::AmmError;
+use crate::states::{Config, Oracle, Twap};
+
+/// Accounts required for reading the time-weighted average price of a pool
+/// Read-only, the result is returned to the caller as return data
+#[derive(Accounts)]
+pub struct GetTwap<'info> {
+    /// The AMM pool configuration account
+    /// Uses PDA derived from "config" seed and config.seed
+    #[account(
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    /// TWAP price oracle of the pool
+    /// Uses PDA derived from "oracle" seed and config pubkey
+    #[account(
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.bump,
+    )]
+    pub oracle: Box<Account<'info, Oracle>>,
+}
+
+impl<'info> GetTwap<'info> {
+    /// Computes the time-weighted average prices over at least the last `window` seconds
+    ///
+    /// # Arguments
+    /// * `window` - Minimum number of seconds to average over
+    ///
+    /// # Returns
+    /// * `Result<Twap>` - Q64.64 average prices of X in Y and Y in X, and the effective window
+    pub fn get_twap(&self, window: u32) -> Result<Twap> {
+        // Nothing may act on the pool's price while its vaults are lent out
+        require!(!self.config.flash_loan_active, AmmError::FlashLoanActive);
+
+        // The time since the last trade is priced with the reserves it recorded,
+        // so balances donated to the vaults since then don't count
+        self.oracle.twap(window, Clock::get()?.unix_timestamp)
+    }
+}
@@ -1,10 +1,15 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{Mint, Token, TokenAccount},
+    token_interface::{Mint, TokenAccount, TokenInterface},
 };
 
-use crate::states::Config;
+use crate::constants::{CONFIG_RESERVED_BYTES, CONFIG_VERSION, MAX_AMP, MAX_FEE_BPS, MIN_AMP};
+use crate::curves::CurveType;
+use crate::errors::AmmError;
+use crate::events::PoolInitialized;
+use crate::states::{Config, Oracle};
+use crate::transfer_fee::check_supported_mint;
 
 /// Accounts required for initializing a new AMM pool
 /// This struct defines all the accounts needed to create a new liquidity pool
@@ -19,11 +24,19 @@ pub struct Initialize<'info> {
 
     /// The mint account for the first token (X) in the trading pair
     /// This is immutable as we only need to read mint information for validation
-    pub mint_x: Account<'info, Mint>,
+    /// Owned by token_program_x, either SPL Token or Token-2022
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
 
     /// The mint account for the second token (Y) in the trading pair
     /// This is immutable as we only need to read mint information for validation
-    pub mint_y: Account<'info, Mint>,
+    /// Must differ from mint_x, a pool trading a token against itself makes no sense
+    /// Owned by token_program_y, which may differ from token_program_x
+    #[account(
+        mint::token_program = token_program_y,
+        constraint = mint_y.key() != mint_x.key() @ AmmError::IdenticalMints,
+    )]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
 
     /// The main configuration account for the AMM pool
     /// This PDA stores all pool settings, token references, and metadata
@@ -33,7 +46,7 @@ pub struct Initialize<'info> {
         payer = admin,                                          // Admin pays for creation
         seeds = [b"config", seed.to_le_bytes().as_ref()],      // PDA derivation
         bump,                                                   // Anchor finds canonical bump
-        space = Config::INIT_SPACE                              // Required space for Config struct
+        space = 8 + Config::INIT_SPACE                          // Discriminator + Config struct
     )]
     pub config: Account<'info, Config>,
 
@@ -47,8 +60,9 @@ pub struct Initialize<'info> {
         mint::authority = config.key(),        // Config PDA controls minting
         seeds = [b"lp", config.key().as_ref()], // PDA derived from config
         bump,                                  // Anchor finds canonical bump
+        mint::token_program = token_program,   // LP tokens live under token_program
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
 
     /// The vault that will hold all deposited token X
     /// This is an associated token account owned by the config PDA
@@ -58,9 +72,9 @@ pub struct Initialize<'info> {
         payer = admin,                                 // Admin pays for creation
         associated_token::mint = mint_x,               // Associated with mint_x
         associated_token::authority = config,          // Owned by config PDA
-        associated_token::token_program = token_program, // Uses SPL Token program
+        associated_token::token_program = token_program_x, // Same program as mint_x
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
 
     /// The vault that will hold all deposited token Y
     /// This is an associated token account owned by the config PDA
@@ -70,12 +84,27 @@ pub struct Initialize<'info> {
         payer = admin,                                 // Admin pays for creation
         associated_token::mint = mint_y,               // Associated with mint_y
         associated_token::authority = config,          // Owned by config PDA
-        associated_token::token_program = token_program, // Uses SPL Token program
+        associated_token::token_program = token_program_y, // Same program as mint_y
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The TWAP price oracle of the pool, required by every trading instruction
+    /// Uses PDA derived from "oracle" seed and config pubkey, one per pool
+    #[account(
+        init,                                   // Create new oracle
+        payer = admin,                         // Admin pays for creation
+        seeds = [b"oracle", config.key().as_ref()], // PDA derived from config
+        bump,                                  // Anchor finds canonical bump
+        space = 8 + Oracle::INIT_SPACE,        // Discriminator + Oracle struct
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub oracle: Box<Account<'info, Oracle>>,
 
-    /// SPL Token program for token operations
-    pub token_program: Program<'info, Token>,
+    /// Token program of the LP mint
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Token program of token X, SPL Token or Token-2022
+    pub token_program_x: Interface<'info, TokenInterface>,
+    /// Token program of token Y, SPL Token or Token-2022
+    pub token_program_y: Interface<'info, TokenInterface>,
     /// Associated Token program for ATA operations
     pub associated_token_program: Program<'info, AssociatedToken>,
     /// System program for account creation
@@ -90,21 +119,46 @@ impl<'info> Initialize<'info> {
     /// - Provide liquidity and earn fees
     /// - Remove liquidity by burning LP tokens
     /// 
-    /// The pool uses the constant product formula (x * y = k) to determine
-    /// exchange rates and maintain liquidity depth.
+    /// The pool uses either the constant product formula (x * y = k) or, for
+    /// correlated pairs, the StableSwap invariant to determine exchange rates
+    /// and maintain liquidity depth.
+    /// 
+    /// The pool's TWAP oracle is created along with it, since every trading
+    /// instruction updates it.
     /// 
     /// # Arguments
     /// * `seed` - Unique identifier for this pool, allows multiple pools with same token pairs
     /// * `fee` - Trading fee in basis points (e.g., 30 = 0.30%)
     /// * `authority` - Optional authority that can update pool settings (None = immutable)
+    /// * `curve_type` - Pricing function of the pool, can't be changed later
+    /// * `amp` - StableSwap amplification coefficient (ignored for constant product pools)
     /// * `bumps` - Canonical bump values for PDAs (provided by Anchor)
     pub fn initialize(
         &mut self, 
         seed: u64, 
         fee: u16, 
         authority: Option<Pubkey>, 
+        curve_type: CurveType,
+        amp: u64,
         bumps: &InitializeBumps
     ) -> Result<()> {
+        // A fee above 100% would make every swap fail
+        require!(fee <= MAX_FEE_BPS, AmmError::InvalidFee);
+
+        // Only StableSwap pools have an amplification coefficient
+        let amp = match curve_type {
+            CurveType::ConstantProduct => 0,
+            CurveType::StableSwap => {
+                require!((MIN_AMP..=MAX_AMP).contains(&amp), AmmError::InvalidAmp);
+                amp
+            }
+        };
+        // Reject mints with extensions that could lock or drain the vaults
+        check_supported_mint(&self.mint_x.to_account_info())?;
+        check_supported_mint(&self.mint_y.to_account_info())?;
+
+        let now = Clock::get()?.unix_timestamp;
+
         // Initialize the config account with all pool parameters
         self.config.set_inner(Config { 
             seed,                              // Unique pool identifier
@@ -114,7 +168,51 @@ impl<'info> Initialize<'info> {
             fee,                              // Trading fee in basis points
             locked: false,                    // Pool starts unlocked (active)
             config_bump: bumps.config,        // PDA bump for config account
-            lp_bump: bumps.mint_lp            // PDA bump for LP mint
+            lp_bump: bumps.mint_lp,           // PDA bump for LP mint
+            pending_authority: None,          // No authority transfer in progress
+            protocol_fee: 0,                  // Whole fee goes to LPs until enabled
+            protocol_fees_x: 0,               // Nothing accrued yet
+            protocol_fees_y: 0,
+            curve_type,                       // Pricing function of the pool
+            initial_amp: amp,                 // No amplification ramp in progress
+            target_amp: amp,
+            ramp_start: now,
+            ramp_stop: now,
+            flash_loan_active: false,         // No flash loan in progress
+            flash_loan_repayment: 0,
+            flash_loan_is_x: false,
+            dynamic_fee: false,               // Static fee until enabled
+            min_fee: fee,
+            max_fee: fee,
+            volatility: 0,                    // No price history yet
+            volatility_updated_at: now,
+            creator: self.admin.key(),        // Gets the rent back if the pool is closed
+            version: CONFIG_VERSION,          // Current account layout
+            permissioned: false,              // Open to every trader
+            gatekeeper: Pubkey::default(),
+            referral_fee: 0,                  // No referral share until enabled
+            max_price_impact: 0,              // No price band until enabled
+            max_slot_deviation: 0,
+            band_slot: 0,                     // Band reference set by the first swap
+            band_reserve_x: 0,
+            band_reserve_y: 0,
+            canonical: false,                 // Set by `create_pool` for factory pools
+            has_oracle: true,                 // Oracle created below
+            reserved: [0; CONFIG_RESERVED_BYTES],
+        });
+
+        // Start the TWAP oracle, trading instructions can't run without it
+        self.oracle.set_inner(Oracle::new(self.config.key(), bumps.oracle, now));
+
+        emit!(PoolInitialized {
+            seed,
+            config: self.config.key(),
+            mint_x: self.mint_x.key(),
+            mint_y: self.mint_y.key(),
+            fee,
+            curve_type,
+            amp,
+            authority,
         });
 
         // Pool is now ready for liquidity deposits and trading
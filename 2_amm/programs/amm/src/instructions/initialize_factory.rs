@@ -0,0 +1,45 @@
+use anchor_lang::prelude::*;
+
+use crate::states::Factory;
+
+/// Accounts required for creating the pool factory
+/// Permissionless, the factory only counts pools and has no settings
+#[derive(Accounts)]
+pub struct InitializeFactory<'info> {
+    /// Whoever creates the factory (must sign the transaction)
+    /// Mutable because they pay for the account creation
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The factory account, one per program
+    /// Uses PDA derived from "factory" seed
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"factory"],
+        bump,
+        space = 8 + Factory::INIT_SPACE,
+    )]
+    pub factory: Account<'info, Factory>,
+
+    /// System program for account creation
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeFactory<'info> {
+    /// Initializes an empty factory
+    ///
+    /// # Arguments
+    /// * `bumps` - Canonical bump values for PDAs (provided by Anchor)
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn initialize_factory(&mut self, bumps: &InitializeFactoryBumps) -> Result<()> {
+        self.factory.set_inner(Factory {
+            pool_count: 0,
+            bump: bumps.factory,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::events::FarmUpdated;
+use crate::states::{Config, Farm};
+use crate::transfer_fee::check_supported_mint;
+
+/// Accounts required for creating the liquidity mining farm of a pool
+/// Authority-gated, since the farm's reward mint can't be changed afterwards
+#[derive(Accounts)]
+pub struct InitializeFarm<'info> {
+    /// The update authority of the pool (must sign the transaction)
+    /// Mutable because they pay for the account creation
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// The AMM pool configuration account whose LP tokens get staked
+    /// Uses PDA derived from "config" seed and config.seed
+    #[account(
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    /// The LP token mint of the pool
+    /// Uses PDA derived from "lp" seed and config pubkey
+    #[account(
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump,
+        mint::token_program = token_program,
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The mint rewards are paid in, SPL Token or Token-2022
+    #[account(mint::token_program = reward_token_program)]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The farm account tracking emissions and stakes
+    /// Uses PDA derived from "farm" seed and config pubkey, one per pool
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"farm", config.key().as_ref()],
+        bump,
+        space = 8 + Farm::INIT_SPACE,
+    )]
+    pub farm: Box<Account<'info, Farm>>,
+
+    /// Vault holding the staked LP tokens
+    /// Associated token account owned by the farm PDA
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = mint_lp,
+        associated_token::authority = farm,
+        associated_token::token_program = token_program,
+    )]
+    pub farm_lp_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Vault the rewards are paid from, funded by transferring reward tokens to it
+    /// Associated token account owned by the farm PDA
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = reward_mint,
+        associated_token::authority = farm,
+        associated_token::token_program = reward_token_program,
+    )]
+    pub reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Token program of the LP mint
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Token program of the reward mint, SPL Token or Token-2022
+    pub reward_token_program: Interface<'info, TokenInterface>,
+    /// Associated Token program for creating the vaults
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    /// System program for account creation
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeFarm<'info> {
+    /// Creates the farm, emitting rewards from now on
+    ///
+    /// # Arguments
+    /// * `reward_per_second` - Rewards emitted per second, shared by all stakers
+    /// * `bumps` - Canonical bump values for PDAs (provided by Anchor)
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn initialize_farm(
+        &mut self,
+        reward_per_second: u64,
+        bumps: &InitializeFarmBumps,
+    ) -> Result<()> {
+        self.config.check_authority(self.authority.key)?;
+        // Same rules as pool tokens, the reward vault must stay able to pay out
+        check_supported_mint(&self.reward_mint.to_account_info())?;
+
+        self.farm.set_inner(Farm {
+            config: self.config.key(),
+            reward_mint: self.reward_mint.key(),
+            reward_per_second,
+            acc_reward_per_share: 0,
+            last_reward_time: Clock::get()?.unix_timestamp,
+            total_staked: 0,
+            bump: bumps.farm,
+        });
+
+        emit!(FarmUpdated {
+            seed: self.config.seed,
+            farm: self.farm.key(),
+            reward_mint: self.reward_mint.key(),
+            reward_per_second,
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use anchor_lang::prelude::*;
+
+use crate::states::{Config, Oracle};
+
+/// Accounts required for creating the TWAP price oracle of a pool created before
+/// `initialize` created it along with the pool
+/// Permissionless, anyone can add the oracle the trading instructions require
+#[derive(Accounts)]
+pub struct InitializeOracle<'info> {
+    /// Whoever creates the oracle (must sign the transaction)
+    /// Mutable because they pay for the account creation
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The AMM pool configuration account the oracle is tracking
+    /// Mutable because it records that the pool has an oracle
+    /// Uses PDA derived from "config" seed and config.seed
+    #[account(
+        mut,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    /// The oracle account holding price accumulators and observations
+    /// Uses PDA derived from "oracle" seed and config pubkey, one per pool
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump,
+        space = 8 + Oracle::INIT_SPACE,
+    )]
+    pub oracle: Box<Account<'info, Oracle>>,
+
+    /// System program for account creation
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeOracle<'info> {
+    /// Initializes an empty oracle for the pool
+    /// Accumulators stand still until the pool's next trade records its reserves
+    ///
+    /// # Arguments
+    /// * `bumps` - Canonical bump values for PDAs (provided by Anchor)
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn initialize_oracle(&mut self, bumps: &InitializeOracleBumps) -> Result<()> {
+        self.oracle.set_inner(Oracle::new(
+            self.config.key(),
+            bumps.oracle,
+            Clock::get()?.unix_timestamp,
+        ));
+        // Closing the pool must close the oracle too
+        self.config.has_oracle = true;
+
+        Ok(())
+    }
+}
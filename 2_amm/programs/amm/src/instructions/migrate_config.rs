@@ -0,0 +1,120 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_lang::Discriminator;
+
+use crate::constants::CONFIG_VERSION;
+use crate::errors::AmmError;
+use crate::events::ConfigMigrated;
+use crate::states::{Config, LegacyConfig, PoolIndex};
+
+/// Accounts required for migrating a pool config to the current layout
+/// Permissionless, the payer only funds the rent of the extra space
+#[derive(Accounts)]
+pub struct MigrateConfig<'info> {
+    /// Whoever migrates the config (must sign the transaction)
+    /// Mutable because they pay for the rent of the grown account
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: config account in an older layout, which `Account<Config>` can't
+    /// deserialize. Owner and discriminator are checked in the handler.
+    #[account(mut, owner = crate::ID)]
+    pub config: UncheckedAccount<'info>,
+
+    /// CHECK: TWAP oracle address of the pool, which may not exist. Only read to
+    /// record whether the pool has an oracle.
+    #[account(seeds = [b"oracle", config.key().as_ref()], bump)]
+    pub oracle: UncheckedAccount<'info>,
+
+    /// CHECK: factory index address of the pool's pair and fee tier, which may not
+    /// exist or point to another pool. Address checked in the handler, only read
+    /// to record whether the pool is canonical.
+    pub pool_index: UncheckedAccount<'info>,
+
+    /// System program for the rent transfer
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrateConfig<'info> {
+    /// Grows a config written in an older layout and back-fills the fields it
+    /// was missing
+    ///
+    /// Accepts the layout of the first deployed program (`LegacyConfig`),
+    /// whose pools get their authority as creator, or no creator at all, and
+    /// the unversioned layout ending right after `creator`.
+    ///
+    /// Neither layout records whether the pool has an oracle or a factory index,
+    /// which `close_pool` must close along with it, so both flags are set from
+    /// the accounts found at their addresses. The index is looked up under the
+    /// pool's current fee tier.
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn migrate_config(&mut self) -> Result<()> {
+        let config = self.config.to_account_info();
+        let (from_version, mut migrated) = {
+            let data = config.try_borrow_data()?;
+            require!(
+                data.starts_with(Config::DISCRIMINATOR),
+                ErrorCode::AccountDiscriminatorMismatch
+            );
+            // Older layouts are told apart by size, current accounts are larger
+            match data.len() {
+                Config::LEGACY_SPACE => {
+                    let legacy = LegacyConfig::deserialize(&mut &data[8..])?;
+                    // Legacy pools don't record who paid for them. Whoever migrates
+                    // one mustn't collect its rent on close, so pools without
+                    // authority are left without creator and can't be closed
+                    let creator = legacy.authority.unwrap_or_default();
+                    // Versioning came later, so these count as version 0 too
+                    (0, legacy.migrate(creator, Clock::get()?.unix_timestamp))
+                }
+                Config::V0_SPACE => {
+                    // Missing bytes read as version 0 with empty reserved space
+                    let mut data = data.to_vec();
+                    data.resize(8 + Config::INIT_SPACE, 0);
+                    let config = Config::try_deserialize(&mut &data[..])?;
+                    (config.version, config)
+                }
+                _ => return err!(AmmError::InvalidConfigVersion),
+            }
+        };
+
+        // Closing the pool must close its oracle and index too, if it has them
+        migrated.has_oracle = self.oracle.owner == &crate::ID && !self.oracle.data_is_empty();
+        // Index seeds take the mints in ascending order, like `create_pool`
+        let (mint_a, mint_b) = (migrated.mint_x.min(migrated.mint_y), migrated.mint_x.max(migrated.mint_y));
+        let (pool_index, _) = Pubkey::find_program_address(
+            &[b"pool", mint_a.as_ref(), mint_b.as_ref(), &migrated.fee.to_le_bytes()],
+            &crate::ID,
+        );
+        require_keys_eq!(self.pool_index.key(), pool_index, AmmError::InvalidPoolIndex);
+        migrated.canonical = self.pool_index.owner == &crate::ID
+            && PoolIndex::try_deserialize(&mut &self.pool_index.try_borrow_data()?[..])
+                .is_ok_and(|index| index.config == config.key());
+
+        // Top up the rent for the new size before growing the account
+        let space = 8 + Config::INIT_SPACE;
+        let rent = Rent::get()?.minimum_balance(space);
+        let missing = rent.saturating_sub(config.lamports());
+        if missing > 0 {
+            let cpi_accounts = Transfer {
+                from: self.payer.to_account_info(),
+                to: config.clone(),
+            };
+            let cpi_context = CpiContext::new(self.system_program.to_account_info(), cpi_accounts);
+            transfer(cpi_context, missing)?;
+        }
+
+        config.resize(space)?;
+        migrated.version = CONFIG_VERSION;
+        migrated.try_serialize(&mut &mut config.try_borrow_mut_data()?[..])?;
+
+        emit!(ConfigMigrated {
+            seed: migrated.seed,
+            from_version,
+            to_version: CONFIG_VERSION,
+        });
+        Ok(())
+    }
+}
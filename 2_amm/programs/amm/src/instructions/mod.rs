@@ -8,4 +8,57 @@ pub mod withdraw;
 pub use withdraw::*;
 
 pub mod swap;
-pub use swap::*;
\ No newline at end of file
+pub use swap::*;
+
+pub mod update_config;
+pub use update_config::*;
+
+pub mod accept_authority;
+pub use accept_authority::*;
+
+pub mod collect_protocol_fees;
+pub use collect_protocol_fees::*;
+
+pub mod initialize_oracle;
+pub use initialize_oracle::*;
+
+pub mod get_twap;
+pub use get_twap::*;
+
+pub mod route_swap;
+pub use route_swap::*;
+
+pub mod zap;
+pub use zap::*;
+
+pub mod flash_loan;
+pub use flash_loan::*;
+pub mod quote;
+pub use quote::*;
+
+pub mod initialize_factory;
+pub use initialize_factory::*;
+
+pub mod create_pool;
+pub use create_pool::*;
+
+pub mod initialize_farm;
+pub use initialize_farm::*;
+
+pub mod update_farm;
+pub use update_farm::*;
+
+pub mod stake;
+pub use stake::*;
+
+pub mod close_pool;
+pub use close_pool::*;
+
+pub mod migrate_config;
+pub use migrate_config::*;
+
+pub mod allow_trader;
+pub use allow_trader::*;
+
+pub mod revoke_trader;
+pub use revoke_trader::*;
@@ -0,0 +1,151 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::errors::AmmError;
+use crate::states::{Config, LiquidityQuote, SwapQuote};
+use crate::transfer_fee::{amount_received, amount_to_send};
+
+/// Accounts required for quoting trades against a pool
+/// Read-only, quotes are returned to the caller as return data
+#[derive(Accounts)]
+pub struct Quote<'info> {
+    /// The mint account for token X in the trading pair
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The mint account for token Y in the trading pair
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The AMM pool configuration account
+    /// Uses PDA derived from "config" seed and config.seed
+    #[account(
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        has_one = mint_x,  // Ensures mint_x matches the one in config
+        has_one = mint_y,  // Ensures mint_y matches the one in config
+    )]
+    pub config: Account<'info, Config>,
+
+    /// The LP token mint, read for the current supply
+    /// Uses PDA derived from "lp" seed and config pubkey
+    #[account(
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump,
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The vault that holds all deposited token X
+    #[account(
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x,
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The vault that holds all deposited token Y
+    #[account(
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y,
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Token program of token X, SPL Token or Token-2022
+    pub token_program_x: Interface<'info, TokenInterface>,
+    /// Token program of token Y, SPL Token or Token-2022
+    pub token_program_y: Interface<'info, TokenInterface>,
+    /// Associated Token program for ATA validation
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> Quote<'info> {
+    /// Quotes an exact-in swap with the same math `swap` executes
+    ///
+    /// # Arguments
+    /// * `is_x` - true if swapping token X for Y, false if swapping Y for X
+    /// * `amount_in` - Amount of input tokens to swap, transfer fee included
+    ///
+    /// # Returns
+    /// * `Result<SwapQuote>` - Amounts leaving and reaching the user (transfer fees
+    ///   included), fees and post-trade reserves of the swap
+    pub fn quote_swap(&self, is_x: bool, amount_in: u64) -> Result<SwapQuote> {
+        require!(amount_in > 0, AmmError::InvalidAmount);
+        let (now, reserve_x, reserve_y) = self.prepare_quote()?;
+
+        let (mint_in, mint_out) = match is_x {
+            true => (self.mint_x.to_account_info(), self.mint_y.to_account_info()),
+            false => (self.mint_y.to_account_info(), self.mint_x.to_account_info()),
+        };
+        let received = amount_received(&mint_in, amount_in)?;
+        let quote = self
+            .config
+            .quote_swap(reserve_x, reserve_y, is_x, received, now, Clock::get()?.slot)?;
+
+        Ok(SwapQuote {
+            amount_in,
+            amount_out: amount_received(&mint_out, quote.amount_out)?,
+            ..quote
+        })
+    }
+
+    /// Quotes the token amounts `deposit` takes for `amount` LP tokens
+    ///
+    /// # Arguments
+    /// * `amount` - Amount of LP tokens to mint
+    ///
+    /// # Returns
+    /// * `Result<LiquidityQuote>` - Token amounts the deposit takes, transfer fees included
+    pub fn quote_deposit(&self, amount: u64) -> Result<LiquidityQuote> {
+        let (now, reserve_x, reserve_y) = self.prepare_quote()?;
+
+        let quote = self
+            .config
+            .quote_deposit(reserve_x, reserve_y, self.mint_lp.supply, amount, now)?;
+
+        Ok(LiquidityQuote {
+            amount_x: amount_to_send(&self.mint_x.to_account_info(), quote.amount_x)?,
+            amount_y: amount_to_send(&self.mint_y.to_account_info(), quote.amount_y)?,
+            ..quote
+        })
+    }
+
+    /// Quotes the token amounts `withdraw` releases for `amount` LP tokens
+    ///
+    /// # Arguments
+    /// * `amount` - Amount of LP tokens to burn
+    ///
+    /// # Returns
+    /// * `Result<LiquidityQuote>` - Token amounts the withdrawal releases, net of transfer fees
+    pub fn quote_withdraw(&self, amount: u64) -> Result<LiquidityQuote> {
+        let (now, reserve_x, reserve_y) = self.prepare_quote()?;
+
+        let quote = self
+            .config
+            .quote_withdraw(reserve_x, reserve_y, self.mint_lp.supply, amount, now)?;
+
+        Ok(LiquidityQuote {
+            amount_x: amount_received(&self.mint_x.to_account_info(), quote.amount_x)?,
+            amount_y: amount_received(&self.mint_y.to_account_info(), quote.amount_y)?,
+            ..quote
+        })
+    }
+
+    /// Checks the pool can trade and reads the current time and pool reserves
+    ///
+    /// # Returns
+    /// * `Result<(i64, u64, u64)>` - Current unix timestamp and pool reserves (X, Y)
+    fn prepare_quote(&self) -> Result<(i64, u64, u64)> {
+        // A locked pool rejects the trades being quoted
+        require!(!self.config.locked, AmmError::PoolLocked);
+        // Vault balances don't reflect the pool while a flash loan is in progress
+        require!(!self.config.flash_loan_active, AmmError::FlashLoanActive);
+
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+
+        Ok((Clock::get()?.unix_timestamp, reserve_x, reserve_y))
+    }
+}
@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+
+use crate::events::AllowlistUpdated;
+use crate::states::{AllowlistEntry, Config};
+
+/// Accounts required for removing a trader from a permissioned pool
+#[derive(Accounts)]
+pub struct RevokeTrader<'info> {
+    /// The update authority of the pool (must sign the transaction)
+    /// Mutable because the entry's rent is refunded to them
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// The AMM pool configuration account the trader is removed from
+    /// Uses PDA derived from "config" seed and config.seed
+    #[account(
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    /// The trader's allowlist entry, closed by this instruction
+    /// Uses PDA derived from "allowlist" seed, config and user pubkeys
+    #[account(
+        mut,
+        seeds = [b"allowlist", config.key().as_ref(), allowlist_entry.user.as_ref()],
+        bump = allowlist_entry.bump,
+        has_one = config,
+        close = authority,
+    )]
+    pub allowlist_entry: Account<'info, AllowlistEntry>,
+}
+
+impl<'info> RevokeTrader<'info> {
+    /// Removes a trader from the pool's allowlist
+    /// A trader holding a valid gatekeeper attestation can still trade until it expires
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn revoke_trader(&mut self) -> Result<()> {
+        self.config.check_authority(self.authority.key)?;
+
+        emit!(AllowlistUpdated {
+            seed: self.config.seed,
+            user: self.allowlist_entry.user,
+            allowed: false,
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id,
+    token::Token,
+    token_2022::Token2022,
+    token_interface::{transfer_checked, Mint, TokenAccount, TransferChecked},
+};
+
+use crate::constants::{MAX_ROUTE_LEGS, ROUTE_LEG_ACCOUNTS};
+use crate::errors::AmmError;
+use crate::events::Swapped;
+use crate::states::{Config, Oracle};
+use crate::transfer_fee::amount_received;
+
+/// Accounts required for a multi-hop swap through several AMM pools
+///
+/// The pools of the route are passed in `remaining_accounts`, `ROUTE_LEG_ACCOUNTS`
+/// per leg and in trading order:
+/// 0. `config` (mut) - configuration account of the pool
+/// 1. `oracle` (mut) - TWAP oracle of the pool
+/// 2. `mint_in` - mint of the token sold on this leg
+/// 3. `mint_out` - mint of the token bought on this leg
+/// 4. `vault_in` (mut) - pool vault of `mint_in`
+/// 5. `vault_out` (mut) - pool vault of `mint_out`
+/// 6. `user_ata_out` (mut) - user's token account receiving `mint_out`
+///
+/// Each leg sells what the previous leg bought, taken from the previous leg's
+/// `user_ata_out` (the first leg sells from `user_ata_in`). The user's token
+/// accounts must already exist. Each token moves under the program owning its
+/// mint, so a route can mix SPL Token and Token-2022 pools.
+#[derive(Accounts)]
+pub struct RouteSwap<'info> {
+    /// The user who is performing the swap (must sign the transaction)
+    pub user: Signer<'info>,
+
+    /// User's token account holding the token sold on the first leg
+    /// Mutable because the input amount is transferred from it
+    #[account(
+        mut,
+        token::authority = user,
+    )]
+    pub user_ata_in: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// SPL Token program, for legs trading SPL Token mints
+    pub token_program: Program<'info, Token>,
+    /// Token-2022 program, for legs trading Token-2022 mints
+    pub token_2022_program: Program<'info, Token2022>,
+}
+
+impl<'info> RouteSwap<'info> {
+    /// Swaps through every pool of the route in one atomic instruction
+    ///
+    /// The output of each leg becomes the input of the next one, so only the
+    /// final output is checked against the user's minimum, and any failing leg
+    /// reverts the whole route.
+    ///
+    /// # Arguments
+    /// * `legs` - Pool accounts of the route (see `RouteSwap`)
+    /// * `amount_in` - Amount of tokens sold on the first leg, transfer fee included
+    /// * `min_amount_out` - Minimum amount of tokens received from the last leg, net of transfer fees (slippage protection)
+    /// * `expiration` - Unix timestamp after which the swap is rejected
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn route_swap(
+        &mut self,
+        legs: &'info [AccountInfo<'info>],
+        amount_in: u64,
+        min_amount_out: u64,
+        expiration: i64,
+    ) -> Result<()> {
+        // Reject transactions that sat around long enough for the price to go stale
+        let now = Clock::get()?.unix_timestamp;
+        require!(now <= expiration, AmmError::OfferExpired);
+        // Ensure user is swapping a positive amount
+        require!(amount_in > 0, AmmError::InvalidAmount);
+        // Ensure the route is made of complete legs and isn't too long
+        let route = legs.chunks_exact(ROUTE_LEG_ACCOUNTS);
+        require!(
+            route.len() > 0 && route.len() <= MAX_ROUTE_LEGS && route.remainder().is_empty(),
+            AmmError::InvalidRoute
+        );
+
+        // Token sold on the current leg and the account it is taken from
+        let mut amount = amount_in;
+        let mut source = self.user_ata_in.to_account_info();
+        let mut source_mint = self.user_ata_in.mint;
+
+        for leg in route {
+            let (amount_out, mint_out) = self.swap_leg(leg, &source, source_mint, amount, now)?;
+
+            // Carry the output forward as the next leg's input
+            amount = amount_out;
+            source = leg[6].clone();
+            source_mint = mint_out;
+        }
+
+        // Slippage protection on the whole route
+        require!(amount >= min_amount_out, AmmError::SlippageExceeded);
+
+        Ok(())
+    }
+
+    /// Validates the accounts of one leg and swaps through its pool
+    ///
+    /// # Arguments
+    /// * `leg` - The `ROUTE_LEG_ACCOUNTS` accounts of the leg
+    /// * `source` - User's token account the input is taken from
+    /// * `source_mint` - Mint of the input token
+    /// * `amount_in` - Amount of input tokens to swap, transfer fee included
+    /// * `now` - Current unix timestamp
+    ///
+    /// # Returns
+    /// * `Result<(u64, Pubkey)>` - Amount of output tokens received (net of transfer fees) and their mint
+    fn swap_leg(
+        &self,
+        leg: &'info [AccountInfo<'info>],
+        source: &AccountInfo<'info>,
+        source_mint: Pubkey,
+        amount_in: u64,
+        now: i64,
+    ) -> Result<(u64, Pubkey)> {
+        let mut config = Box::new(Account::<Config>::try_from(&leg[0])?);
+        let mut oracle = Box::new(Account::<Oracle>::try_from(&leg[1])?);
+        let mint_in = Box::new(InterfaceAccount::<Mint>::try_from(&leg[2])?);
+        let mint_out = Box::new(InterfaceAccount::<Mint>::try_from(&leg[3])?);
+        let mut vault_in = Box::new(InterfaceAccount::<TokenAccount>::try_from(&leg[4])?);
+        let mut vault_out = Box::new(InterfaceAccount::<TokenAccount>::try_from(&leg[5])?);
+        let user_ata_out = Box::new(InterfaceAccount::<TokenAccount>::try_from(&leg[6])?);
+        let token_program_in = self.token_program_of(&leg[2])?;
+        let token_program_out = self.token_program_of(&leg[3])?;
+
+        // The config must be the canonical PDA of a pool of this program
+        let config_key = Pubkey::create_program_address(
+            &[b"config", &config.seed.to_le_bytes(), &[config.config_bump]],
+            &crate::ID,
+        )
+        .map_err(|_| AmmError::InvalidRoute)?;
+        require_keys_eq!(config_key, config.key(), AmmError::InvalidRoute);
+        // The oracle must be the one of this pool
+        let oracle_key = Pubkey::create_program_address(
+            &[b"oracle", config_key.as_ref(), &[oracle.bump]],
+            &crate::ID,
+        )
+        .map_err(|_| AmmError::InvalidRoute)?;
+        require_keys_eq!(oracle_key, oracle.key(), AmmError::InvalidRoute);
+
+        // The leg must sell the token bought by the previous leg, and trade the pool's pair
+        require_keys_eq!(mint_in.key(), source_mint, AmmError::InvalidRoute);
+        let is_x = match (mint_in.key(), mint_out.key()) {
+            (mint_in, mint_out) if mint_in == config.mint_x && mint_out == config.mint_y => true,
+            (mint_in, mint_out) if mint_in == config.mint_y && mint_out == config.mint_x => false,
+            _ => return err!(AmmError::InvalidRoute),
+        };
+
+        // Vaults are the config's associated token accounts
+        require_keys_eq!(
+            vault_in.key(),
+            get_associated_token_address_with_program_id(&config_key, &mint_in.key(), token_program_in.key),
+            AmmError::InvalidRoute
+        );
+        require_keys_eq!(
+            vault_out.key(),
+            get_associated_token_address_with_program_id(&config_key, &mint_out.key(), token_program_out.key),
+            AmmError::InvalidRoute
+        );
+
+        // Output goes to a token account of the user
+        require_keys_eq!(user_ata_out.mint, mint_out.key(), AmmError::InvalidToken);
+        require_keys_eq!(user_ata_out.owner, self.user.key(), AmmError::InvalidToken);
+
+        // Ensure the pool is not locked for swaps
+        require!(!config.locked, AmmError::PoolLocked);
+        // Vault balances are borrowed out while a flash loan is in progress
+        require!(!config.flash_loan_active, AmmError::FlashLoanActive);
+        // Legs carry no allowlist entries, permissioned pools are swapped in directly
+        require!(!config.permissioned, AmmError::TraderNotAllowed);
+
+        // Pool reserves exclude protocol fees waiting in the vaults
+        let (vault_x, vault_y) = match is_x {
+            true => (vault_in.amount, vault_out.amount),
+            false => (vault_out.amount, vault_in.amount),
+        };
+        let (reserve_x, reserve_y) = config.reserves(vault_x, vault_y)?;
+
+        // Record the pre-swap price in the price band
+        config.open_price_band(reserve_x, reserve_y, Clock::get()?.slot);
+
+        // Price the leg on the pool's curve, on what the vault receives net of any
+        // transfer fee; slippage is only checked on the whole route
+        let fee_bps = config.swap_fee(now);
+        let received = amount_received(&leg[2], amount_in)?;
+        let swap_result = config.swap_exact_in(reserve_x, reserve_y, is_x, received, 0, now)?;
+
+        // Deposit the input tokens from the user into the pool
+        let cpi_accounts = TransferChecked {
+            from: source.clone(),
+            to: vault_in.to_account_info(),
+            mint: mint_in.to_account_info(),
+            authority: self.user.to_account_info(),  // User signs the transfer
+        };
+        let cpi_context = CpiContext::new(token_program_in, cpi_accounts);
+        transfer_checked(cpi_context, amount_in, mint_in.decimals)?;
+
+        // Withdraw the output tokens from the pool to the user
+        let cpi_accounts = TransferChecked {
+            from: vault_out.to_account_info(),
+            to: user_ata_out.to_account_info(),
+            mint: mint_out.to_account_info(),
+            authority: config.to_account_info(),  // Config PDA signs the transfer
+        };
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"config",
+            &config.seed.to_le_bytes(),
+            &[config.config_bump],
+        ]];
+        let cpi_context = CpiContext::new_with_signer(
+            token_program_out,
+            cpi_accounts,
+            signer_seeds,
+        );
+        transfer_checked(cpi_context, swap_result.withdraw, mint_out.decimals)?;
+
+        // Record the pool's post-trade reserves in the TWAP oracle and report the leg
+        vault_in.reload()?;
+        vault_out.reload()?;
+        let (vault_x, vault_y) = match is_x {
+            true => (vault_in.amount, vault_out.amount),
+            false => (vault_out.amount, vault_in.amount),
+        };
+        let (reserve_x, reserve_y) = config.reserves(vault_x, vault_y)?;
+        oracle.update(reserve_x, reserve_y, now)?;
+        emit!(Swapped {
+            seed: config.seed,
+            user: self.user.key(),
+            is_x,
+            amount_in: swap_result.deposit,
+            amount_out: swap_result.withdraw,
+            fee: swap_result.fee,
+            fee_bps,
+            referrer: None,
+            referral_fee: 0,
+            reserve_x,
+            reserve_y,
+        });
+
+        // Persist the accrued fees and oracle update before the next leg reads them
+        config.exit(&crate::ID)?;
+        oracle.exit(&crate::ID)?;
+
+        let amount_out = amount_received(&leg[3], swap_result.withdraw)?;
+        Ok((amount_out, mint_out.key()))
+    }
+
+    /// Token program a mint belongs to
+    ///
+    /// # Arguments
+    /// * `mint` - Mint account of one of the leg's tokens
+    ///
+    /// # Returns
+    /// * `Result<AccountInfo>` - SPL Token or Token-2022 program, whichever owns the mint
+    fn token_program_of(&self, mint: &AccountInfo<'info>) -> Result<AccountInfo<'info>> {
+        match *mint.owner {
+            owner if owner == self.token_program.key() => Ok(self.token_program.to_account_info()),
+            owner if owner == self.token_2022_program.key() => Ok(self.token_2022_program.to_account_info()),
+            _ => err!(AmmError::InvalidToken),
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::errors::AmmError;
+use crate::events::{RewardsHarvested, Staked, Unstaked};
+use crate::states::{Config, Farm, StakePosition};
+
+/// Accounts required for staking LP tokens in a farm, unstaking them and harvesting rewards
+#[derive(Accounts)]
+pub struct Stake<'info> {
+    /// The staker (must sign the transaction)
+    /// Mutable because they pay for their position and token accounts
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// The AMM pool configuration account the farm belongs to
+    /// Uses PDA derived from "config" seed and config.seed
+    #[account(
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    /// The farm account of the pool
+    /// Mutable because every operation accrues rewards and may change the total stake
+    /// Uses PDA derived from "farm" seed and config pubkey
+    #[account(
+        mut,
+        seeds = [b"farm", config.key().as_ref()],
+        bump = farm.bump,
+        has_one = config,
+        has_one = reward_mint,
+    )]
+    pub farm: Box<Account<'info, Farm>>,
+
+    /// The user's stake in the farm
+    /// Created on first use, the user pays for creation
+    /// Uses PDA derived from "stake" seed, farm and user pubkeys
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"stake", farm.key().as_ref(), user.key().as_ref()],
+        bump,
+        space = 8 + StakePosition::INIT_SPACE,
+    )]
+    pub position: Box<Account<'info, StakePosition>>,
+
+    /// The LP token mint of the pool
+    /// Uses PDA derived from "lp" seed and config pubkey
+    #[account(
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump,
+        mint::token_program = token_program,
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The mint rewards are paid in
+    #[account(mint::token_program = reward_token_program)]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// Vault holding the staked LP tokens
+    /// Associated token account owned by the farm PDA
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = farm,
+        associated_token::token_program = token_program,
+    )]
+    pub farm_lp_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Vault the rewards are paid from
+    /// Associated token account owned by the farm PDA
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = farm,
+        associated_token::token_program = reward_token_program,
+    )]
+    pub reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// User's token account for LP tokens
+    /// Will be created if it doesn't exist, user pays for creation
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint_lp,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_ata_lp: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// User's token account for rewards
+    /// Will be created if it doesn't exist, user pays for creation
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = reward_mint,
+        associated_token::authority = user,
+        associated_token::token_program = reward_token_program,
+    )]
+    pub user_ata_reward: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Token program of the LP mint
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Token program of the reward mint, SPL Token or Token-2022
+    pub reward_token_program: Interface<'info, TokenInterface>,
+    /// Associated Token program for ATA operations
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    /// System program for account creation
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Stake<'info> {
+    /// Stakes LP tokens in the farm, paying out the rewards earned so far
+    ///
+    /// # Arguments
+    /// * `amount` - Amount of LP tokens to stake
+    /// * `bumps` - Canonical bump values for PDAs (provided by Anchor)
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn stake(&mut self, amount: u64, bumps: &StakeBumps) -> Result<()> {
+        require!(amount != 0, AmmError::InvalidAmount);
+        self.settle(bumps)?;
+
+        let staked = self
+            .position
+            .amount
+            .checked_add(amount)
+            .ok_or(AmmError::Overflow)?;
+        self.farm.set_stake(&mut self.position, staked)?;
+
+        // Move the LP tokens from the user into the farm
+        let cpi_accounts = TransferChecked {
+            from: self.user_ata_lp.to_account_info(),
+            to: self.farm_lp_vault.to_account_info(),
+            mint: self.mint_lp.to_account_info(),
+            authority: self.user.to_account_info(),  // User signs the transfer
+        };
+        let cpi_context = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+        transfer_checked(cpi_context, amount, self.mint_lp.decimals)?;
+
+        self.pay_rewards()?;
+
+        emit!(Staked {
+            seed: self.config.seed,
+            user: self.user.key(),
+            amount,
+            total_staked: self.farm.total_staked,
+        });
+        Ok(())
+    }
+
+    /// Takes LP tokens out of the farm, paying out the rewards earned so far
+    ///
+    /// # Arguments
+    /// * `amount` - Amount of LP tokens to unstake
+    /// * `bumps` - Canonical bump values for PDAs (provided by Anchor)
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn unstake(&mut self, amount: u64, bumps: &StakeBumps) -> Result<()> {
+        require!(amount != 0, AmmError::InvalidAmount);
+        require!(amount <= self.position.amount, AmmError::InsufficientBalance);
+        self.settle(bumps)?;
+
+        let staked = self.position.amount - amount;
+        self.farm.set_stake(&mut self.position, staked)?;
+        self.withdraw_lp(amount)?;
+        self.pay_rewards()?;
+
+        emit!(Unstaked {
+            seed: self.config.seed,
+            user: self.user.key(),
+            amount,
+            total_staked: self.farm.total_staked,
+            emergency: false,
+        });
+        Ok(())
+    }
+
+    /// Pays out the rewards earned so far, keeping the stake
+    ///
+    /// # Arguments
+    /// * `bumps` - Canonical bump values for PDAs (provided by Anchor)
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn harvest(&mut self, bumps: &StakeBumps) -> Result<()> {
+        self.settle(bumps)?;
+        self.pay_rewards()
+    }
+
+    /// Takes the whole stake out of the farm without touching the rewards
+    /// For when rewards can't be paid, the unpaid rewards are forfeited
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn emergency_withdraw(&mut self) -> Result<()> {
+        let amount = self.position.amount;
+        require!(amount != 0, AmmError::InsufficientBalance);
+
+        // Accrue at the current total first, so other stakers keep what they earned
+        self.farm.update(Clock::get()?.unix_timestamp)?;
+        self.farm.set_stake(&mut self.position, 0)?;
+        self.position.rewards_owed = 0;
+        self.withdraw_lp(amount)?;
+
+        emit!(Unstaked {
+            seed: self.config.seed,
+            user: self.user.key(),
+            amount,
+            total_staked: self.farm.total_staked,
+            emergency: true,
+        });
+        Ok(())
+    }
+
+    /// Brings the farm up to date and moves the user's new rewards into `rewards_owed`
+    fn settle(&mut self, bumps: &StakeBumps) -> Result<()> {
+        self.init_position(bumps);
+        self.farm.update(Clock::get()?.unix_timestamp)?;
+        self.farm.settle(&mut self.position)?;
+        Ok(())
+    }
+
+    /// Fills in a position created by this instruction
+    fn init_position(&mut self, bumps: &StakeBumps) {
+        if self.position.farm == Pubkey::default() {
+            self.position.farm = self.farm.key();
+            self.position.owner = self.user.key();
+            self.position.bump = bumps.position;
+        }
+    }
+
+    /// Pays the rewards owed to the user, as far as the reward vault allows
+    /// Whatever the vault is short of stays owed until it is topped up
+    fn pay_rewards(&mut self) -> Result<()> {
+        let amount = self.position.rewards_owed.min(self.reward_vault.amount);
+        if amount == 0 {
+            return Ok(());
+        }
+        self.position.rewards_owed -= amount;
+
+        let cpi_accounts = TransferChecked {
+            from: self.reward_vault.to_account_info(),
+            to: self.user_ata_reward.to_account_info(),
+            mint: self.reward_mint.to_account_info(),
+            authority: self.farm.to_account_info(),  // Farm PDA signs the transfer
+        };
+        let config = self.config.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[b"farm", config.as_ref(), &[self.farm.bump]]];
+        let cpi_context = CpiContext::new_with_signer(
+            self.reward_token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        transfer_checked(cpi_context, amount, self.reward_mint.decimals)?;
+
+        emit!(RewardsHarvested {
+            seed: self.config.seed,
+            user: self.user.key(),
+            amount,
+            rewards_owed: self.position.rewards_owed,
+        });
+        Ok(())
+    }
+
+    /// Sends staked LP tokens from the farm back to the user
+    fn withdraw_lp(&mut self, amount: u64) -> Result<()> {
+        let cpi_accounts = TransferChecked {
+            from: self.farm_lp_vault.to_account_info(),
+            to: self.user_ata_lp.to_account_info(),
+            mint: self.mint_lp.to_account_info(),
+            authority: self.farm.to_account_info(),  // Farm PDA signs the transfer
+        };
+        let config = self.config.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[b"farm", config.as_ref(), &[self.farm.bump]]];
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        transfer_checked(cpi_context, amount, self.mint_lp.decimals)
+    }
+}
@@ -1,12 +1,17 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions as sysvar_instructions;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{transfer_checked, Mint, Token, TokenAccount, TransferChecked},
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
 };
-use constant_product_curve::{ConstantProduct, LiquidityPair};
+use constant_product_curve::SwapResult;
 
 use crate::errors::AmmError;
-use crate::states::Config;
+use crate::events::Swapped;
+use crate::native_sol::{native_sides, unwrap_sol, wrap_sol};
+use crate::permissions::check_trader;
+use crate::states::{AllowlistEntry, Config, Oracle};
+use crate::transfer_fee::{amount_received, amount_to_send};
 
 /// Accounts required for performing token swaps in the AMM pool
 /// This struct defines all the accounts needed to execute a swap operation
@@ -20,18 +25,20 @@ pub struct Swap<'info> {
 
     /// The mint account for token X in the trading pair
     /// Immutable as we only need to read mint information for transfers
-    #[account(mint::token_program = token_program)]
-    pub mint_x: Account<'info, Mint>,
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
 
     /// The mint account for token Y in the trading pair
     /// Immutable as we only need to read mint information for transfers
-    #[account(mint::token_program = token_program)]
-    pub mint_y: Account<'info, Mint>,
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
 
     /// The AMM pool configuration account
     /// Contains pool settings, fees, and references to the token mints
+    /// Mutable because the protocol share of the fee is accrued here
     /// Uses PDA derived from "config" seed and config.seed
     #[account(
+        mut,
         seeds = [b"config", config.seed.to_le_bytes().as_ref()],
         bump = config.config_bump,
         has_one = mint_x,  // Ensures mint_x matches the one in config
@@ -39,6 +46,16 @@ pub struct Swap<'info> {
     )]
     pub config: Account<'info, Config>,
 
+    /// TWAP price oracle of the pool
+    /// Mutable because every trade updates its price accumulators
+    /// Uses PDA derived from "oracle" seed and config pubkey
+    #[account(
+        mut,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.bump,
+    )]
+    pub oracle: Box<Account<'info, Oracle>>,
+
     /// The LP (Liquidity Provider) token mint
     /// Used for reading supply information in swap calculations
     /// Uses PDA derived from "lp" seed and config pubkey
@@ -46,7 +63,7 @@ pub struct Swap<'info> {
         seeds = [b"lp", config.key().as_ref()],
         bump = config.lp_bump
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
 
     /// The vault that holds all deposited token X
     /// Mutable because swap operations either deposit to or withdraw from this vault
@@ -55,9 +72,9 @@ pub struct Swap<'info> {
         mut,
         associated_token::mint = mint_x,
         associated_token::authority = config,
-        associated_token::token_program = token_program,
+        associated_token::token_program = token_program_x,
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
 
     /// The vault that holds all deposited token Y
     /// Mutable because swap operations either deposit to or withdraw from this vault
@@ -66,9 +83,9 @@ pub struct Swap<'info> {
         mut,
         associated_token::mint = mint_y,
         associated_token::authority = config,
-        associated_token::token_program = token_program,
+        associated_token::token_program = token_program_y,
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
 
     /// User's token account for token X
     /// Will be created if it doesn't exist, user pays for creation
@@ -78,9 +95,9 @@ pub struct Swap<'info> {
         payer = user,
         associated_token::mint = mint_x,
         associated_token::authority = user,
-        associated_token::token_program = token_program
+        associated_token::token_program = token_program_x
     )]
-    pub user_ata_x: Account<'info, TokenAccount>,
+    pub user_ata_x: Box<InterfaceAccount<'info, TokenAccount>>,
 
     /// User's token account for token Y
     /// Will be created if it doesn't exist, user pays for creation
@@ -90,12 +107,33 @@ pub struct Swap<'info> {
         payer = user,
         associated_token::mint = mint_y,
         associated_token::authority = user,
-        associated_token::token_program = token_program
+        associated_token::token_program = token_program_y
     )]
-    pub user_ata_y: Account<'info, TokenAccount>,
+    pub user_ata_y: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The user's allowlist entry, only needed in permissioned pools
+    /// Uses PDA derived from "allowlist" seed, config and user pubkeys
+    #[account(
+        seeds = [b"allowlist", config.key().as_ref(), user.key().as_ref()],
+        bump = allowlist_entry.bump,
+    )]
+    pub allowlist_entry: Option<Box<Account<'info, AllowlistEntry>>>,
+
+    /// Instructions sysvar, only needed to present a gatekeeper attestation
+    /// CHECK: address is checked against the sysvar id
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+
+    /// Token account of whoever referred the swap, paid a share of the fee
+    /// Must hold the input token of the swap and not be owned by the user,
+    /// omit it for swaps without a referrer
+    #[account(mut)]
+    pub referrer_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
 
-    /// SPL Token program for token operations
-    pub token_program: Program<'info, Token>,
+    /// Token program of token X, SPL Token or Token-2022
+    pub token_program_x: Interface<'info, TokenInterface>,
+    /// Token program of token Y, SPL Token or Token-2022
+    pub token_program_y: Interface<'info, TokenInterface>,
     /// Associated Token program for ATA operations
     pub associated_token_program: Program<'info, AssociatedToken>,
     /// System program for account creation
@@ -108,60 +146,298 @@ impl<'info> Swap<'info> {
     /// The swap process follows these steps:
     /// 1. Validate pool is not locked and amounts are valid
     /// 2. Initialize constant product curve with current pool state
-    /// 3. Calculate swap amounts using the curve (accounting for fees)
-    /// 4. Validate slippage protection (output meets minimum requirement)
-    /// 5. Deposit input tokens to appropriate vault
-    /// 6. Withdraw output tokens from appropriate vault to user
+    /// 3. Calculate swap amounts using the curve (accounting for fees) on the
+    ///    input the vault receives, net of any Token-2022 transfer fee
+    /// 4. Validate slippage protection (output reaching the user meets minimum requirement)
+    /// 5. Accrue the protocol share of the fee
+    /// 6. Deposit input tokens to appropriate vault
+    /// 7. Withdraw output tokens from appropriate vault to user
+    /// 8. Pay the referrer, if any, its share of the fee out of the input vault
     /// 
-    /// The constant product formula (x * y = k) ensures that the product of
-    /// token reserves remains constant after accounting for fees.
+    /// The referral share comes out of the LP share of the fee, so the user
+    /// pays the same with or without a referrer.
+    /// 
+    /// In native SOL mode, the SOL paid in is wrapped into the user's wrapped
+    /// SOL account first, and that account is closed at the end so everything
+    /// in it goes back to the user as lamports.
+    /// 
+    /// The pool's curve (constant product or StableSwap) keeps its invariant
+    /// from decreasing after accounting for fees.
     /// 
     /// # Arguments
     /// * `is_x` - true if swapping token X for Y, false if swapping Y for X
-    /// * `amount_in` - Amount of input tokens to swap
-    /// * `min_amount_out` - Minimum amount of output tokens expected (slippage protection)
+    /// * `amount_in` - Amount of input tokens to swap, transfer fee included
+    /// * `min_amount_out` - Minimum amount of output tokens the user must receive (slippage protection)
+    /// * `expiration` - Unix timestamp after which the swap is rejected
+    /// * `native_sol` - Pay or receive native SOL for the wrapped SOL side
     /// 
     /// # Returns
     /// * `Result<()>` - Ok if successful, error otherwise
-    pub fn swap(&mut self, is_x: bool, amount_in: u64, min_amount_out: u64) -> Result<()> {
-        // Ensure the pool is not locked for swaps
-        require!(!self.config.locked, AmmError::PoolLocked);
+    pub fn swap(
+        &mut self,
+        is_x: bool,
+        amount_in: u64,
+        min_amount_out: u64,
+        expiration: i64,
+        native_sol: bool,
+    ) -> Result<()> {
         // Ensure user is swapping a positive amount
         require!(amount_in > 0, AmmError::InvalidAmount);
 
-        // Initialize constant product curve with current pool state
-        let mut curve = ConstantProduct::init(
-            self.vault_x.amount,    // Current token X reserves
-            self.vault_y.amount,    // Current token Y reserves
-            self.mint_lp.supply,    // Current LP token supply
-            self.config.fee,        // Trading fee in basis points
-            None,                   // No additional configuration
-        )
-        .map_err(AmmError::from)?;
+        // Validate the pool state and get the current reserves
+        let (now, reserve_x, reserve_y) = self.prepare_swap(expiration)?;
 
-        // Determine which token is being swapped in
-        let p = match is_x {
-            true => LiquidityPair::X,   // Swapping X for Y
-            false => LiquidityPair::Y,  // Swapping Y for X
-        };
+        let (mint_in, mint_out) = self.mints(is_x);
 
-        // Calculate swap amounts using constant product formula
-        // This accounts for fees and maintains the invariant
-        let swap_result = curve
-            .swap(p, amount_in, min_amount_out)
-            .map_err(AmmError::from)?;
+        // Price the swap on the pool's curve (accounting for fees), using the
+        // amount the vault actually receives once any transfer fee is withheld
+        let fee_bps = self.config.swap_fee(now);
+        let received = amount_received(&mint_in, amount_in)?;
+        let swap_result = self
+            .config
+            .swap_exact_in(reserve_x, reserve_y, is_x, received, 0, now)?;
 
-        // Validate that the calculated amounts are valid
-        require!(swap_result.deposit != 0, AmmError::InvalidAmount);
-        require!(swap_result.withdraw != 0, AmmError::InvalidAmount);
+        // Slippage protection on what reaches the user, net of transfer fees
+        let amount_out = amount_received(&mint_out, swap_result.withdraw)?;
+        require!(amount_out >= min_amount_out, AmmError::SlippageExceeded);
 
         // Execute the swap by depositing input tokens and withdrawing output tokens
-        self.deposit_token(is_x, swap_result.deposit)?;      // Deposit input tokens
+        let (native_x, native_y) = native_sides(native_sol, &self.user_ata_x, &self.user_ata_y)?;
+        if (is_x && native_x) || (!is_x && native_y) {
+            self.wrap_sol(is_x, amount_in)?;                 // Wrap the SOL paid in
+        }
+        self.deposit_token(is_x, amount_in)?;                // Deposit input tokens
         self.withdraw_token(!is_x, swap_result.withdraw)?;   // Withdraw output tokens
+        let referral_fee = self.pay_referrer(is_x, swap_result.fee)?;  // Pay the referrer's share
+        self.unwrap_sol(native_x, native_y)?;                // Hand wrapped SOL back as lamports
+
+        self.finish_swap(is_x, fee_bps, &swap_result, referral_fee, now)
+    }
+
+    /// Exact-output swap: buys exactly `amount_out` tokens for as little input as possible
+    /// See `Config::swap_exact_out` for how the required input is derived
+    ///
+    /// # Arguments
+    /// * `is_x` - true if swapping token X for Y, false if swapping Y for X
+    /// * `amount_out` - Exact amount of output tokens to receive, net of transfer fees
+    /// * `max_amount_in` - Maximum amount of input tokens to spend, transfer fee included (slippage protection)
+    /// * `expiration` - Unix timestamp after which the swap is rejected
+    /// * `native_sol` - Pay or receive native SOL for the wrapped SOL side
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn swap_exact_out(
+        &mut self,
+        is_x: bool,
+        amount_out: u64,
+        max_amount_in: u64,
+        expiration: i64,
+        native_sol: bool,
+    ) -> Result<()> {
+        // Ensure user is buying a positive amount
+        require!(amount_out > 0, AmmError::InvalidAmount);
+
+        // Validate the pool state and get the current reserves
+        let (now, reserve_x, reserve_y) = self.prepare_swap(expiration)?;
+
+        let (mint_in, mint_out) = self.mints(is_x);
+
+        // The vault sends enough for `amount_out` to reach the user after any transfer fee
+        let withdraw = amount_to_send(&mint_out, amount_out)?;
+
+        // Derive the required input and price it on the pool's curve
+        let fee_bps = self.config.swap_fee(now);
+        let swap_result = self
+            .config
+            .swap_exact_out(reserve_x, reserve_y, is_x, withdraw, u64::MAX, now)?;
+
+        // Slippage protection on what the user pays, transfer fee included
+        let amount_in = amount_to_send(&mint_in, swap_result.deposit)?;
+        require!(amount_in <= max_amount_in, AmmError::SlippageExceeded);
+
+        // Execute the swap by depositing input tokens and withdrawing output tokens
+        let (native_x, native_y) = native_sides(native_sol, &self.user_ata_x, &self.user_ata_y)?;
+        if (is_x && native_x) || (!is_x && native_y) {
+            self.wrap_sol(is_x, amount_in)?;                 // Wrap the SOL paid in
+        }
+        self.deposit_token(is_x, amount_in)?;                // Deposit input tokens
+        self.withdraw_token(!is_x, swap_result.withdraw)?;   // Withdraw output tokens
+        let referral_fee = self.pay_referrer(is_x, swap_result.fee)?;  // Pay the referrer's share
+        self.unwrap_sol(native_x, native_y)?;                // Hand wrapped SOL back as lamports
+
+        self.finish_swap(is_x, fee_bps, &swap_result, referral_fee, now)
+    }
+
+    /// Records the post-trade reserves in the oracle and emits a `Swapped` event carrying them
+    ///
+    /// # Arguments
+    /// * `is_x` - true if token X was sold for Y, false if Y was sold for X
+    /// * `fee_bps` - Swap fee rate the swap was priced with
+    /// * `swap_result` - Executed swap
+    /// * `referral_fee` - Share of the fee paid to the referrer
+    /// * `now` - Current unix timestamp
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    fn finish_swap(
+        &mut self,
+        is_x: bool,
+        fee_bps: u16,
+        swap_result: &SwapResult,
+        referral_fee: u64,
+        now: i64,
+    ) -> Result<()> {
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+
+        // The TWAP prices the time until the next trade with the new reserves
+        self.oracle.update(reserve_x, reserve_y, now)?;
+
+        emit!(Swapped {
+            seed: self.config.seed,
+            user: self.user.key(),
+            is_x,
+            amount_in: swap_result.deposit,
+            amount_out: swap_result.withdraw,
+            fee: swap_result.fee,
+            fee_bps,
+            referrer: self.referrer_token_account.as_ref().map(|referrer| referrer.owner),
+            referral_fee,
+            reserve_x,
+            reserve_y,
+        });
+        Ok(())
+    }
+
+    /// Pays the referrer's share of a swap fee out of the input vault
+    ///
+    /// # Arguments
+    /// * `is_x` - true if the fee was paid in token X, false for token Y
+    /// * `fee` - Total fee charged on the swap
+    ///
+    /// # Returns
+    /// * `Result<u64>` - Amount paid to the referrer, zero without one
+    fn pay_referrer(&mut self, is_x: bool, fee: u64) -> Result<u64> {
+        let Some(referrer) = self.referrer_token_account.as_ref() else {
+            return Ok(0);
+        };
+        let mint_in = match is_x {
+            true => self.mint_x.key(),
+            false => self.mint_y.key(),
+        };
+        require_keys_eq!(referrer.mint, mint_in, AmmError::InvalidReferrer);
+        // Only catches passing one's own account by mistake: an account of a second
+        // wallet gets the share all the same, so any trader can take it as a discount
+        require_keys_neq!(referrer.owner, self.user.key(), AmmError::SelfReferral);
 
+        let referral_fee = self.config.referral_share(fee)?;
+        if referral_fee > 0 {
+            let to = referrer.to_account_info();
+            self.transfer_from_vault(is_x, to, referral_fee)?;
+        }
+        Ok(referral_fee)
+    }
+
+    /// Wraps native SOL of the user into their wrapped SOL account
+    ///
+    /// # Arguments
+    /// * `is_x` - true if token X is wrapped SOL, false if token Y is
+    /// * `amount` - Lamports to wrap
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    fn wrap_sol(&self, is_x: bool, amount: u64) -> Result<()> {
+        let (user_ata, token_program) = match is_x {
+            true => (self.user_ata_x.to_account_info(), self.token_program_x.to_account_info()),
+            false => (self.user_ata_y.to_account_info(), self.token_program_y.to_account_info()),
+        };
+        wrap_sol(
+            self.user.to_account_info(),
+            user_ata,
+            self.system_program.to_account_info(),
+            token_program,
+            amount,
+        )
+    }
+
+    /// Closes the user's wrapped SOL accounts, returning their balance and rent as lamports
+    ///
+    /// # Arguments
+    /// * `native_x` - Whether token X is traded in native SOL
+    /// * `native_y` - Whether token Y is traded in native SOL
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    fn unwrap_sol(&self, native_x: bool, native_y: bool) -> Result<()> {
+        if native_x {
+            unwrap_sol(
+                self.user.to_account_info(),
+                self.user_ata_x.to_account_info(),
+                self.token_program_x.to_account_info(),
+            )?;
+        }
+        if native_y {
+            unwrap_sol(
+                self.user.to_account_info(),
+                self.user_ata_y.to_account_info(),
+                self.token_program_y.to_account_info(),
+            )?;
+        }
         Ok(())
     }
 
+    /// Mints of the input and output tokens of a swap
+    ///
+    /// # Arguments
+    /// * `is_x` - true if swapping token X for Y, false if swapping Y for X
+    ///
+    /// # Returns
+    /// * `(AccountInfo, AccountInfo)` - Input mint and output mint
+    fn mints(&self, is_x: bool) -> (AccountInfo<'info>, AccountInfo<'info>) {
+        match is_x {
+            true => (self.mint_x.to_account_info(), self.mint_y.to_account_info()),
+            false => (self.mint_y.to_account_info(), self.mint_x.to_account_info()),
+        }
+    }
+
+    /// Checks shared by both swap modes and records the pre-swap price in the price band
+    ///
+    /// # Arguments
+    /// * `expiration` - Unix timestamp after which the swap is rejected
+    ///
+    /// # Returns
+    /// * `Result<(i64, u64, u64)>` - Current unix timestamp and pool reserves (X, Y)
+    fn prepare_swap(&mut self, expiration: i64) -> Result<(i64, u64, u64)> {
+        // Reject transactions that sat around long enough for the price to go stale
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        require!(now <= expiration, AmmError::OfferExpired);
+        // Ensure the pool is not locked for swaps
+        require!(!self.config.locked, AmmError::PoolLocked);
+        // Vault balances are borrowed out while a flash loan is in progress
+        require!(!self.config.flash_loan_active, AmmError::FlashLoanActive);
+        // Permissioned pools only trade with admitted users
+        check_trader(
+            &self.config,
+            self.user.key,
+            self.allowlist_entry.as_deref().map(|entry| &**entry),
+            self.instructions.as_deref(),
+        )?;
+
+        // Pool reserves exclude protocol fees waiting in the vaults
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+
+        // Record the pre-swap price in the price band
+        self.config.open_price_band(reserve_x, reserve_y, clock.slot);
+
+        Ok((now, reserve_x, reserve_y))
+    }
+
     /// Deposits tokens from user's account to the appropriate vault
     /// This increases the vault's balance and decreases the user's balance
     /// 
@@ -173,23 +449,23 @@ impl<'info> Swap<'info> {
     /// * `Result<()>` - Ok if successful, error otherwise
     pub fn deposit_token(&mut self, is_x: bool, amount: u64) -> Result<()> {
         // Select appropriate accounts based on token type
-        let (from, to, mint, decimals) = match is_x {
+        let (from, to, mint, decimals, cpi_program) = match is_x {
             true => (
                 self.user_ata_x.to_account_info(),    // Transfer from user's X account
                 self.vault_x.to_account_info(),       // Transfer to vault X
                 self.mint_x.to_account_info(),        // Token X mint
                 self.mint_x.decimals,                 // Token X decimals
+                self.token_program_x.to_account_info(),
             ),
             false => (
                 self.user_ata_y.to_account_info(),    // Transfer from user's Y account
                 self.vault_y.to_account_info(),       // Transfer to vault Y
                 self.mint_y.to_account_info(),        // Token Y mint
                 self.mint_y.decimals,                 // Token Y decimals
+                self.token_program_y.to_account_info(),
             ),
         };
 
-        let cpi_program = self.token_program.to_account_info();
-
         // Set up transfer instruction accounts
         let cpi_accounts = TransferChecked {
             from,
@@ -214,24 +490,39 @@ impl<'info> Swap<'info> {
     /// # Returns
     /// * `Result<()>` - Ok if successful, error otherwise
     pub fn withdraw_token(&mut self, is_x: bool, amount: u64) -> Result<()> {
+        let to = match is_x {
+            true => self.user_ata_x.to_account_info(),   // Transfer to user's X account
+            false => self.user_ata_y.to_account_info(),  // Transfer to user's Y account
+        };
+        self.transfer_from_vault(is_x, to, amount)
+    }
+
+    /// Transfers tokens out of a vault, signed by the config PDA
+    ///
+    /// # Arguments
+    /// * `is_x` - true for token X, false for token Y
+    /// * `to` - Token account receiving the tokens
+    /// * `amount` - Amount of tokens to send
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    fn transfer_from_vault(&self, is_x: bool, to: AccountInfo<'info>, amount: u64) -> Result<()> {
         // Select appropriate accounts based on token type
-        let (from, to, mint, decimals) = match is_x {
+        let (from, mint, decimals, cpi_program) = match is_x {
             true => (
                 self.vault_x.to_account_info(),       // Transfer from vault X
-                self.user_ata_x.to_account_info(),    // Transfer to user's X account
                 self.mint_x.to_account_info(),        // Token X mint
                 self.mint_x.decimals,                 // Token X decimals
+                self.token_program_x.to_account_info(),
             ),
             false => (
                 self.vault_y.to_account_info(),       // Transfer from vault Y
-                self.user_ata_y.to_account_info(),    // Transfer to user's Y account
                 self.mint_y.to_account_info(),        // Token Y mint
                 self.mint_y.decimals,                 // Token Y decimals
+                self.token_program_y.to_account_info(),
             ),
         };
 
-        let cpi_program = self.token_program.to_account_info();
-
         // Set up transfer instruction accounts
         let cpi_accounts = TransferChecked {
             from,
@@ -0,0 +1,272 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::{MAX_AMP, MAX_AMP_CHANGE, MAX_FEE_BPS, MIN_AMP, MIN_PRICE_BAND_BPS, MIN_RAMP_DURATION};
+use crate::curves::CurveType;
+use crate::errors::AmmError;
+use crate::events::ConfigUpdated;
+use crate::states::Config;
+
+/// Accounts required for the authority-gated admin instructions
+/// Used to update the fees, price band and amplification, lock/unlock or permission the pool and hand over or renounce authority
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    /// The current update authority of the pool (must sign the transaction)
+    pub authority: Signer<'info>,
+
+    /// The AMM pool configuration account being updated
+    /// Mutable because admin instructions change its settings
+    /// Uses PDA derived from "config" seed and config.seed
+    #[account(
+        mut,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> UpdateConfig<'info> {
+    /// Updates the swap fee charged by the pool
+    ///
+    /// # Arguments
+    /// * `fee` - New trading fee in basis points (e.g., 30 = 0.30%)
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn update_fee(&mut self, fee: u16) -> Result<()> {
+        self.config.check_authority(self.authority.key)?;
+        // The fee is part of a canonical pool's index key
+        require!(!self.config.canonical, AmmError::CanonicalPoolFee);
+        // A fee above 100% would make every swap fail
+        require!(fee <= MAX_FEE_BPS, AmmError::InvalidFee);
+
+        self.config.fee = fee;
+        emit!(ConfigUpdated::from(&*self.config));
+        Ok(())
+    }
+
+    /// Switches the pool to a dynamic swap fee between `min_fee` and `max_fee`
+    /// The fee rises with recent price movement and falls back as it decays,
+    /// see `Config::swap_fee`
+    ///
+    /// # Arguments
+    /// * `min_fee` - Fee in basis points charged while the price is steady
+    /// * `max_fee` - Highest fee in basis points, however much the price moves
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn update_dynamic_fee(&mut self, min_fee: u16, max_fee: u16) -> Result<()> {
+        self.config.check_authority(self.authority.key)?;
+        // A canonical pool charges the fee tier it is indexed under
+        require!(!self.config.canonical, AmmError::CanonicalPoolFee);
+        require!(min_fee <= max_fee && max_fee <= MAX_FEE_BPS, AmmError::InvalidFee);
+
+        self.config.dynamic_fee = true;
+        self.config.min_fee = min_fee;
+        self.config.max_fee = max_fee;
+        emit!(ConfigUpdated::from(&*self.config));
+        Ok(())
+    }
+
+    /// Switches the pool back to the static `fee`
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn disable_dynamic_fee(&mut self) -> Result<()> {
+        self.config.check_authority(self.authority.key)?;
+
+        self.config.dynamic_fee = false;
+        emit!(ConfigUpdated::from(&*self.config));
+        Ok(())
+    }
+
+    /// Updates the protocol share of the swap fee
+    /// Only affects fees charged from now on, already accrued fees are kept
+    ///
+    /// # Arguments
+    /// * `protocol_fee` - Protocol share of the fee in basis points (10000 = whole fee)
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn update_protocol_fee(&mut self, protocol_fee: u16) -> Result<()> {
+        self.config.check_authority(self.authority.key)?;
+        // The protocol and referral shares both come out of the same fee
+        require!(
+            protocol_fee as u32 + self.config.referral_fee as u32 <= MAX_FEE_BPS as u32,
+            AmmError::InvalidFee
+        );
+
+        self.config.protocol_fee = protocol_fee;
+        emit!(ConfigUpdated::from(&*self.config));
+        Ok(())
+    }
+
+    /// Updates the share of the swap fee paid to referrers
+    /// Taken out of the LP share of the fee, so it caps what a referrer can earn
+    /// without changing what traders pay
+    ///
+    /// # Arguments
+    /// * `referral_fee` - Referrer share of the fee in basis points (10000 = whole fee)
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn update_referral_fee(&mut self, referral_fee: u16) -> Result<()> {
+        self.config.check_authority(self.authority.key)?;
+        // The protocol and referral shares both come out of the same fee
+        require!(
+            referral_fee as u32 + self.config.protocol_fee as u32 <= MAX_FEE_BPS as u32,
+            AmmError::InvalidFee
+        );
+
+        self.config.referral_fee = referral_fee;
+        emit!(ConfigUpdated::from(&*self.config));
+        Ok(())
+    }
+
+    /// Sets the circuit breaker limits on how far swaps can move the price
+    /// Swaps beyond either limit revert, zero turns a limit off
+    /// Limits must be at least `MIN_PRICE_BAND_BPS`, and the per-swap limit can't
+    /// exceed the per-slot one
+    ///
+    /// # Arguments
+    /// * `max_price_impact` - Largest price move of a single swap, in basis points
+    /// * `max_slot_deviation` - Largest price move within one slot, in basis points
+    ///   from the price the slot opened at
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn update_price_band(&mut self, max_price_impact: u16, max_slot_deviation: u16) -> Result<()> {
+        self.config.check_authority(self.authority.key)?;
+        // Limits that tight would reject every swap, zero is the way to turn one off
+        for limit in [max_price_impact, max_slot_deviation] {
+            require!(limit == 0 || limit >= MIN_PRICE_BAND_BPS, AmmError::InvalidPriceBand);
+        }
+        // A single swap can't be allowed further than the whole slot
+        require!(
+            max_price_impact == 0 || max_slot_deviation == 0 || max_price_impact <= max_slot_deviation,
+            AmmError::InvalidPriceBand
+        );
+
+        self.config.max_price_impact = max_price_impact;
+        self.config.max_slot_deviation = max_slot_deviation;
+        emit!(ConfigUpdated::from(&*self.config));
+        Ok(())
+    }
+
+    /// Starts moving the StableSwap amplification coefficient towards `target_amp`
+    /// The coefficient changes linearly from its current value until `ramp_stop`,
+    /// so the curve never jumps and LPs can react to the change
+    ///
+    /// # Arguments
+    /// * `target_amp` - Amplification coefficient at the end of the ramp
+    /// * `ramp_stop` - Unix timestamp the ramp ends at
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn ramp_amp(&mut self, target_amp: u64, ramp_stop: i64) -> Result<()> {
+        self.config.check_authority(self.authority.key)?;
+        require!(self.config.curve_type == CurveType::StableSwap, AmmError::InvalidCurveType);
+        require!((MIN_AMP..=MAX_AMP).contains(&target_amp), AmmError::InvalidAmp);
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(ramp_stop >= now.saturating_add(MIN_RAMP_DURATION), AmmError::InvalidRamp);
+
+        // Bound how far a single ramp can move the coefficient
+        let current_amp = self.config.amp(now);
+        require!(
+            target_amp <= current_amp * MAX_AMP_CHANGE && target_amp * MAX_AMP_CHANGE >= current_amp,
+            AmmError::InvalidRamp
+        );
+
+        self.config.initial_amp = current_amp;
+        self.config.target_amp = target_amp;
+        self.config.ramp_start = now;
+        self.config.ramp_stop = ramp_stop;
+        emit!(ConfigUpdated::from(&*self.config));
+        Ok(())
+    }
+
+    /// Stops any amplification ramp in progress, freezing the current coefficient
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn stop_ramp_amp(&mut self) -> Result<()> {
+        self.config.check_authority(self.authority.key)?;
+        require!(self.config.curve_type == CurveType::StableSwap, AmmError::InvalidCurveType);
+
+        let now = Clock::get()?.unix_timestamp;
+        let current_amp = self.config.amp(now);
+
+        self.config.initial_amp = current_amp;
+        self.config.target_amp = current_amp;
+        self.config.ramp_start = now;
+        self.config.ramp_stop = now;
+        emit!(ConfigUpdated::from(&*self.config));
+        Ok(())
+    }
+
+    /// Locks or unlocks the pool
+    /// While locked, deposits, withdrawals and swaps are rejected
+    ///
+    /// # Arguments
+    /// * `locked` - true to pause the pool, false to resume it
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn set_locked(&mut self, locked: bool) -> Result<()> {
+        self.config.check_authority(self.authority.key)?;
+
+        self.config.locked = locked;
+        emit!(ConfigUpdated::from(&*self.config));
+        Ok(())
+    }
+
+    /// Restricts swaps and deposits to allowlisted traders, or opens the pool up again
+    /// Withdrawals stay open to every LP either way
+    ///
+    /// # Arguments
+    /// * `permissioned` - true to require an allowlist entry or an attestation
+    /// * `gatekeeper` - Key whose ed25519 attestations admit traders, `None` to
+    ///   only accept allowlist entries
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn set_permissioned(&mut self, permissioned: bool, gatekeeper: Option<Pubkey>) -> Result<()> {
+        self.config.check_authority(self.authority.key)?;
+
+        self.config.permissioned = permissioned;
+        self.config.gatekeeper = gatekeeper.unwrap_or_default();
+        emit!(ConfigUpdated::from(&*self.config));
+        Ok(())
+    }
+
+    /// Nominates a new authority for the pool (step one of a two-step transfer)
+    /// The nominee must call `accept_authority` before the transfer takes effect,
+    /// nominating again overwrites any previous pending transfer
+    ///
+    /// # Arguments
+    /// * `new_authority` - Key that will be allowed to accept the authority
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn transfer_authority(&mut self, new_authority: Pubkey) -> Result<()> {
+        self.config.check_authority(self.authority.key)?;
+
+        self.config.pending_authority = Some(new_authority);
+        emit!(ConfigUpdated::from(&*self.config));
+        Ok(())
+    }
+
+    /// Permanently removes the update authority
+    /// After this the fee and lock state can never be changed again
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn renounce_authority(&mut self) -> Result<()> {
+        self.config.check_authority(self.authority.key)?;
+
+        self.config.authority = None;
+        self.config.pending_authority = None;
+        emit!(ConfigUpdated::from(&*self.config));
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+use anchor_lang::prelude::*;
+
+use crate::events::FarmUpdated;
+use crate::states::{Config, Farm};
+
+/// Accounts required for changing the emission rate of a farm
+#[derive(Accounts)]
+pub struct UpdateFarm<'info> {
+    /// The update authority of the pool (must sign the transaction)
+    pub authority: Signer<'info>,
+
+    /// The AMM pool configuration account the farm belongs to
+    /// Uses PDA derived from "config" seed and config.seed
+    #[account(
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    /// The farm account of the pool
+    /// Mutable because the emission rate and accumulator change
+    /// Uses PDA derived from "farm" seed and config pubkey
+    #[account(
+        mut,
+        seeds = [b"farm", config.key().as_ref()],
+        bump = farm.bump,
+        has_one = config,
+    )]
+    pub farm: Box<Account<'info, Farm>>,
+}
+
+impl<'info> UpdateFarm<'info> {
+    /// Changes the rewards emitted per second
+    /// Rewards earned until now are accrued at the old rate first
+    ///
+    /// # Arguments
+    /// * `reward_per_second` - New emission rate, zero to stop emissions
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn set_reward_rate(&mut self, reward_per_second: u64) -> Result<()> {
+        self.config.check_authority(self.authority.key)?;
+
+        self.farm.update(Clock::get()?.unix_timestamp)?;
+        self.farm.reward_per_second = reward_per_second;
+
+        emit!(FarmUpdated {
+            seed: self.config.seed,
+            farm: self.farm.key(),
+            reward_mint: self.farm.reward_mint,
+            reward_per_second,
+        });
+        Ok(())
+    }
+}
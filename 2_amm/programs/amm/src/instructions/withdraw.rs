@@ -1,12 +1,14 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{burn, transfer_checked, Burn, Mint, Token, TokenAccount, TransferChecked},
+    token_interface::{burn, transfer_checked, Burn, Mint, TokenAccount, TokenInterface, TransferChecked},
 };
-use constant_product_curve::ConstantProduct;
 
 use crate::errors::AmmError;
-use crate::states::Config;
+use crate::events::LiquidityRemoved;
+use crate::native_sol::{native_sides, unwrap_sol};
+use crate::transfer_fee::amount_received;
+use crate::states::{Config, Oracle};
 
 /// Accounts required for withdrawing liquidity from the AMM pool
 /// This struct defines all the accounts needed to perform a liquidity withdrawal operation
@@ -18,13 +20,13 @@ pub struct Withdraw<'info> {
 
     /// The mint account for token X in the trading pair
     /// Immutable as we only need to read mint information for transfers
-    #[account(mint::token_program = token_program)]
-    pub mint_x: Account<'info, Mint>,
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
 
     /// The mint account for token Y in the trading pair
     /// Immutable as we only need to read mint information for transfers
-    #[account(mint::token_program = token_program)]
-    pub mint_y: Account<'info, Mint>,
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
 
     /// The AMM pool configuration account
     /// Contains pool settings and references to the token mints
@@ -37,6 +39,16 @@ pub struct Withdraw<'info> {
     )]
     pub config: Account<'info, Config>,
 
+    /// TWAP price oracle of the pool
+    /// Mutable because every trade updates its price accumulators
+    /// Uses PDA derived from "oracle" seed and config pubkey
+    #[account(
+        mut,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.bump,
+    )]
+    pub oracle: Box<Account<'info, Oracle>>,
+
     /// The LP (Liquidity Provider) token mint
     /// Mutable because we need to burn LP tokens from the user
     /// Uses PDA derived from "lp" seed and config pubkey
@@ -45,7 +57,7 @@ pub struct Withdraw<'info> {
         seeds = [b"lp", config.key().as_ref()],
         bump = config.lp_bump
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
 
     /// The vault that holds all deposited token X
     /// Mutable because we're withdrawing tokens from it
@@ -54,9 +66,9 @@ pub struct Withdraw<'info> {
         mut,
         associated_token::mint = mint_x,
         associated_token::authority = config,
-        associated_token::token_program = token_program,
+        associated_token::token_program = token_program_x,
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
 
     /// The vault that holds all deposited token Y
     /// Mutable because we're withdrawing tokens from it
@@ -65,29 +77,35 @@ pub struct Withdraw<'info> {
         mut,
         associated_token::mint = mint_y,
         associated_token::authority = config,
-        associated_token::token_program = token_program,
+        associated_token::token_program = token_program_y,
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
 
     /// User's token account for token X
+    /// Will be created if it doesn't exist (the temporary wrapped SOL account
+    /// when trading native SOL), user pays for creation
     /// Mutable because we're transferring tokens to it
     #[account(
-        mut,
+        init_if_needed,
+        payer = user,
         associated_token::mint = mint_x,
         associated_token::authority = user,
-        associated_token::token_program = token_program
+        associated_token::token_program = token_program_x
     )]
-    pub user_ata_x: Account<'info, TokenAccount>,
+    pub user_ata_x: Box<InterfaceAccount<'info, TokenAccount>>,
 
     /// User's token account for token Y
+    /// Will be created if it doesn't exist (the temporary wrapped SOL account
+    /// when trading native SOL), user pays for creation
     /// Mutable because we're transferring tokens to it
     #[account(
-        mut,
+        init_if_needed,
+        payer = user,
         associated_token::mint = mint_y,
         associated_token::authority = user,
-        associated_token::token_program = token_program
+        associated_token::token_program = token_program_y
     )]
-    pub user_ata_y: Account<'info, TokenAccount>,
+    pub user_ata_y: Box<InterfaceAccount<'info, TokenAccount>>,
 
     /// User's token account for LP tokens
     /// Will be created if it doesn't exist, user pays for creation
@@ -99,10 +117,14 @@ pub struct Withdraw<'info> {
         associated_token::authority = user,
         associated_token::token_program = token_program
     )]
-    pub user_ata_lp: Account<'info, TokenAccount>,
+    pub user_ata_lp: Box<InterfaceAccount<'info, TokenAccount>>,
 
-    /// SPL Token program for token operations
-    pub token_program: Program<'info, Token>,
+    /// Token program of the LP mint
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Token program of token X, SPL Token or Token-2022
+    pub token_program_x: Interface<'info, TokenInterface>,
+    /// Token program of token Y, SPL Token or Token-2022
+    pub token_program_y: Interface<'info, TokenInterface>,
     /// Associated Token program for ATA operations
     pub associated_token_program: Program<'info, AssociatedToken>,
     /// System program for account creation
@@ -118,51 +140,88 @@ impl<'info> Withdraw<'info> {
     /// 3. Validate slippage protection (amounts meet minimum requirements)
     /// 4. Burn the LP tokens from user's account
     /// 5. Transfer proportional amounts of both tokens to user
+    /// 6. In native SOL mode, close the user's wrapped SOL account so it pays out as lamports
     /// 
     /// # Arguments
     /// * `amount` - Amount of LP tokens to burn
-    /// * `min_x` - Minimum amount of token X user expects to receive
-    /// * `min_y` - Minimum amount of token Y user expects to receive
+    /// * `min_x` - Minimum amount of token X user expects to receive, net of transfer fees
+    /// * `min_y` - Minimum amount of token Y user expects to receive, net of transfer fees
+    /// * `expiration` - Unix timestamp after which the withdrawal is rejected
+    /// * `native_sol` - Receive native SOL for the wrapped SOL side
     /// 
     /// # Returns
     /// * `Result<()>` - Ok if successful, error otherwise
-    pub fn withdraw(&mut self, amount: u64, min_x: u64, min_y: u64) -> Result<()> {
+    pub fn withdraw(
+        &mut self,
+        amount: u64,
+        min_x: u64,
+        min_y: u64,
+        expiration: i64,
+        native_sol: bool,
+    ) -> Result<()> {
+        // Reject transactions that sat around long enough for the price to go stale
+        let now = Clock::get()?.unix_timestamp;
+        require!(now <= expiration, AmmError::OfferExpired);
         // Ensure the pool is not locked for withdrawals
         require!(self.config.locked == false, AmmError::PoolLocked);
+        // Vault balances are borrowed out while a flash loan is in progress
+        require!(!self.config.flash_loan_active, AmmError::FlashLoanActive);
         // Ensure user is requesting to burn some LP tokens
         require!(amount != 0, AmmError::InvalidAmount);
 
+        // Pool reserves exclude protocol fees waiting in the vaults
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+
         // Calculate token amounts to withdraw based on current pool state
-        let (x, y) = match self.mint_lp.supply == 0
-            && self.vault_x.amount == 0
-            && self.vault_y.amount == 0
-        {
+        let (x, y) = match self.mint_lp.supply == 0 && reserve_x == 0 && reserve_y == 0 {
             // Edge case: if pool is completely empty, use minimum amounts
             // This shouldn't happen in normal operation but provides safety
             true => (min_x, min_y),
             // Normal case: calculate proportional amounts based on LP token share
-            false => {
-                let amounts = ConstantProduct::xy_withdraw_amounts_from_l(
-                    self.vault_x.amount,   // Current vault X balance
-                    self.vault_y.amount,   // Current vault Y balance
-                    self.mint_lp.supply,   // Current LP token supply
-                    amount,                // LP tokens being burned
-                    6,                     // Precision for calculations
-                )
-                .unwrap();
-                (amounts.x, amounts.y)
-            }
+            false => self.config.curve(now).withdraw_amounts(
+                reserve_x,             // Current X reserves
+                reserve_y,             // Current Y reserves
+                self.mint_lp.supply,   // Current LP token supply
+                amount,                // LP tokens being burned
+            )?,
         };
 
-        // Slippage protection: ensure calculated amounts meet user's minimum requirements
-        require!(x >= min_x && y >= min_y, AmmError::SlippageExceeded);
+        // Slippage protection: ensure the amounts reaching the user, net of any
+        // transfer fee, meet user's minimum requirements
+        require!(
+            amount_received(&self.mint_x.to_account_info(), x)? >= min_x
+                && amount_received(&self.mint_y.to_account_info(), y)? >= min_y,
+            AmmError::SlippageExceeded
+        );
+
+        // Sides paid out in native SOL, checked before any tokens move
+        let (native_x, native_y) = native_sides(native_sol, &self.user_ata_x, &self.user_ata_y)?;
 
         // Burn LP tokens from user's account first
         self.burn_lp_tokens(amount)?;
 
         // Transfer calculated amounts of both tokens to user
         self.withdraw_tokens(x, true)?;   // Transfer token X
-        self.withdraw_tokens(y, false)    // Transfer token Y
+        self.withdraw_tokens(y, false)?;  // Transfer token Y
+
+        // Hand wrapped SOL back as lamports, in native SOL mode
+        self.unwrap_sol(native_x, native_y)?;
+
+        // Record the post-withdrawal reserves in the TWAP oracle
+        self.oracle.update(reserve_x - x, reserve_y - y, now)?;
+
+        emit!(LiquidityRemoved {
+            seed: self.config.seed,
+            user: self.user.key(),
+            amount_x: x,
+            amount_y: y,
+            liquidity: amount,
+            reserve_x: reserve_x - x,
+            reserve_y: reserve_y - y,
+        });
+        Ok(())
     }
 
     /// Burns LP tokens from the user's account
@@ -207,23 +266,23 @@ impl<'info> Withdraw<'info> {
     /// * `Result<()>` - Ok if successful, error otherwise
     pub fn withdraw_tokens(&mut self, amount: u64, is_x: bool) -> Result<()> {
         // Select appropriate accounts based on token type
-        let (from, to, mint, decimals) = match is_x {
+        let (from, to, mint, decimals, cpi_program) = match is_x {
             true => (
                 self.vault_x.to_account_info(),      // Transfer from vault X
                 self.user_ata_x.to_account_info(),   // Transfer to user's X account
                 self.mint_x.to_account_info(),       // Token X mint
                 self.mint_x.decimals,                // Token X decimals
+                self.token_program_x.to_account_info(),
             ),
             false => (
                 self.vault_y.to_account_info(),      // Transfer from vault Y
                 self.user_ata_y.to_account_info(),   // Transfer to user's Y account
                 self.mint_y.to_account_info(),       // Token Y mint
                 self.mint_y.decimals,                // Token Y decimals
+                self.token_program_y.to_account_info(),
             ),
         };
 
-        let cpi_program = self.token_program.to_account_info();
-
         // Set up transfer instruction accounts
         let cpi_accounts = TransferChecked {
             from,
@@ -245,4 +304,30 @@ impl<'info> Withdraw<'info> {
         // Execute the transfer with amount and decimal validation
         transfer_checked(cpi_context, amount, decimals)
     }
-}
\ No newline at end of file
+
+    /// Closes the user's wrapped SOL accounts, returning their balance and rent as lamports
+    ///
+    /// # Arguments
+    /// * `native_x` - Whether token X is traded in native SOL
+    /// * `native_y` - Whether token Y is traded in native SOL
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    fn unwrap_sol(&self, native_x: bool, native_y: bool) -> Result<()> {
+        if native_x {
+            unwrap_sol(
+                self.user.to_account_info(),
+                self.user_ata_x.to_account_info(),
+                self.token_program_x.to_account_info(),
+            )?;
+        }
+        if native_y {
+            unwrap_sol(
+                self.user.to_account_info(),
+                self.user_ata_y.to_account_info(),
+                self.token_program_y.to_account_info(),
+            )?;
+        }
+        Ok(())
+    }
+}
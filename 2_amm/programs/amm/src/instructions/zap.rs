@@ -0,0 +1,524 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions as sysvar_instructions;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        burn, mint_to, transfer_checked, Burn, Mint, MintTo, TokenAccount, TokenInterface, TransferChecked,
+    },
+};
+
+use crate::errors::AmmError;
+use crate::events::{LiquidityAdded, LiquidityRemoved, Swapped};
+use crate::math::{amounts_for_liquidity, liquidity_for_amounts};
+use crate::permissions::check_trader;
+use crate::states::{AllowlistEntry, Config, Oracle};
+use crate::transfer_fee::{amount_received, amount_to_send};
+
+/// Accounts required for single-sided liquidity operations ("zaps")
+/// Used both to provide liquidity from one token and to remove it into one token
+#[derive(Accounts)]
+pub struct Zap<'info> {
+    /// The user who is zapping (must sign the transaction)
+    /// Mutable because they may need to pay for ATA creation if accounts don't exist
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// The mint account for token X in the trading pair
+    /// Immutable as we only need to read mint information for transfers
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The mint account for token Y in the trading pair
+    /// Immutable as we only need to read mint information for transfers
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The AMM pool configuration account
+    /// Mutable because the protocol share of the swap fee is accrued here
+    /// Uses PDA derived from "config" seed and config.seed
+    #[account(
+        mut,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        has_one = mint_x,  // Ensures mint_x matches the one in config
+        has_one = mint_y,  // Ensures mint_y matches the one in config
+    )]
+    pub config: Account<'info, Config>,
+
+    /// TWAP price oracle of the pool
+    /// Mutable because every trade updates its price accumulators
+    /// Uses PDA derived from "oracle" seed and config pubkey
+    #[account(
+        mut,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.bump,
+    )]
+    pub oracle: Box<Account<'info, Oracle>>,
+
+    /// The LP (Liquidity Provider) token mint
+    /// Mutable because LP tokens are minted or burned
+    /// Uses PDA derived from "lp" seed and config pubkey
+    #[account(
+        mut,
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The vault that holds all deposited token X
+    /// Associated token account owned by the config PDA
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x,
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The vault that holds all deposited token Y
+    /// Associated token account owned by the config PDA
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y,
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// User's token account for token X
+    /// Will be created if it doesn't exist, since a zap may only return the other token
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint_x,
+        associated_token::authority = user,
+        associated_token::token_program = token_program_x
+    )]
+    pub user_ata_x: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// User's token account for token Y
+    /// Will be created if it doesn't exist, since a zap may only return the other token
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint_y,
+        associated_token::authority = user,
+        associated_token::token_program = token_program_y
+    )]
+    pub user_ata_y: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// User's token account for LP tokens
+    /// Will be created if it doesn't exist, user pays for creation
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint_lp,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_ata_lp: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The user's allowlist entry, only needed in permissioned pools
+    /// Uses PDA derived from "allowlist" seed, config and user pubkeys
+    #[account(
+        seeds = [b"allowlist", config.key().as_ref(), user.key().as_ref()],
+        bump = allowlist_entry.bump,
+    )]
+    pub allowlist_entry: Option<Box<Account<'info, AllowlistEntry>>>,
+
+    /// Instructions sysvar, only needed to present a gatekeeper attestation
+    /// CHECK: address is checked against the sysvar id
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+
+    /// Token program of the LP mint
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Token program of token X, SPL Token or Token-2022
+    pub token_program_x: Interface<'info, TokenInterface>,
+    /// Token program of token Y, SPL Token or Token-2022
+    pub token_program_y: Interface<'info, TokenInterface>,
+    /// Associated Token program for ATA operations
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    /// System program for account creation
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Zap<'info> {
+    /// Provides liquidity from a single token
+    ///
+    /// Swaps the fraction of the input that balances the rest against the pool
+    /// ratio, then deposits both sides for LP tokens. Only the net amounts move:
+    /// the input side is taken from the user once, and whatever part of the swap
+    /// output doesn't fit the deposit is sent back. Input that doesn't fit stays
+    /// with the user.
+    ///
+    /// # Arguments
+    /// * `is_x` - true if depositing token X, false if depositing token Y
+    /// * `amount_in` - Amount of the single input token, transfer fee included
+    /// * `min_lp_out` - Minimum amount of LP tokens to receive (slippage protection)
+    /// * `expiration` - Unix timestamp after which the zap is rejected
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn zap_in(
+        &mut self,
+        is_x: bool,
+        amount_in: u64,
+        min_lp_out: u64,
+        expiration: i64,
+    ) -> Result<()> {
+        // Ensure user is depositing a positive amount
+        require!(amount_in > 0, AmmError::InvalidAmount);
+        let (now, reserve_x, reserve_y) = self.prepare_zap(expiration)?;
+
+        // Everything is priced on what the vault can receive, net of any transfer fee
+        let mint_in = match is_x {
+            true => self.mint_x.to_account_info(),
+            false => self.mint_y.to_account_info(),
+        };
+        let received = amount_received(&mint_in, amount_in)?;
+
+        // Swap just enough of the input for the rest to match the pool ratio
+        let fee_bps = self.config.swap_fee(now);
+        let swap_amount = self.config.curve(now).zap_swap_amount(
+            reserve_x,
+            reserve_y,
+            is_x,
+            received,
+            fee_bps,
+        )?;
+        let swap_result = self
+            .config
+            .swap_exact_in(reserve_x, reserve_y, is_x, swap_amount, 0, now)?;
+
+        // Reserves once the swap leg has settled (net of the protocol fee it accrued)
+        let (vault_x, vault_y, held_x, held_y) = match is_x {
+            true => (
+                self.vault_x.amount + swap_result.deposit,
+                self.vault_y.amount - swap_result.withdraw,
+                received - swap_result.deposit,
+                swap_result.withdraw,
+            ),
+            false => (
+                self.vault_x.amount - swap_result.withdraw,
+                self.vault_y.amount + swap_result.deposit,
+                swap_result.withdraw,
+                received - swap_result.deposit,
+            ),
+        };
+        let (reserve_x, reserve_y) = self.config.reserves(vault_x, vault_y)?;
+        emit!(Swapped {
+            seed: self.config.seed,
+            user: self.user.key(),
+            is_x,
+            amount_in: swap_result.deposit,
+            amount_out: swap_result.withdraw,
+            fee: swap_result.fee,
+            fee_bps,
+            referrer: None,
+            referral_fee: 0,
+            reserve_x,
+            reserve_y,
+        });
+
+        // Deposit as much of both sides as the new pool ratio allows
+        let supply = self.mint_lp.supply;
+        let liquidity = liquidity_for_amounts(held_x, held_y, reserve_x, reserve_y, supply)?;
+        require!(liquidity != 0, AmmError::InvalidAmount);
+        require!(liquidity >= min_lp_out, AmmError::SlippageExceeded);
+        let (x, y) = amounts_for_liquidity(liquidity, reserve_x, reserve_y, supply)?;
+
+        // Net transfers: swap input plus deposit in, unused swap output back out
+        let (amount_deposited, leftover) = match is_x {
+            true => (swap_result.deposit + x, held_y - y),
+            false => (swap_result.deposit + y, held_x - x),
+        };
+        self.deposit_token(is_x, amount_to_send(&mint_in, amount_deposited)?)?;
+        if leftover > 0 {
+            self.withdraw_token(!is_x, leftover)?;
+        }
+
+        self.mint_lp_tokens(liquidity)?;
+
+        // Record the post-zap reserves in the TWAP oracle
+        self.oracle.update(reserve_x + x, reserve_y + y, now)?;
+
+        emit!(LiquidityAdded {
+            seed: self.config.seed,
+            user: self.user.key(),
+            amount_x: x,
+            amount_y: y,
+            liquidity,
+            reserve_x: reserve_x + x,
+            reserve_y: reserve_y + y,
+        });
+        Ok(())
+    }
+
+    /// Removes liquidity into a single token
+    ///
+    /// Withdraws both sides for the burned LP tokens and swaps the unwanted side
+    /// back into the pool, so only the wanted token leaves the vaults.
+    ///
+    /// # Arguments
+    /// * `is_x` - true to receive token X, false to receive token Y
+    /// * `amount` - Amount of LP tokens to burn
+    /// * `min_amount_out` - Minimum amount of the wanted token to receive, net of transfer fees (slippage protection)
+    /// * `expiration` - Unix timestamp after which the zap is rejected
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn zap_out(
+        &mut self,
+        is_x: bool,
+        amount: u64,
+        min_amount_out: u64,
+        expiration: i64,
+    ) -> Result<()> {
+        // Ensure user is burning some LP tokens
+        require!(amount != 0, AmmError::InvalidAmount);
+        let (now, reserve_x, reserve_y) = self.prepare_zap(expiration)?;
+
+        // Proportional share of both reserves, same as a regular withdrawal
+        let (x, y) = self.config.curve(now).withdraw_amounts(
+            reserve_x,
+            reserve_y,
+            self.mint_lp.supply,
+            amount,
+        )?;
+
+        // Sell the unwanted side to the pool as it stands after the withdrawal
+        let (kept, sold) = match is_x {
+            true => (x, y),
+            false => (y, x),
+        };
+        let fee_bps = self.config.swap_fee(now);
+        let swap_result = match sold {
+            0 => None,
+            sold => Some(
+                self.config
+                    .swap_exact_in(reserve_x - x, reserve_y - y, !is_x, sold, 0, now)?,
+            ),
+        };
+        let bought = swap_result.as_ref().map_or(0, |swap_result| swap_result.withdraw);
+
+        // Slippage protection on the total amount of the wanted token reaching the
+        // user, net of any transfer fee
+        let amount_out = kept + bought;
+        let mint_out = match is_x {
+            true => self.mint_x.to_account_info(),
+            false => self.mint_y.to_account_info(),
+        };
+        require!(
+            amount_received(&mint_out, amount_out)? >= min_amount_out,
+            AmmError::SlippageExceeded
+        );
+
+        self.burn_lp_tokens(amount)?;
+        self.withdraw_token(is_x, amount_out)?;
+
+        emit!(LiquidityRemoved {
+            seed: self.config.seed,
+            user: self.user.key(),
+            amount_x: x,
+            amount_y: y,
+            liquidity: amount,
+            reserve_x: reserve_x - x,
+            reserve_y: reserve_y - y,
+        });
+        if let Some(swap_result) = swap_result {
+            self.vault_x.reload()?;
+            self.vault_y.reload()?;
+            let (reserve_x, reserve_y) = self
+                .config
+                .reserves(self.vault_x.amount, self.vault_y.amount)?;
+            // Record the post-zap reserves in the TWAP oracle
+            self.oracle.update(reserve_x, reserve_y, now)?;
+            emit!(Swapped {
+                seed: self.config.seed,
+                user: self.user.key(),
+                is_x: !is_x,
+                amount_in: swap_result.deposit,
+                amount_out: swap_result.withdraw,
+                fee: swap_result.fee,
+                fee_bps,
+                referrer: None,
+                referral_fee: 0,
+                reserve_x,
+                reserve_y,
+            });
+        } else {
+            // Record the post-zap reserves in the TWAP oracle
+            self.oracle.update(reserve_x - x, reserve_y - y, now)?;
+        }
+        Ok(())
+    }
+
+    /// Checks shared by both zap directions and records the pre-trade price in the price band
+    ///
+    /// # Arguments
+    /// * `expiration` - Unix timestamp after which the zap is rejected
+    ///
+    /// # Returns
+    /// * `Result<(i64, u64, u64)>` - Current unix timestamp and pool reserves (X, Y)
+    fn prepare_zap(&mut self, expiration: i64) -> Result<(i64, u64, u64)> {
+        // Reject transactions that sat around long enough for the price to go stale
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        require!(now <= expiration, AmmError::OfferExpired);
+        // Ensure the pool is not locked
+        require!(!self.config.locked, AmmError::PoolLocked);
+        // Vault balances are borrowed out while a flash loan is in progress
+        require!(!self.config.flash_loan_active, AmmError::FlashLoanActive);
+        // Both directions swap, so permissioned pools only zap for admitted users
+        check_trader(
+            &self.config,
+            self.user.key,
+            self.allowlist_entry.as_deref().map(|entry| &**entry),
+            self.instructions.as_deref(),
+        )?;
+
+        // Pool reserves exclude protocol fees waiting in the vaults
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+        // Zaps trade against the pool, so it needs liquidity on both sides
+        require!(
+            self.mint_lp.supply != 0 && reserve_x != 0 && reserve_y != 0,
+            AmmError::NoLiquidityInPool
+        );
+
+        // Record the pre-trade price in the price band
+        self.config.open_price_band(reserve_x, reserve_y, clock.slot);
+
+        Ok((now, reserve_x, reserve_y))
+    }
+
+    /// Transfers tokens from the user's account to the appropriate vault
+    ///
+    /// # Arguments
+    /// * `is_x` - true for token X, false for token Y
+    /// * `amount` - Amount of tokens to transfer
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    fn deposit_token(&mut self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, decimals, token_program) = match is_x {
+            true => (
+                self.user_ata_x.to_account_info(),
+                self.vault_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                self.user_ata_y.to_account_info(),
+                self.vault_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+                self.token_program_y.to_account_info(),
+            ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            to,
+            mint,
+            authority: self.user.to_account_info(),  // User signs the transfer
+        };
+        let cpi_context = CpiContext::new(token_program, cpi_accounts);
+
+        transfer_checked(cpi_context, amount, decimals)
+    }
+
+    /// Transfers tokens from the appropriate vault to the user's account
+    ///
+    /// # Arguments
+    /// * `is_x` - true for token X, false for token Y
+    /// * `amount` - Amount of tokens to transfer
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    fn withdraw_token(&mut self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, decimals, token_program) = match is_x {
+            true => (
+                self.vault_x.to_account_info(),
+                self.user_ata_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                self.vault_y.to_account_info(),
+                self.user_ata_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+                self.token_program_y.to_account_info(),
+            ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            to,
+            mint,
+            authority: self.config.to_account_info(),  // Config PDA signs the transfer
+        };
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"config",
+            &self.config.seed.to_le_bytes(),
+            &[self.config.config_bump],
+        ]];
+        let cpi_context = CpiContext::new_with_signer(
+            token_program,
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(cpi_context, amount, decimals)
+    }
+
+    /// Mints LP tokens to the user
+    ///
+    /// # Arguments
+    /// * `amount` - Amount of LP tokens to mint
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    fn mint_lp_tokens(&mut self, amount: u64) -> Result<()> {
+        let cpi_accounts = MintTo {
+            mint: self.mint_lp.to_account_info(),
+            to: self.user_ata_lp.to_account_info(),
+            authority: self.config.to_account_info(),  // Config PDA is mint authority
+        };
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"config",
+            &self.config.seed.to_le_bytes(),
+            &[self.config.config_bump],
+        ]];
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        mint_to(cpi_context, amount)
+    }
+
+    /// Burns LP tokens from the user's account
+    ///
+    /// # Arguments
+    /// * `amount` - Amount of LP tokens to burn
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    fn burn_lp_tokens(&mut self, amount: u64) -> Result<()> {
+        let cpi_accounts = Burn {
+            mint: self.mint_lp.to_account_info(),
+            from: self.user_ata_lp.to_account_info(),
+            authority: self.user.to_account_info(),  // User signs the burn
+        };
+        let cpi_context = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+        burn(cpi_context, amount)
+    }
+}
@@ -3,11 +3,19 @@ use anchor_lang::prelude::*;
 declare_id!("A4nd5NiheUfbHVk13uDLrQv3gfWDJM9rsCYG1mdF9hjn");
 
 pub mod constants;
+pub mod curves;
 pub mod errors;
+pub mod events;
 pub mod instructions;
+pub mod math;
+pub mod native_sol;
+pub mod permissions;
 pub mod states;
+pub mod transfer_fee;
 
 pub use instructions::*;
+pub use curves::CurveType;
+pub use states::{LiquidityQuote, SwapQuote, Twap};
 
 #[program]
 pub mod amm {
@@ -18,19 +26,236 @@ pub mod amm {
         seed: u64,
         fee: u16,
         authority: Option<Pubkey>,
+        curve_type: CurveType,
+        amp: u64,
     ) -> Result<()> {
-        ctx.accounts.initialize(seed, fee, authority, &ctx.bumps)
+        ctx.accounts
+            .initialize(seed, fee, authority, curve_type, amp, &ctx.bumps)
     }
 
-    pub fn deposit(ctx: Context<Deposit>, amount: u64, max_x: u64, max_y: u64) -> Result<()> {
-        ctx.accounts.deposit(amount, max_x, max_y)
+    pub fn initialize_factory(ctx: Context<InitializeFactory>) -> Result<()> {
+        ctx.accounts.initialize_factory(&ctx.bumps)
     }
 
-    pub fn withdraw(ctx: Context<Withdraw>, amount: u64, min_x: u64, min_y: u64) -> Result<()> {
-        ctx.accounts.withdraw(amount, min_x, min_y)
+    pub fn create_pool(
+        ctx: Context<CreatePool>,
+        seed: u64,
+        fee: u16,
+        authority: Option<Pubkey>,
+        curve_type: CurveType,
+        amp: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .create_pool(seed, fee, authority, curve_type, amp, &ctx.bumps)
+    }
+
+    pub fn initialize_oracle(ctx: Context<InitializeOracle>) -> Result<()> {
+        ctx.accounts.initialize_oracle(&ctx.bumps)
+    }
+
+    pub fn get_twap(ctx: Context<GetTwap>, window: u32) -> Result<Twap> {
+        ctx.accounts.get_twap(window)
+    }
+
+    pub fn quote_swap(ctx: Context<Quote>, is_x: bool, amount_in: u64) -> Result<SwapQuote> {
+        ctx.accounts.quote_swap(is_x, amount_in)
+    }
+
+    pub fn quote_deposit(ctx: Context<Quote>, amount: u64) -> Result<LiquidityQuote> {
+        ctx.accounts.quote_deposit(amount)
+    }
+
+    pub fn quote_withdraw(ctx: Context<Quote>, amount: u64) -> Result<LiquidityQuote> {
+        ctx.accounts.quote_withdraw(amount)
+    }
+
+    pub fn deposit(
+        ctx: Context<Deposit>,
+        amount: u64,
+        max_x: u64,
+        max_y: u64,
+        expiration: i64,
+        native_sol: bool,
+    ) -> Result<()> {
+        ctx.accounts.deposit(amount, max_x, max_y, expiration, native_sol)
+    }
+
+    pub fn withdraw(
+        ctx: Context<Withdraw>,
+        amount: u64,
+        min_x: u64,
+        min_y: u64,
+        expiration: i64,
+        native_sol: bool,
+    ) -> Result<()> {
+        ctx.accounts.withdraw(amount, min_x, min_y, expiration, native_sol)
+    }
+
+    pub fn swap(
+        ctx: Context<Swap>,
+        is_x: bool,
+        amount_in: u64,
+        min_amount_out: u64,
+        expiration: i64,
+        native_sol: bool,
+    ) -> Result<()> {
+        ctx.accounts.swap(is_x, amount_in, min_amount_out, expiration, native_sol)
+    }
+
+    pub fn swap_exact_out(
+        ctx: Context<Swap>,
+        is_x: bool,
+        amount_out: u64,
+        max_amount_in: u64,
+        expiration: i64,
+        native_sol: bool,
+    ) -> Result<()> {
+        ctx.accounts.swap_exact_out(is_x, amount_out, max_amount_in, expiration, native_sol)
+    }
+
+    pub fn route_swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RouteSwap<'info>>,
+        amount_in: u64,
+        min_amount_out: u64,
+        expiration: i64,
+    ) -> Result<()> {
+        ctx.accounts
+            .route_swap(ctx.remaining_accounts, amount_in, min_amount_out, expiration)
+    }
+
+    pub fn zap_in(
+        ctx: Context<Zap>,
+        is_x: bool,
+        amount_in: u64,
+        min_lp_out: u64,
+        expiration: i64,
+    ) -> Result<()> {
+        ctx.accounts.zap_in(is_x, amount_in, min_lp_out, expiration)
+    }
+
+    pub fn zap_out(
+        ctx: Context<Zap>,
+        is_x: bool,
+        amount: u64,
+        min_amount_out: u64,
+        expiration: i64,
+    ) -> Result<()> {
+        ctx.accounts.zap_out(is_x, amount, min_amount_out, expiration)
+    }
+
+    pub fn flash_borrow(ctx: Context<FlashLoan>, is_x: bool, amount: u64) -> Result<()> {
+        ctx.accounts.flash_borrow(is_x, amount)
+    }
+
+    pub fn flash_repay(ctx: Context<FlashLoan>) -> Result<()> {
+        ctx.accounts.flash_repay()
+    }
+
+    pub fn update_fee(ctx: Context<UpdateConfig>, fee: u16) -> Result<()> {
+        ctx.accounts.update_fee(fee)
+    }
+
+    pub fn update_dynamic_fee(ctx: Context<UpdateConfig>, min_fee: u16, max_fee: u16) -> Result<()> {
+        ctx.accounts.update_dynamic_fee(min_fee, max_fee)
+    }
+
+    pub fn disable_dynamic_fee(ctx: Context<UpdateConfig>) -> Result<()> {
+        ctx.accounts.disable_dynamic_fee()
+    }
+
+    pub fn update_protocol_fee(ctx: Context<UpdateConfig>, protocol_fee: u16) -> Result<()> {
+        ctx.accounts.update_protocol_fee(protocol_fee)
+    }
+
+    pub fn update_referral_fee(ctx: Context<UpdateConfig>, referral_fee: u16) -> Result<()> {
+        ctx.accounts.update_referral_fee(referral_fee)
+    }
+
+    pub fn update_price_band(
+        ctx: Context<UpdateConfig>,
+        max_price_impact: u16,
+        max_slot_deviation: u16,
+    ) -> Result<()> {
+        ctx.accounts.update_price_band(max_price_impact, max_slot_deviation)
+    }
+
+    pub fn collect_protocol_fees(ctx: Context<CollectProtocolFees>) -> Result<()> {
+        ctx.accounts.collect_protocol_fees()
+    }
+
+    pub fn ramp_amp(ctx: Context<UpdateConfig>, target_amp: u64, ramp_stop: i64) -> Result<()> {
+        ctx.accounts.ramp_amp(target_amp, ramp_stop)
+    }
+
+    pub fn stop_ramp_amp(ctx: Context<UpdateConfig>) -> Result<()> {
+        ctx.accounts.stop_ramp_amp()
+    }
+
+    pub fn lock(ctx: Context<UpdateConfig>) -> Result<()> {
+        ctx.accounts.set_locked(true)
+    }
+
+    pub fn unlock(ctx: Context<UpdateConfig>) -> Result<()> {
+        ctx.accounts.set_locked(false)
+    }
+
+    pub fn enable_allowlist(ctx: Context<UpdateConfig>, gatekeeper: Option<Pubkey>) -> Result<()> {
+        ctx.accounts.set_permissioned(true, gatekeeper)
+    }
+
+    pub fn disable_allowlist(ctx: Context<UpdateConfig>) -> Result<()> {
+        ctx.accounts.set_permissioned(false, None)
+    }
+
+    pub fn allow_trader(ctx: Context<AllowTrader>, user: Pubkey) -> Result<()> {
+        ctx.accounts.allow_trader(user, &ctx.bumps)
+    }
+
+    pub fn revoke_trader(ctx: Context<RevokeTrader>) -> Result<()> {
+        ctx.accounts.revoke_trader()
+    }
+
+    pub fn transfer_authority(ctx: Context<UpdateConfig>, new_authority: Pubkey) -> Result<()> {
+        ctx.accounts.transfer_authority(new_authority)
+    }
+
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        ctx.accounts.accept_authority()
+    }
+
+    pub fn renounce_authority(ctx: Context<UpdateConfig>) -> Result<()> {
+        ctx.accounts.renounce_authority()
+    }
+
+    pub fn migrate_config(ctx: Context<MigrateConfig>) -> Result<()> {
+        ctx.accounts.migrate_config()
+    }
+
+    pub fn close_pool(ctx: Context<ClosePool>) -> Result<()> {
+        ctx.accounts.close_pool()
+    }
+
+    pub fn initialize_farm(ctx: Context<InitializeFarm>, reward_per_second: u64) -> Result<()> {
+        ctx.accounts.initialize_farm(reward_per_second, &ctx.bumps)
+    }
+
+    pub fn set_reward_rate(ctx: Context<UpdateFarm>, reward_per_second: u64) -> Result<()> {
+        ctx.accounts.set_reward_rate(reward_per_second)
+    }
+
+    pub fn stake(ctx: Context<Stake>, amount: u64) -> Result<()> {
+        ctx.accounts.stake(amount, &ctx.bumps)
+    }
+
+    pub fn unstake(ctx: Context<Stake>, amount: u64) -> Result<()> {
+        ctx.accounts.unstake(amount, &ctx.bumps)
+    }
+
+    pub fn harvest(ctx: Context<Stake>) -> Result<()> {
+        ctx.accounts.harvest(&ctx.bumps)
     }
 
-    pub fn swap(ctx: Context<Swap>, is_x: bool, amount_in: u64, min_amount_out: u64) -> Result<()> {
-        ctx.accounts.swap(is_x, amount_in, min_amount_out)
+    pub fn emergency_withdraw(ctx: Context<Stake>) -> Result<()> {
+        ctx.accounts.emergency_withdraw()
     }
 }
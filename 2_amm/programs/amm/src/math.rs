@@ -0,0 +1,414 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::{FLASH_LOAN_FEE_BPS, MAX_FEE_BPS};
+use crate::curves::U256;
+use crate::errors::AmmError;
+
+/// Splits a swap fee between liquidity providers and the protocol
+///
+/// The protocol share is rounded down so any rounding dust stays with LPs.
+///
+/// # Arguments
+/// * `fee_amount` - Total fee charged on a swap, in input tokens
+/// * `protocol_fee` - Protocol share of the fee in basis points (10000 = whole fee)
+///
+/// # Returns
+/// * `Result<(u64, u64)>` - (LP share, protocol share), always summing to `fee_amount`
+pub fn split_fee(fee_amount: u64, protocol_fee: u16) -> Result<(u64, u64)> {
+    require!(protocol_fee <= MAX_FEE_BPS, AmmError::InvalidFee);
+
+    let protocol_share = (fee_amount as u128)
+        .checked_mul(protocol_fee as u128)
+        .ok_or(AmmError::Overflow)?
+        / MAX_FEE_BPS as u128;
+    // Never exceeds fee_amount since protocol_fee <= MAX_FEE_BPS
+    let protocol_share = protocol_share as u64;
+    let lp_share = fee_amount
+        .checked_sub(protocol_share)
+        .ok_or(AmmError::Underflow)?;
+
+    Ok((lp_share, protocol_share))
+}
+
+/// Price of one unit of the base token in quote tokens, as a Q64.64 fixed-point number
+///
+/// Cannot overflow: a u64 quote reserve shifted by 64 bits always fits in a u128.
+///
+/// # Arguments
+/// * `base_reserve` - Reserve of the token being priced
+/// * `quote_reserve` - Reserve of the token the price is expressed in
+///
+/// # Returns
+/// * `Result<u128>` - quote_reserve / base_reserve in Q64.64
+pub fn price_q64(base_reserve: u64, quote_reserve: u64) -> Result<u128> {
+    require!(base_reserve != 0, AmmError::ZeroBalance);
+
+    Ok(((quote_reserve as u128) << 64) / base_reserve as u128)
+}
+
+/// Relative change of the price of X in Y between two sets of reserves
+///
+/// # Arguments
+/// * `reserve_x` - Reserve of token X before the change
+/// * `reserve_y` - Reserve of token Y before the change
+/// * `new_x` - Reserve of token X after the change
+/// * `new_y` - Reserve of token Y after the change
+///
+/// # Returns
+/// * `Result<u64>` - |new price - old price| / old price in basis points, saturating at u64::MAX
+pub fn price_change_bps(reserve_x: u64, reserve_y: u64, new_x: u64, new_y: u64) -> Result<u64> {
+    require!(reserve_y != 0 && new_x != 0, AmmError::ZeroBalance);
+
+    // new_y / new_x against reserve_y / reserve_x, cross-multiplied
+    let old = U256::from(reserve_y) * U256::from(new_x);
+    let new = U256::from(new_y) * U256::from(reserve_x);
+    let change = (old.max(new) - old.min(new)) * U256::from(MAX_FEE_BPS) / old;
+
+    Ok(match change > U256::from(u64::MAX) {
+        true => u64::MAX,
+        false => change.as_u64(),
+    })
+}
+
+/// Smallest input (fee included) that buys exactly `amount_out` from a constant product pool
+///
+/// Inverse of the exact-in swap, where the fee `floor(amount_in * fee / 10000)` is
+/// taken from the input and the output is `floor(reserve_out * net / (reserve_in + net))`.
+///
+/// # Arguments
+/// * `reserve_in` - Pool reserve of the input token
+/// * `reserve_out` - Pool reserve of the output token
+/// * `amount_out` - Exact amount of output tokens wanted
+/// * `fee` - Swap fee in basis points
+///
+/// # Returns
+/// * `Result<u64>` - Required amount of input tokens
+pub fn amount_in_for_exact_out(
+    reserve_in: u64,
+    reserve_out: u64,
+    amount_out: u64,
+    fee: u16,
+) -> Result<u64> {
+    require!(reserve_in != 0 && reserve_out != 0, AmmError::NoLiquidityInPool);
+    require!(amount_out != 0, AmmError::InvalidAmount);
+    // The pool can never be fully drained
+    require!(amount_out < reserve_out, AmmError::InsufficientBalance);
+    // With a 100% fee no input is ever enough
+    require!(fee < MAX_FEE_BPS, AmmError::InvalidFee);
+
+    // Smallest net input keeping (reserve_in + net) * (reserve_out - amount_out) >= k
+    let net = (reserve_in as u128 * amount_out as u128).div_ceil((reserve_out - amount_out) as u128);
+
+    amount_with_fee(net, fee)
+}
+
+/// Smallest gross input whose net part after the swap fee reaches `net`
+///
+/// The net part of `gross` is `gross - floor(gross * fee / 10000)`, i.e.
+/// `ceil(gross * (10000 - fee) / 10000)`.
+///
+/// # Arguments
+/// * `net` - Net input the curve needs, must be positive
+/// * `fee` - Swap fee in basis points, below 100%
+///
+/// # Returns
+/// * `Result<u64>` - Required gross input
+pub fn amount_with_fee(net: u128, fee: u16) -> Result<u64> {
+    require!(net != 0, AmmError::InvalidAmount);
+    require!(fee < MAX_FEE_BPS, AmmError::InvalidFee);
+
+    let gross = (net - 1) * MAX_FEE_BPS as u128 / (MAX_FEE_BPS - fee) as u128 + 1;
+
+    u64::try_from(gross).map_err(|_| AmmError::Overflow.into())
+}
+
+/// Integer square root, rounded down
+///
+/// # Arguments
+/// * `n` - Number to take the square root of
+///
+/// # Returns
+/// * `u128` - floor(sqrt(n))
+pub fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+
+    // Newton's method from an initial guess above the root, decreasing monotonically
+    let mut x = 1u128 << ((128 - n.leading_zeros()).div_ceil(2));
+    loop {
+        let y = (x + n / x) / 2;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
+/// Portion of a single-sided deposit to swap so the rest matches the pool ratio
+///
+/// Solves for the swap amount `s` that leaves `amount_in - s` and the swap output in
+/// the same proportion as the post-swap reserves (fee kept in the pool):
+/// `s = (sqrt(r² (2 - f)² + 4 (1 - f) a r) - r (2 - f)) / (2 (1 - f))`.
+/// Inputs are scaled down to 48 bits first so every term fits in a u128, which
+/// costs far less precision than the fee rounding already does.
+///
+/// # Arguments
+/// * `reserve_in` - Pool reserve of the deposited token
+/// * `amount_in` - Amount of the deposited token
+/// * `fee` - Swap fee in basis points
+///
+/// # Returns
+/// * `Result<u64>` - Amount of the deposited token to swap
+pub fn zap_swap_amount(reserve_in: u64, amount_in: u64, fee: u16) -> Result<u64> {
+    require!(reserve_in != 0, AmmError::NoLiquidityInPool);
+    require!(fee < MAX_FEE_BPS, AmmError::InvalidFee);
+
+    let shift = (64 - (reserve_in | amount_in).leading_zeros()).saturating_sub(48);
+    let r = (reserve_in >> shift) as u128;
+    let a = (amount_in >> shift) as u128;
+
+    // Same formula with f = fee / d and g = d - fee, multiplied through by d
+    let d = MAX_FEE_BPS as u128;
+    let g = d - fee as u128;
+    let b = r * (d + g);
+    let s = (isqrt(b * b + 4 * g * d * a * r) - b) / (2 * g);
+
+    Ok(((s << shift) as u64).min(amount_in))
+}
+
+/// LP tokens earned by depositing the given amounts, limited by the scarcer side
+///
+/// # Arguments
+/// * `amount_x` - Available amount of token X
+/// * `amount_y` - Available amount of token Y
+/// * `reserve_x` - Pool reserve of token X
+/// * `reserve_y` - Pool reserve of token Y
+/// * `supply` - Current LP token supply
+///
+/// # Returns
+/// * `Result<u64>` - min(amount_x * supply / reserve_x, amount_y * supply / reserve_y)
+pub fn liquidity_for_amounts(
+    amount_x: u64,
+    amount_y: u64,
+    reserve_x: u64,
+    reserve_y: u64,
+    supply: u64,
+) -> Result<u64> {
+    require!(reserve_x != 0 && reserve_y != 0 && supply != 0, AmmError::NoLiquidityInPool);
+
+    let liquidity = (amount_x as u128 * supply as u128 / reserve_x as u128)
+        .min(amount_y as u128 * supply as u128 / reserve_y as u128);
+
+    u64::try_from(liquidity).map_err(|_| AmmError::Overflow.into())
+}
+
+/// Token amounts backing `liquidity` LP tokens, rounded up in favour of the pool
+///
+/// # Arguments
+/// * `liquidity` - Amount of LP tokens to mint
+/// * `reserve_x` - Pool reserve of token X
+/// * `reserve_y` - Pool reserve of token Y
+/// * `supply` - Current LP token supply
+///
+/// # Returns
+/// * `Result<(u64, u64)>` - Amounts of token X and Y to deposit
+pub fn amounts_for_liquidity(
+    liquidity: u64,
+    reserve_x: u64,
+    reserve_y: u64,
+    supply: u64,
+) -> Result<(u64, u64)> {
+    require!(supply != 0, AmmError::NoLiquidityInPool);
+
+    let x = (liquidity as u128 * reserve_x as u128).div_ceil(supply as u128);
+    let y = (liquidity as u128 * reserve_y as u128).div_ceil(supply as u128);
+
+    Ok((
+        u64::try_from(x).map_err(|_| AmmError::Overflow)?,
+        u64::try_from(y).map_err(|_| AmmError::Overflow)?,
+    ))
+}
+
+/// Fee owed on a flash loan, rounded up so every loan pays something
+///
+/// # Arguments
+/// * `amount` - Borrowed amount
+///
+/// # Returns
+/// * `Result<u64>` - ceil(amount * FLASH_LOAN_FEE_BPS / 10000)
+pub fn flash_loan_fee(amount: u64) -> Result<u64> {
+    let fee = (amount as u128 * FLASH_LOAN_FEE_BPS as u128).div_ceil(MAX_FEE_BPS as u128);
+
+    u64::try_from(fee).map_err(|_| AmmError::Overflow.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use constant_product_curve::{ConstantProduct, LiquidityPair};
+
+    #[test]
+    fn split_fee_adds_up_to_total() {
+        for fee_amount in [0, 1, 2, 3, 7, 29, 1_000, 123_457, u64::MAX] {
+            for protocol_fee in [0, 1, 1_666, 2_500, 5_000, 9_999, 10_000] {
+                let (lp, protocol) = split_fee(fee_amount, protocol_fee).unwrap();
+                assert_eq!(lp + protocol, fee_amount);
+            }
+        }
+    }
+
+    #[test]
+    fn split_fee_extremes() {
+        assert_eq!(split_fee(1_000, 0).unwrap(), (1_000, 0));
+        assert_eq!(split_fee(1_000, 10_000).unwrap(), (0, 1_000));
+        assert_eq!(split_fee(1_000, 2_500).unwrap(), (750, 250));
+        // Rounding dust goes to LPs
+        assert_eq!(split_fee(3, 5_000).unwrap(), (2, 1));
+        assert!(split_fee(1_000, 10_001).is_err());
+    }
+
+    #[test]
+    fn price_q64_bounds() {
+        assert_eq!(price_q64(1, 1).unwrap(), 1 << 64);
+        assert_eq!(price_q64(2, 1).unwrap(), 1 << 63);
+        assert_eq!(price_q64(1, u64::MAX).unwrap(), (u64::MAX as u128) << 64);
+        assert_eq!(price_q64(u64::MAX, 1).unwrap(), 1);
+        assert!(price_q64(0, 1).is_err());
+    }
+
+    /// Output of the exact-in swap path for `amount_in`, or 0 if it can't execute
+    fn exact_in_output(reserve_in: u64, reserve_out: u64, amount_in: u64, fee: u16) -> u64 {
+        let mut curve = ConstantProduct::init(reserve_in, reserve_out, 0, fee, None).unwrap();
+        curve
+            .swap(LiquidityPair::X, amount_in, 0)
+            .map(|result| result.withdraw)
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn price_change_is_relative() {
+        assert_eq!(price_change_bps(1_000, 1_000, 1_000, 1_000).unwrap(), 0);
+        // Price of X doubles, then halves back
+        assert_eq!(price_change_bps(1_000, 1_000, 1_000, 2_000).unwrap(), 10_000);
+        assert_eq!(price_change_bps(1_000, 2_000, 1_000, 1_000).unwrap(), 5_000);
+        // A 1% swap into a constant product pool moves the price by about 2%
+        assert_eq!(price_change_bps(1_000_000, 1_000_000, 1_010_000, 990_100).unwrap(), 197);
+        assert!(price_change_bps(1_000, 0, 1_000, 1_000).is_err());
+    }
+
+    #[test]
+    fn exact_out_matches_exact_in() {
+        let pools = [(1_000_000, 1_000_000), (1_000_000, 2_000_000_000), (987_654_321, 12_345), (10, 10)];
+        for (reserve_in, reserve_out) in pools {
+            for fee in [0, 1, 30, 100, 1_000, 9_999] {
+                for amount_out in [1, 2, 7, reserve_out / 1_000, reserve_out / 3, reserve_out / 2, reserve_out - 1] {
+                    if amount_out == 0 {
+                        continue;
+                    }
+                    // Draining a pool at a 99.99% fee can need more input than fits in a u64
+                    let Ok(amount_in) = amount_in_for_exact_out(reserve_in, reserve_out, amount_out, fee) else {
+                        assert!(fee == 9_999 && amount_out == reserve_out - 1);
+                        continue;
+                    };
+
+                    // Enough to buy amount_out on the exact-in path...
+                    assert!(exact_in_output(reserve_in, reserve_out, amount_in, fee) >= amount_out);
+                    // ...and one unit less isn't
+                    assert!(exact_in_output(reserve_in, reserve_out, amount_in - 1, fee) < amount_out);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn exact_out_rejects_impossible_trades() {
+        assert!(amount_in_for_exact_out(1_000, 1_000, 1_000, 30).is_err());
+        assert!(amount_in_for_exact_out(1_000, 1_000, 0, 30).is_err());
+        assert!(amount_in_for_exact_out(0, 1_000, 10, 30).is_err());
+        assert!(amount_in_for_exact_out(1_000, 1_000, 10, 10_000).is_err());
+        // Input that doesn't fit in a u64
+        assert!(amount_in_for_exact_out(u64::MAX, 2, 1, 30).is_err());
+    }
+
+    #[test]
+    fn isqrt_rounds_down() {
+        for n in [0u128, 1, 2, 3, 4, 15, 16, 17, 99, 100, 1 << 64, (1 << 64) + 1, u64::MAX as u128, u128::MAX] {
+            let root = isqrt(n);
+            assert!(root * root <= n);
+            assert!(root.checked_add(1).and_then(|r| r.checked_mul(r)).is_none_or(|sq| sq > n));
+        }
+    }
+
+    #[test]
+    fn zap_leaves_balanced_amounts() {
+        let pools = [(1_000_000u64, 1_000_000u64), (5_000_000_000, 1_000), (1 << 60, 1 << 50)];
+        for (reserve_in, reserve_out) in pools {
+            for fee in [0, 30, 1_000] {
+                for amount_in in [reserve_in / 1_000, reserve_in / 10, reserve_in, reserve_in.saturating_mul(4)] {
+                    let swap = zap_swap_amount(reserve_in, amount_in, fee).unwrap();
+                    let mut curve = ConstantProduct::init(reserve_in, reserve_out, 0, fee, None).unwrap();
+                    let out = curve.swap(LiquidityPair::X, swap, 0).unwrap().withdraw;
+
+                    // What is left of the input matches the new pool ratio up to rounding
+                    let (new_in, new_out) = (reserve_in + swap, reserve_out - out);
+                    let left = amount_in - swap;
+                    let wanted = out as u128 * new_in as u128 / new_out as u128;
+                    let dust = (left as u128).abs_diff(wanted);
+                    // Off by a fraction of the input plus the value of a unit of output
+                    let tolerance = amount_in as u128 / 1_000 + 2 * (new_in / new_out) as u128 + 4;
+                    assert!(dust <= tolerance, "dust {dust} for {amount_in}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn liquidity_amounts_round_trip() {
+        let (reserve_x, reserve_y, supply) = (1_000_003u64, 7_000_019u64, 2_645_757u64);
+        for (amount_x, amount_y) in [(1, 7), (10, 10), (1_000, 7_000), (999_999, 7_000_000)] {
+            let liquidity = liquidity_for_amounts(amount_x, amount_y, reserve_x, reserve_y, supply).unwrap();
+            let (x, y) = amounts_for_liquidity(liquidity, reserve_x, reserve_y, supply).unwrap();
+            // Never asks for more than what is available...
+            assert!(x <= amount_x && y <= amount_y);
+            // ...and never gives LP tokens for free
+            assert!(x as u128 * supply as u128 >= liquidity as u128 * reserve_x as u128);
+            assert!(y as u128 * supply as u128 >= liquidity as u128 * reserve_y as u128);
+        }
+    }
+
+    #[test]
+    fn flash_loan_fee_rounds_up() {
+        assert_eq!(flash_loan_fee(0).unwrap(), 0);
+        assert_eq!(flash_loan_fee(1).unwrap(), 1);
+        assert_eq!(flash_loan_fee(10_000).unwrap(), FLASH_LOAN_FEE_BPS as u64);
+        assert_eq!(flash_loan_fee(10_001).unwrap(), FLASH_LOAN_FEE_BPS as u64 + 1);
+        assert!(flash_loan_fee(u64::MAX).unwrap() < u64::MAX);
+    }
+
+    #[test]
+    fn swap_fee_accounting() {
+        let (mut vault_x, vault_y) = (1_000_000_000u64, 2_000_000_000u64);
+        let mut protocol_fees_x = 0u64;
+        let mut lp_fees_x = 0u64;
+        let mut total_fees_x = 0u64;
+
+        for amount_in in [1_000u64, 50_000, 333_333, 10_000_000] {
+            let reserve_x = vault_x - protocol_fees_x;
+            let mut curve = ConstantProduct::init(reserve_x, vault_y, 0, 30, None).unwrap();
+            let result = curve.swap(LiquidityPair::X, amount_in, 0).unwrap();
+
+            let (lp, protocol) = split_fee(result.fee, 1_666).unwrap();
+            vault_x += result.deposit;
+            protocol_fees_x += protocol;
+            lp_fees_x += lp;
+            total_fees_x += result.fee;
+
+            // What LPs can redeem grew by everything except the protocol cut
+            assert_eq!(vault_x - protocol_fees_x, reserve_x + result.deposit - protocol);
+        }
+
+        assert_eq!(lp_fees_x + protocol_fees_x, total_fees_x);
+        assert!(protocol_fees_x > 0 && lp_fees_x > protocol_fees_x);
+    }
+}
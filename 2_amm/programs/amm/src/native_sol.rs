@@ -0,0 +1,103 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022;
+use anchor_spl::token_interface::{close_account, sync_native, CloseAccount, SyncNative, TokenAccount};
+
+use crate::errors::AmmError;
+
+/// Checks whether a mint is wrapped SOL, under either token program
+///
+/// # Arguments
+/// * `mint` - Mint of one of the pool tokens
+///
+/// # Returns
+/// * `bool` - true for the native mint of SPL Token or Token-2022
+pub fn is_native_mint(mint: &Pubkey) -> bool {
+    *mint == spl_token::native_mint::ID || *mint == spl_token_2022::native_mint::ID
+}
+
+/// Finds the sides of a pool an instruction trades in native SOL
+///
+/// Native SOL mode closes the user's wrapped SOL account once the instruction
+/// is done, so it must be called before any tokens move, and rejects accounts
+/// already holding wrapped SOL rather than unwrapping the user's balance.
+///
+/// # Arguments
+/// * `native_sol` - Whether the user asked to trade in native SOL
+/// * `user_ata_x` - User's token account of token X
+/// * `user_ata_y` - User's token account of token Y
+///
+/// # Returns
+/// * `Result<(bool, bool)>` - Whether token X and token Y are paid in native SOL,
+///   error if native SOL was asked for and neither is wrapped SOL
+pub fn native_sides(
+    native_sol: bool,
+    user_ata_x: &TokenAccount,
+    user_ata_y: &TokenAccount,
+) -> Result<(bool, bool)> {
+    if !native_sol {
+        return Ok((false, false));
+    }
+    let sides = (is_native_mint(&user_ata_x.mint), is_native_mint(&user_ata_y.mint));
+    require!(sides.0 || sides.1, AmmError::NoWrappedSol);
+    require!(
+        (!sides.0 || user_ata_x.amount == 0) && (!sides.1 || user_ata_y.amount == 0),
+        AmmError::WrappedSolNotEmpty
+    );
+    Ok(sides)
+}
+
+/// Wraps native SOL of the user into their wrapped SOL token account
+///
+/// # Arguments
+/// * `user` - Signer paying the lamports
+/// * `token_account` - User's token account of the native mint
+/// * `system_program` - System program, for the lamport transfer
+/// * `token_program` - Token program of the native mint
+/// * `amount` - Lamports to wrap
+///
+/// # Returns
+/// * `Result<()>` - Ok if the token balance grew by `amount`
+pub fn wrap_sol<'info>(
+    user: AccountInfo<'info>,
+    token_account: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    let cpi_accounts = Transfer {
+        from: user,
+        to: token_account.clone(),
+    };
+    transfer(CpiContext::new(system_program, cpi_accounts), amount)?;
+
+    // The token program only counts the new lamports once synced
+    let cpi_accounts = SyncNative {
+        account: token_account,
+    };
+    sync_native(CpiContext::new(token_program, cpi_accounts))
+}
+
+/// Unwraps the user's wrapped SOL by closing their token account
+/// The whole balance comes back as lamports, along with the account's rent
+///
+/// # Arguments
+/// * `user` - Signer owning the token account, receiving the lamports
+/// * `token_account` - User's token account of the native mint
+/// * `token_program` - Token program of the native mint
+///
+/// # Returns
+/// * `Result<()>` - Ok if the account was closed
+pub fn unwrap_sol<'info>(
+    user: AccountInfo<'info>,
+    token_account: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+) -> Result<()> {
+    let cpi_accounts = CloseAccount {
+        account: token_account,
+        destination: user.clone(),
+        authority: user,  // User signs the close
+    };
+    close_account(CpiContext::new(token_program, cpi_accounts))
+}
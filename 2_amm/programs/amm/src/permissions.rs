@@ -0,0 +1,174 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::sysvar::instructions::load_instruction_at_checked;
+
+use crate::errors::AmmError;
+use crate::states::{AllowlistEntry, Config};
+
+/// Offset of the first signature's offsets in an ed25519 instruction,
+/// after the signature count and a padding byte
+const SIGNATURE_OFFSETS_START: usize = 2;
+
+/// Size of one signature's offsets: seven little-endian u16
+const SIGNATURE_OFFSETS_SIZE: usize = 14;
+
+/// Instruction index meaning the data is in the ed25519 instruction itself
+const CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+/// Size of a gatekeeper attestation: the config, the trader and an expiration
+pub const ATTESTATION_SIZE: usize = 32 + 32 + 8;
+
+/// Checks that `user` may swap in or deposit into the pool
+///
+/// Permissionless pools admit everyone. Permissioned pools admit traders with
+/// an allowlist entry, or holding an attestation from the gatekeeper: an ed25519
+/// signature over the config, the trader and an expiration timestamp, verified
+/// by an ed25519 program instruction in the same transaction.
+///
+/// # Arguments
+/// * `config` - The pool's config account
+/// * `user` - Trader signing the swap or deposit
+/// * `allowlist_entry` - The trader's allowlist entry, seeds already checked
+/// * `instructions` - Instructions sysvar, to look for an attestation
+///
+/// # Returns
+/// * `Result<()>` - Ok if the trader is admitted, error otherwise
+pub fn check_trader(
+    config: &Account<Config>,
+    user: &Pubkey,
+    allowlist_entry: Option<&AllowlistEntry>,
+    instructions: Option<&AccountInfo>,
+) -> Result<()> {
+    if !config.permissioned || allowlist_entry.is_some() {
+        return Ok(());
+    }
+
+    let attested = match instructions {
+        Some(instructions) if config.gatekeeper != Pubkey::default() => {
+            is_attested(instructions, &config.gatekeeper, &config.key(), user)?
+        }
+        _ => false,
+    };
+    require!(attested, AmmError::TraderNotAllowed);
+    Ok(())
+}
+
+/// Message the gatekeeper signs to admit `user` to a pool until `expiration`
+///
+/// # Arguments
+/// * `config` - Config account of the permissioned pool
+/// * `user` - Trader being admitted
+/// * `expiration` - Unix timestamp after which the attestation is rejected
+///
+/// # Returns
+/// * `[u8; ATTESTATION_SIZE]` - The config, the user and the little-endian expiration
+pub fn attestation_message(config: &Pubkey, user: &Pubkey, expiration: i64) -> [u8; ATTESTATION_SIZE] {
+    let mut message = [0; ATTESTATION_SIZE];
+    message[..32].copy_from_slice(config.as_ref());
+    message[32..64].copy_from_slice(user.as_ref());
+    message[64..].copy_from_slice(&expiration.to_le_bytes());
+    message
+}
+
+/// Looks for an unexpired gatekeeper attestation of `user` in the transaction
+///
+/// The ed25519 program fails the whole transaction on a bad signature, so any
+/// signature found in one of its instructions is valid.
+fn is_attested(
+    instructions: &AccountInfo,
+    gatekeeper: &Pubkey,
+    config: &Pubkey,
+    user: &Pubkey,
+) -> Result<bool> {
+    let now = Clock::get()?.unix_timestamp;
+    let attested = (0..)
+        .map_while(|index| load_instruction_at_checked(index, instructions).ok())
+        .filter(|instruction| instruction.program_id == ed25519_program::ID)
+        .any(|instruction| {
+            signed_messages(&instruction.data).any(|(signer, message)| {
+                let Some(expiration) = message.get(64..).and_then(|bytes| bytes.try_into().ok()) else {
+                    return false;
+                };
+                let expiration = i64::from_le_bytes(expiration);
+                signer == gatekeeper.as_ref()
+                    && message == attestation_message(config, user, expiration)
+                    && now <= expiration
+            })
+        });
+    Ok(attested)
+}
+
+/// Public keys and messages of the signatures an ed25519 instruction verifies
+///
+/// Only signatures whose key and message sit in the instruction itself are
+/// returned: data pointed at in other instructions isn't read here.
+fn signed_messages(data: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let count = data.first().copied().unwrap_or(0) as usize;
+    (0..count).filter_map(move |index| {
+        let start = SIGNATURE_OFFSETS_START + index * SIGNATURE_OFFSETS_SIZE;
+        let offsets = data.get(start..start + SIGNATURE_OFFSETS_SIZE)?;
+        let offset = |field: usize| u16::from_le_bytes([offsets[2 * field], offsets[2 * field + 1]]);
+        // signature, its instruction, public key, its instruction, message, size, its instruction
+        let (public_key, public_key_index) = (offset(2) as usize, offset(3));
+        let (message, size, message_index) = (offset(4) as usize, offset(5) as usize, offset(6));
+        if public_key_index != CURRENT_INSTRUCTION || message_index != CURRENT_INSTRUCTION {
+            return None;
+        }
+        Some((
+            data.get(public_key..public_key + 32)?,
+            data.get(message..message + size)?,
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ed25519 instruction data with one signature, laid out as the SDK does
+    fn instruction_data(public_key: &[u8; 32], message: &[u8], index: u16) -> Vec<u8> {
+        let public_key_offset = SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_SIZE;
+        let signature_offset = public_key_offset + 32;
+        let message_offset = signature_offset + 64;
+        let mut data = vec![1, 0];
+        for value in [
+            signature_offset as u16,
+            index,
+            public_key_offset as u16,
+            index,
+            message_offset as u16,
+            message.len() as u16,
+            index,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(public_key);
+        data.extend_from_slice(&[0; 64]);
+        data.extend_from_slice(message);
+        data
+    }
+
+    #[test]
+    fn reads_keys_and_messages_of_self_contained_signatures() {
+        let key = [7; 32];
+        let data = instruction_data(&key, b"attestation", CURRENT_INSTRUCTION);
+        let signed: Vec<_> = signed_messages(&data).collect();
+        assert_eq!(signed, vec![(&key[..], &b"attestation"[..])]);
+    }
+
+    #[test]
+    fn ignores_signatures_over_other_instructions() {
+        let data = instruction_data(&[7; 32], b"attestation", 0);
+        assert_eq!(signed_messages(&data).count(), 0);
+    }
+
+    #[test]
+    fn ignores_truncated_data() {
+        let mut data = instruction_data(&[7; 32], b"attestation", CURRENT_INSTRUCTION);
+        data.truncate(data.len() - 1);
+        assert_eq!(signed_messages(&data).count(), 0);
+        // Claims more signatures than it has offsets for
+        data[0] = 2;
+        assert_eq!(signed_messages(&data[..20]).count(), 0);
+    }
+}
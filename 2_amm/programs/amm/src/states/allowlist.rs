@@ -0,0 +1,16 @@
+use anchor_lang::prelude::*;
+
+/// Admission of a trader to a permissioned pool
+/// Derived from "allowlist", the config and the trader, created and closed by the authority
+#[account]
+#[derive(InitSpace)]
+pub struct AllowlistEntry {
+    /// Config account of the pool the trader is admitted to.
+    pub config: Pubkey,
+
+    /// Trader allowed to swap and deposit.
+    pub user: Pubkey,
+
+    /// Bump used to derive the PDA for this entry.
+    pub bump: u8,
+}
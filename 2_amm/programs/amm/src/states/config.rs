@@ -0,0 +1,820 @@
+use anchor_lang::prelude::*;
+
+use constant_product_curve::SwapResult;
+
+use crate::constants::{CONFIG_RESERVED_BYTES, CONFIG_VERSION, MAX_FEE_BPS, VOLATILITY_FEE_BPS, VOLATILITY_HALF_LIFE};
+use crate::curves::{ramped_amp, ConstantProductCurve, Curve, CurveType, StableSwapCurve};
+use crate::errors::AmmError;
+use crate::math::{price_change_bps, split_fee};
+use crate::states::{LiquidityQuote, SwapQuote};
+
+#[account]
+#[derive(Debug, InitSpace)]
+pub struct Config {
+    /// Unique identifier used to differentiate between multiple AMM pools.
+    /// Helps in deriving unique PDAs for each AMM instance.
+    pub seed: u64,
+
+    /// Optional authority/maintainer of the AMM.
+    /// Can be used to control parameters like fees or to pause the AMM.
+    /// If set to `None`, the AMM is fully decentralized.
+    pub authority: Option<Pubkey>,
+
+    /// SPL token mint address for Token X (first asset in the pair).
+    pub mint_x: Pubkey,
+
+    /// SPL token mint address for Token Y (second asset in the pair).
+    pub mint_y: Pubkey,
+
+    /// Swap fee taken on each trade (in basis points, e.g., 30 = 0.3%).
+    /// This fee typically goes to liquidity providers or protocol treasury.
+    pub fee: u16,
+
+    /// Boolean flag to lock the AMM.
+    /// When `true`, operations like swap or deposit can be disabled.
+    pub locked: bool,
+
+    /// Bump used to derive the PDA for this config account.
+    /// Ensures the correct address is derived on-chain.
+    pub config_bump: u8,
+
+    /// Bump used to derive the PDA for the LP token mint account.
+    /// LP tokens represent a user’s share of the liquidity pool.
+    pub lp_bump: u8,
+
+    /// Authority nominated by the current authority, waiting to accept.
+    /// Authority transfers are two-step so a typo can't brick the pool.
+    pub pending_authority: Option<Pubkey>,
+
+    /// Protocol share of the swap fee (in basis points of the fee, e.g., 2500 = 25%).
+    /// The rest of the fee stays in the pool for liquidity providers.
+    pub protocol_fee: u16,
+
+    /// Protocol fees accrued in token X, held in the X vault until collected.
+    /// Not part of the pool reserves, so LPs can't withdraw them.
+    pub protocol_fees_x: u64,
+
+    /// Protocol fees accrued in token Y, held in the Y vault until collected.
+    /// Not part of the pool reserves, so LPs can't withdraw them.
+    pub protocol_fees_y: u64,
+
+    /// Pricing function of the pool, fixed at initialization.
+    pub curve_type: CurveType,
+
+    /// StableSwap amplification coefficient at the start of the current ramp.
+    /// Unused (zero) for constant product pools.
+    pub initial_amp: u64,
+
+    /// StableSwap amplification coefficient at the end of the current ramp.
+    pub target_amp: u64,
+
+    /// Unix timestamp the current amplification ramp started at.
+    pub ramp_start: i64,
+
+    /// Unix timestamp the current amplification ramp ends at.
+    pub ramp_stop: i64,
+
+    /// Whether a flash loan from the vaults is waiting to be repaid.
+    /// Swaps, deposits and withdrawals are rejected until it is.
+    pub flash_loan_active: bool,
+
+    /// Amount (loan plus fee) owed to the vault of the current flash loan.
+    pub flash_loan_repayment: u64,
+
+    /// Whether the current flash loan was borrowed in token X (true) or Y (false).
+    pub flash_loan_is_x: bool,
+
+    /// Whether the swap fee follows recent price movement instead of `fee`.
+    pub dynamic_fee: bool,
+
+    /// Lowest dynamic swap fee in basis points, charged when the price is steady.
+    pub min_fee: u16,
+
+    /// Highest dynamic swap fee in basis points, however much the price moves.
+    pub max_fee: u16,
+
+    /// Recent price movement in basis points, summed over swaps.
+    /// Halves every `VOLATILITY_HALF_LIFE` seconds, tracked even with a static fee.
+    pub volatility: u64,
+
+    /// Unix timestamp `volatility` was last decayed to.
+    pub volatility_updated_at: i64,
+
+    /// Account that paid for the pool's creation, refunded when it is closed.
+    /// Unknown (default) for migrated legacy pools without authority, which can't be closed.
+    pub creator: Pubkey,
+
+    /// Layout version of this account, `CONFIG_VERSION` once created or migrated.
+    pub version: u8,
+
+    /// Whether swaps and deposits are restricted to allowlisted traders.
+    /// Traders need an `AllowlistEntry` or an attestation from the gatekeeper.
+    pub permissioned: bool,
+
+    /// Key whose ed25519 attestations admit traders to a permissioned pool.
+    /// `Pubkey::default()` when attestations aren't accepted.
+    pub gatekeeper: Pubkey,
+
+    /// Share of the swap fee paid to the referrer of a swap (in basis points of the fee).
+    /// Comes out of the LP share, so traders pay the same fee with or without a referrer.
+    pub referral_fee: u16,
+
+    /// Largest price move a single swap may cause, in basis points. Zero for no limit.
+    pub max_price_impact: u16,
+
+    /// Largest price move all swaps of a slot may cause together, in basis points,
+    /// measured from the price the slot opened at. Zero for no limit.
+    pub max_slot_deviation: u16,
+
+    /// Slot the price band reference was recorded in.
+    pub band_slot: u64,
+
+    /// Pool reserve of token X before the first swap of `band_slot`.
+    pub band_reserve_x: u64,
+
+    /// Pool reserve of token Y before the first swap of `band_slot`.
+    pub band_reserve_y: u64,
+
+    /// Whether the pool was created through the factory and has a `PoolIndex`.
+    /// Canonical pools have no authority and keep the fee tier they are indexed under.
+    pub canonical: bool,
+
+    /// Whether the pool's TWAP oracle has been created, so closing the pool closes it too.
+    pub has_oracle: bool,
+
+    /// Zeroed space for future fields, so they can be added without a realloc.
+    /// New fields are carved out of its start and must treat zero as their default.
+    pub reserved: [u8; CONFIG_RESERVED_BYTES],
+}
+
+/// Layout of `Config` in the first deployed version of the program
+///
+/// Pools created back then (such as the one in `pool-info.json`) only hold
+/// these fields, in an account sized `LegacyConfig::INIT_SPACE` that doesn't
+/// count the discriminator. `migrate_config` rewrites them into `Config`.
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, InitSpace)]
+pub struct LegacyConfig {
+    pub seed: u64,
+    pub authority: Option<Pubkey>,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub fee: u16,
+    pub locked: bool,
+    pub config_bump: u8,
+    pub lp_bump: u8,
+}
+
+impl LegacyConfig {
+    /// Converts the legacy fields into a current config, with every newer
+    /// setting at the default a new pool starts with
+    ///
+    /// # Arguments
+    /// * `creator` - Account refunded when the pool is closed, unknown to the legacy layout
+    /// * `now` - Current unix timestamp
+    ///
+    /// # Returns
+    /// * `Config` - The same pool in the current layout
+    pub fn migrate(self, creator: Pubkey, now: i64) -> Config {
+        Config {
+            seed: self.seed,
+            authority: self.authority,
+            mint_x: self.mint_x,
+            mint_y: self.mint_y,
+            fee: self.fee,
+            locked: self.locked,
+            config_bump: self.config_bump,
+            lp_bump: self.lp_bump,
+            pending_authority: None,
+            protocol_fee: 0,                  // Legacy pools paid every fee to LPs
+            protocol_fees_x: 0,
+            protocol_fees_y: 0,
+            curve_type: CurveType::ConstantProduct,  // The only curve back then
+            initial_amp: 0,
+            target_amp: 0,
+            ramp_start: 0,
+            ramp_stop: 0,
+            flash_loan_active: false,
+            flash_loan_repayment: 0,
+            flash_loan_is_x: false,
+            dynamic_fee: false,
+            min_fee: self.fee,
+            max_fee: self.fee,
+            volatility: 0,
+            volatility_updated_at: now,
+            creator,
+            version: CONFIG_VERSION,
+            permissioned: false,
+            gatekeeper: Pubkey::default(),
+            referral_fee: 0,
+            max_price_impact: 0,
+            max_slot_deviation: 0,
+            band_slot: 0,
+            band_reserve_x: 0,
+            band_reserve_y: 0,
+            canonical: false,
+            has_oracle: false,
+            reserved: [0; CONFIG_RESERVED_BYTES],
+        }
+    }
+}
+
+impl Config {
+    /// Size of an account created by the first deployed version of the program
+    /// Holds a `LegacyConfig` and must go through `migrate_config`
+    pub const LEGACY_SPACE: usize = LegacyConfig::INIT_SPACE;
+
+    /// Size of an account created before configs were versioned, discriminator included
+    /// Such accounts end right after `creator` and must go through `migrate_config`
+    pub const V0_SPACE: usize = 8 + Config::INIT_SPACE - Config::VERSIONED_SPACE;
+
+    /// Bytes after `creator`: the version byte and the 128 bytes first reserved
+    /// Fields carved out of the reserved space don't change it
+    pub const VERSIONED_SPACE: usize = 1 + 128;
+
+    /// Checks that `signer` is the current update authority of the pool
+    ///
+    /// # Arguments
+    /// * `signer` - Key that signed the admin instruction
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if `signer` is the authority, error otherwise
+    pub fn check_authority(&self, signer: &Pubkey) -> Result<()> {
+        match self.authority {
+            // Renounced or never set: nobody can change the pool anymore
+            None => err!(AmmError::NoAuthoritySet),
+            Some(authority) => {
+                require_keys_eq!(authority, *signer, AmmError::InvalidAuthority);
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the pool reserves backing LP tokens and the swap curve
+    /// The vaults also hold uncollected protocol fees, which are excluded here
+    ///
+    /// # Arguments
+    /// * `vault_x` - Current balance of the X vault
+    /// * `vault_y` - Current balance of the Y vault
+    ///
+    /// # Returns
+    /// * `Result<(u64, u64)>` - (reserve X, reserve Y)
+    pub fn reserves(&self, vault_x: u64, vault_y: u64) -> Result<(u64, u64)> {
+        Ok((
+            vault_x
+                .checked_sub(self.protocol_fees_x)
+                .ok_or(AmmError::Underflow)?,
+            vault_y
+                .checked_sub(self.protocol_fees_y)
+                .ok_or(AmmError::Underflow)?,
+        ))
+    }
+
+    /// Accrues the protocol share of a swap fee paid in token X or Y
+    ///
+    /// # Arguments
+    /// * `is_x` - true if the fee was paid in token X, false for token Y
+    /// * `fee_amount` - Total fee charged on the swap
+    ///
+    /// # Returns
+    /// * `Result<u64>` - Amount accrued to the protocol
+    pub fn accrue_protocol_fee(&mut self, is_x: bool, fee_amount: u64) -> Result<u64> {
+        let (_, protocol_share) = split_fee(fee_amount, self.protocol_fee)?;
+
+        let accrued = match is_x {
+            true => &mut self.protocol_fees_x,
+            false => &mut self.protocol_fees_y,
+        };
+        *accrued = accrued
+            .checked_add(protocol_share)
+            .ok_or(AmmError::Overflow)?;
+
+        Ok(protocol_share)
+    }
+
+    /// Referrer share of a swap fee paid in the input token
+    ///
+    /// # Arguments
+    /// * `fee_amount` - Total fee charged on the swap
+    ///
+    /// # Returns
+    /// * `Result<u64>` - Amount owed to the referrer
+    pub fn referral_share(&self, fee_amount: u64) -> Result<u64> {
+        let (_, referral_share) = split_fee(fee_amount, self.referral_fee)?;
+        Ok(referral_share)
+    }
+
+    /// Records the reserves the pool opens a slot with, as the reference of the
+    /// per-slot price band
+    /// Later swaps of the same slot keep the reference of the first one
+    ///
+    /// # Arguments
+    /// * `reserve_x` - Pool reserve of token X before the swap
+    /// * `reserve_y` - Pool reserve of token Y before the swap
+    /// * `slot` - Current slot
+    pub fn open_price_band(&mut self, reserve_x: u64, reserve_y: u64, slot: u64) {
+        if slot != self.band_slot {
+            self.band_slot = slot;
+            self.band_reserve_x = reserve_x;
+            self.band_reserve_y = reserve_y;
+        }
+    }
+
+    /// StableSwap amplification coefficient in effect at `now`
+    /// Moves linearly from `initial_amp` to `target_amp` while a ramp is in progress
+    pub fn amp(&self, now: i64) -> u64 {
+        ramped_amp(self.initial_amp, self.target_amp, self.ramp_start, self.ramp_stop, now)
+    }
+
+    /// Returns the pricing curve of the pool as it stands at `now`
+    pub fn curve(&self, now: i64) -> Box<dyn Curve> {
+        match self.curve_type {
+            CurveType::ConstantProduct => Box::new(ConstantProductCurve),
+            CurveType::StableSwap => Box::new(StableSwapCurve { amp: self.amp(now) }),
+        }
+    }
+
+    /// Recent price movement in basis points, decayed to `now`
+    pub fn volatility(&self, now: i64) -> u64 {
+        let halvings = now.saturating_sub(self.volatility_updated_at).max(0) / VOLATILITY_HALF_LIFE;
+        match halvings < 64 {
+            true => self.volatility >> halvings,
+            false => 0,
+        }
+    }
+
+    /// Swap fee in basis points charged at `now`
+    ///
+    /// With a static fee this is `fee`. In dynamic mode it starts at `min_fee`,
+    /// grows by `VOLATILITY_FEE_BPS` of the recent price movement and is capped
+    /// at `max_fee`.
+    pub fn swap_fee(&self, now: i64) -> u16 {
+        if !self.dynamic_fee {
+            return self.fee;
+        }
+
+        let surcharge =
+            self.volatility(now) as u128 * VOLATILITY_FEE_BPS as u128 / MAX_FEE_BPS as u128;
+        (self.min_fee as u128 + surcharge).min(self.max_fee as u128) as u16
+    }
+
+    /// Prices an exact-in swap against the given reserves and accrues the protocol fee
+    ///
+    /// # Arguments
+    /// * `reserve_x` - Pool reserve of token X before the swap
+    /// * `reserve_y` - Pool reserve of token Y before the swap
+    /// * `is_x` - true if swapping token X for Y, false if swapping Y for X
+    /// * `amount_in` - Amount of input tokens to swap (fee included)
+    /// * `min_amount_out` - Minimum amount of output tokens expected (slippage protection)
+    /// * `now` - Current unix timestamp
+    ///
+    /// # Returns
+    /// * `Result<SwapResult>` - Input to deposit, output to withdraw and fee charged
+    pub fn swap_exact_in(
+        &mut self,
+        reserve_x: u64,
+        reserve_y: u64,
+        is_x: bool,
+        amount_in: u64,
+        min_amount_out: u64,
+        now: i64,
+    ) -> Result<SwapResult> {
+        // Calculate swap amounts using the pool's curve
+        // This accounts for fees and maintains the invariant
+        let swap_result = self
+            .curve(now)
+            .swap(reserve_x, reserve_y, is_x, amount_in, self.swap_fee(now))?;
+
+        // Slippage protection: ensure user gets at least the minimum expected output
+        require!(swap_result.withdraw >= min_amount_out, AmmError::SlippageExceeded);
+
+        self.settle_swap(reserve_x, reserve_y, is_x, swap_result, now)
+    }
+
+    /// Prices a swap buying exactly `amount_out` and accrues the protocol fee
+    ///
+    /// The required input (fee included) is derived by inverting the curve, then run
+    /// through the same exact-in curve math as `swap_exact_in` so both paths price
+    /// identically. If that math would pay out slightly more than `amount_out`
+    /// because of rounding, the surplus stays in the pool.
+    ///
+    /// # Arguments
+    /// * `reserve_x` - Pool reserve of token X before the swap
+    /// * `reserve_y` - Pool reserve of token Y before the swap
+    /// * `is_x` - true if swapping token X for Y, false if swapping Y for X
+    /// * `amount_out` - Exact amount of output tokens to receive
+    /// * `max_amount_in` - Maximum amount of input tokens to spend (slippage protection)
+    /// * `now` - Current unix timestamp
+    ///
+    /// # Returns
+    /// * `Result<SwapResult>` - Input to deposit, output to withdraw and fee charged
+    pub fn swap_exact_out(
+        &mut self,
+        reserve_x: u64,
+        reserve_y: u64,
+        is_x: bool,
+        amount_out: u64,
+        max_amount_in: u64,
+        now: i64,
+    ) -> Result<SwapResult> {
+        let curve = self.curve(now);
+        let fee = self.swap_fee(now);
+
+        // Smallest input (fee included) that buys `amount_out`
+        let amount_in =
+            curve.amount_in_for_exact_out(reserve_x, reserve_y, is_x, amount_out, fee)?;
+
+        // Slippage protection: ensure the user doesn't pay more than their maximum
+        require!(amount_in <= max_amount_in, AmmError::SlippageExceeded);
+
+        // Price the input with the exact-in math and check it buys `amount_out`
+        let swap_result = curve.swap(reserve_x, reserve_y, is_x, amount_in, fee)?;
+        require!(swap_result.withdraw >= amount_out, AmmError::SlippageExceeded);
+
+        // Pay out exactly what was asked for
+        self.settle_swap(
+            reserve_x,
+            reserve_y,
+            is_x,
+            SwapResult {
+                deposit: swap_result.deposit,
+                withdraw: amount_out,
+                fee: swap_result.fee,
+            },
+            now,
+        )
+    }
+
+    /// Prices an exact-in swap without changing the pool
+    ///
+    /// Runs the same code as `swap_exact_in` on a copy of the config, so the
+    /// quote matches what the swap would execute to the unit, price band included.
+    ///
+    /// # Arguments
+    /// * `reserve_x` - Pool reserve of token X before the swap
+    /// * `reserve_y` - Pool reserve of token Y before the swap
+    /// * `is_x` - true if swapping token X for Y, false if swapping Y for X
+    /// * `amount_in` - Amount of input tokens to swap (fee included)
+    /// * `now` - Current unix timestamp
+    /// * `slot` - Current slot
+    ///
+    /// # Returns
+    /// * `Result<SwapQuote>` - Amounts, fees and post-trade reserves of the swap
+    pub fn quote_swap(
+        &self,
+        reserve_x: u64,
+        reserve_y: u64,
+        is_x: bool,
+        amount_in: u64,
+        now: i64,
+        slot: u64,
+    ) -> Result<SwapQuote> {
+        let mut config = self.clone();
+        let fee_bps = self.swap_fee(now);
+        // Measured against the slot's opening price like the swap, see `open_price_band`
+        config.open_price_band(reserve_x, reserve_y, slot);
+        let swap_result = config.swap_exact_in(reserve_x, reserve_y, is_x, amount_in, 0, now)?;
+
+        // Only the LP share of the fee stays in the reserves
+        let protocol_fee = (config.protocol_fees_x - self.protocol_fees_x)
+            + (config.protocol_fees_y - self.protocol_fees_y);
+        let added = swap_result.deposit - protocol_fee;
+        let (reserve_x, reserve_y) = match is_x {
+            true => (reserve_x + added, reserve_y - swap_result.withdraw),
+            false => (reserve_x - swap_result.withdraw, reserve_y + added),
+        };
+
+        Ok(SwapQuote {
+            amount_in: swap_result.deposit,
+            amount_out: swap_result.withdraw,
+            fee: swap_result.fee,
+            fee_bps,
+            protocol_fee,
+            reserve_x,
+            reserve_y,
+        })
+    }
+
+    /// Prices a deposit of `liquidity` LP tokens into a pool that already has liquidity
+    ///
+    /// # Arguments
+    /// * `reserve_x` - Pool reserve of token X
+    /// * `reserve_y` - Pool reserve of token Y
+    /// * `supply` - Current LP token supply
+    /// * `liquidity` - Amount of LP tokens to mint
+    /// * `now` - Current unix timestamp
+    ///
+    /// # Returns
+    /// * `Result<LiquidityQuote>` - Token amounts the deposit takes
+    pub fn quote_deposit(
+        &self,
+        reserve_x: u64,
+        reserve_y: u64,
+        supply: u64,
+        liquidity: u64,
+        now: i64,
+    ) -> Result<LiquidityQuote> {
+        // The first deposit sets the price, there is nothing to quote
+        require!(supply != 0, AmmError::NoLiquidityInPool);
+        require!(liquidity != 0, AmmError::InvalidAmount);
+
+        let (amount_x, amount_y) = self
+            .curve(now)
+            .deposit_amounts(reserve_x, reserve_y, supply, liquidity)?;
+
+        Ok(LiquidityQuote { amount_x, amount_y, liquidity })
+    }
+
+    /// Prices a withdrawal of `liquidity` LP tokens
+    ///
+    /// # Arguments
+    /// * `reserve_x` - Pool reserve of token X
+    /// * `reserve_y` - Pool reserve of token Y
+    /// * `supply` - Current LP token supply
+    /// * `liquidity` - Amount of LP tokens to burn
+    /// * `now` - Current unix timestamp
+    ///
+    /// # Returns
+    /// * `Result<LiquidityQuote>` - Token amounts the withdrawal releases
+    pub fn quote_withdraw(
+        &self,
+        reserve_x: u64,
+        reserve_y: u64,
+        supply: u64,
+        liquidity: u64,
+        now: i64,
+    ) -> Result<LiquidityQuote> {
+        require!(liquidity != 0, AmmError::InvalidAmount);
+
+        let (amount_x, amount_y) = self
+            .curve(now)
+            .withdraw_amounts(reserve_x, reserve_y, supply, liquidity)?;
+
+        Ok(LiquidityQuote { amount_x, amount_y, liquidity })
+    }
+
+    /// Validates a priced swap, sets aside the protocol share of its fee and
+    /// records the price move it causes
+    ///
+    /// Rejects swaps moving the price by more than `max_price_impact`, or taking
+    /// it further than `max_slot_deviation` from where the slot opened.
+    fn settle_swap(
+        &mut self,
+        reserve_x: u64,
+        reserve_y: u64,
+        is_x: bool,
+        swap_result: SwapResult,
+        now: i64,
+    ) -> Result<SwapResult> {
+        // Validate that the calculated amounts are valid
+        require!(swap_result.deposit != 0, AmmError::InvalidAmount);
+        require!(swap_result.withdraw != 0, AmmError::InvalidAmount);
+
+        // Set aside the protocol share of the fee (paid in the input token)
+        let protocol_share = self.accrue_protocol_fee(is_x, swap_result.fee)?;
+
+        // Reserves after the swap, the protocol share is not part of them
+        let added = swap_result
+            .deposit
+            .checked_sub(protocol_share)
+            .ok_or(AmmError::Underflow)?;
+        let (new_x, new_y) = match is_x {
+            true => (
+                reserve_x.checked_add(added).ok_or(AmmError::Overflow)?,
+                reserve_y.checked_sub(swap_result.withdraw).ok_or(AmmError::Underflow)?,
+            ),
+            false => (
+                reserve_x.checked_sub(swap_result.withdraw).ok_or(AmmError::Underflow)?,
+                reserve_y.checked_add(added).ok_or(AmmError::Overflow)?,
+            ),
+        };
+
+        // Circuit breaker: bound how far one swap, and one slot, can move the price
+        let price_change = price_change_bps(reserve_x, reserve_y, new_x, new_y)?;
+        require!(
+            self.max_price_impact == 0 || price_change <= self.max_price_impact as u64,
+            AmmError::PriceBandExceeded
+        );
+        if self.max_slot_deviation != 0 && self.band_reserve_x != 0 && self.band_reserve_y != 0 {
+            let deviation = price_change_bps(self.band_reserve_x, self.band_reserve_y, new_x, new_y)?;
+            require!(deviation <= self.max_slot_deviation as u64, AmmError::PriceBandExceeded);
+        }
+
+        // Beyond this the surcharge alone exceeds 100%, so there is no point
+        // in keeping more history, which would only slow down the decay
+        let max_volatility = MAX_FEE_BPS as u64 * MAX_FEE_BPS as u64 / VOLATILITY_FEE_BPS as u64;
+        self.volatility = self
+            .volatility(now)
+            .saturating_add(price_change)
+            .min(max_volatility);
+        // Keep the time since the last halving so frequent swaps still decay
+        let elapsed = now.saturating_sub(self.volatility_updated_at).max(0);
+        self.volatility_updated_at = now - elapsed % VOLATILITY_HALF_LIFE;
+
+        Ok(swap_result)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::CONFIG_VERSION;
+
+    fn config(curve_type: CurveType, protocol_fee: u16) -> Config {
+        Config {
+            seed: 0,
+            authority: None,
+            mint_x: Pubkey::new_unique(),
+            mint_y: Pubkey::new_unique(),
+            fee: 30,
+            locked: false,
+            config_bump: 255,
+            lp_bump: 255,
+            pending_authority: None,
+            protocol_fee,
+            protocol_fees_x: 7,
+            protocol_fees_y: 11,
+            curve_type,
+            initial_amp: 100,
+            target_amp: 100,
+            ramp_start: 0,
+            ramp_stop: 0,
+            flash_loan_active: false,
+            flash_loan_repayment: 0,
+            flash_loan_is_x: false,
+            dynamic_fee: false,
+            min_fee: 30,
+            max_fee: 30,
+            volatility: 0,
+            volatility_updated_at: 0,
+            creator: Pubkey::new_unique(),
+            version: CONFIG_VERSION,
+            permissioned: false,
+            gatekeeper: Pubkey::default(),
+            referral_fee: 0,
+            max_price_impact: 0,
+            max_slot_deviation: 0,
+            band_slot: 0,
+            band_reserve_x: 0,
+            band_reserve_y: 0,
+            canonical: false,
+            has_oracle: false,
+            reserved: [0; CONFIG_RESERVED_BYTES],
+        }
+    }
+
+    #[test]
+    fn quote_swap_matches_swap() {
+        let (vault_x, vault_y) = (1_000_000_007u64, 3_000_000_011u64);
+        for curve_type in [CurveType::ConstantProduct, CurveType::StableSwap] {
+            for protocol_fee in [0, 2_500] {
+                for (is_x, amount_in) in [(true, 1_000u64), (false, 12_345_678), (true, 400_000_000)] {
+                    let mut config = config(curve_type, protocol_fee);
+                    let (reserve_x, reserve_y) = config.reserves(vault_x, vault_y).unwrap();
+                    let quote = config.quote_swap(reserve_x, reserve_y, is_x, amount_in, 0, 0).unwrap();
+
+                    let result = config
+                        .swap_exact_in(reserve_x, reserve_y, is_x, amount_in, 0, 0)
+                        .unwrap();
+                    let (vault_x, vault_y) = match is_x {
+                        true => (vault_x + result.deposit, vault_y - result.withdraw),
+                        false => (vault_x - result.withdraw, vault_y + result.deposit),
+                    };
+
+                    assert_eq!((quote.amount_in, quote.amount_out, quote.fee), (result.deposit, result.withdraw, result.fee));
+                    assert_eq!(quote.protocol_fee, split_fee(result.fee, protocol_fee).unwrap().1);
+                    assert_eq!((quote.reserve_x, quote.reserve_y), config.reserves(vault_x, vault_y).unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dynamic_fee_follows_volatility() {
+        let mut config = config(CurveType::ConstantProduct, 0);
+        let (reserve_x, reserve_y) = (1_000_000_000u64, 1_000_000_000u64);
+
+        // Price moves are tracked while the fee is static
+        config.swap_exact_in(reserve_x, reserve_y, true, 50_000_000, 0, 0).unwrap();
+        assert!(config.volatility(0) > 900);
+        assert_eq!(config.swap_fee(0), 30);
+
+        config.dynamic_fee = true;
+        config.min_fee = 5;
+        config.max_fee = 100;
+        let volatility = config.volatility(0);
+        assert_eq!(config.swap_fee(0) as u64, 5 + volatility / 10);
+        assert_eq!(config.quote_swap(reserve_x, reserve_y, true, 1_000, 0, 0).unwrap().fee_bps, config.swap_fee(0));
+
+        // Halves every half-life, back to the minimum once it has decayed
+        assert_eq!(config.volatility(VOLATILITY_HALF_LIFE), volatility / 2);
+        assert_eq!(config.volatility(VOLATILITY_HALF_LIFE * 2 - 1), volatility / 2);
+        assert_eq!(config.swap_fee(VOLATILITY_HALF_LIFE * 64), 5);
+
+        // Capped at the maximum however much the price moves
+        config.swap_exact_in(reserve_x, reserve_y, true, 1_000_000_000, 0, 0).unwrap();
+        assert_eq!(config.swap_fee(0), 100);
+    }
+
+    #[test]
+    fn volatility_decays_between_frequent_swaps() {
+        let mut config = config(CurveType::ConstantProduct, 0);
+        config.volatility = 1_024;
+
+        // A swap that doesn't move the price still keeps the decay going
+        let small = VOLATILITY_HALF_LIFE * 3 / 4;
+        config.swap_exact_in(u64::MAX / 2, u64::MAX / 2, true, 1_000, 0, small).unwrap();
+        config.swap_exact_in(u64::MAX / 2, u64::MAX / 2, true, 1_000, 0, small * 2).unwrap();
+        assert_eq!(config.volatility(small * 2), 512);
+    }
+
+    #[test]
+    fn price_band_limits_moves_per_swap_and_per_slot() {
+        let (reserve_x, reserve_y) = (1_000_000, 1_000_000);
+
+        // About 0.2% and 2% price moves against a 1% limit per swap
+        let mut per_swap = config(CurveType::ConstantProduct, 0);
+        per_swap.max_price_impact = 100;
+        assert!(per_swap.swap_exact_in(reserve_x, reserve_y, true, 1_000, 0, 0).is_ok());
+        assert!(per_swap.swap_exact_in(reserve_x, reserve_y, true, 10_000, 0, 0).is_err());
+        assert!(per_swap.quote_swap(reserve_x, reserve_y, true, 10_000, 0, 1).is_err());
+
+        // Two 2% moves within a slot add up past a 3% band
+        let mut per_slot = config(CurveType::ConstantProduct, 0);
+        per_slot.max_slot_deviation = 300;
+        per_slot.open_price_band(reserve_x, reserve_y, 1);
+        let swap = per_slot.swap_exact_in(reserve_x, reserve_y, true, 10_000, 0, 0).unwrap();
+        let (reserve_x, reserve_y) = (reserve_x + swap.deposit, reserve_y - swap.withdraw);
+        per_slot.open_price_band(reserve_x, reserve_y, 1);
+        assert!(per_slot.swap_exact_in(reserve_x, reserve_y, true, 10_000, 0, 0).is_err());
+        // Quotes see the moves already made in the slot, and a fresh band in the next one
+        assert!(per_slot.quote_swap(reserve_x, reserve_y, true, 10_000, 0, 1).is_err());
+        assert!(per_slot.quote_swap(reserve_x, reserve_y, true, 10_000, 0, 2).is_ok());
+        // Moving back towards the opening price stays within the band
+        assert!(per_slot.swap_exact_in(reserve_x, reserve_y, false, 10_000, 0, 0).is_ok());
+
+        // The next slot measures from where it opens
+        per_slot.open_price_band(reserve_x, reserve_y, 2);
+        assert!(per_slot.swap_exact_in(reserve_x, reserve_y, true, 10_000, 0, 0).is_ok());
+    }
+
+    #[test]
+    fn unversioned_accounts_are_a_prefix_of_the_layout() {
+        let mut config = config(CurveType::StableSwap, 2_500);
+        config.authority = Some(Pubkey::new_unique());
+        let mut data = Vec::new();
+        config.try_serialize(&mut data).unwrap();
+
+        // An account written before versioning: same fields without the trailing
+        // version and reserved bytes, zero-padded to its allocated size
+        data.truncate(data.len() - Config::VERSIONED_SPACE);
+        assert!(data.len() <= Config::V0_SPACE);
+        data.resize(Config::V0_SPACE, 0);
+
+        // What migrate_config reads once the account is grown with zeroes
+        data.resize(8 + Config::INIT_SPACE, 0);
+        let migrated = Config::try_deserialize(&mut &data[..]).unwrap();
+        assert_eq!((migrated.version, migrated.reserved), (0, [0; CONFIG_RESERVED_BYTES]));
+        assert_eq!((migrated.permissioned, migrated.gatekeeper), (false, Pubkey::default()));
+        assert_eq!(migrated.referral_fee, 0);
+        assert_eq!((migrated.seed, migrated.authority, migrated.creator), (config.seed, config.authority, config.creator));
+        assert_eq!((migrated.curve_type, migrated.protocol_fee), (config.curve_type, config.protocol_fee));
+    }
+
+    #[test]
+    fn legacy_accounts_migrate_with_defaults() {
+        // The first deployed program sized configs at its `Config::INIT_SPACE`
+        assert_eq!(Config::LEGACY_SPACE, 110);
+        assert_ne!(Config::LEGACY_SPACE, Config::V0_SPACE);
+
+        let legacy = LegacyConfig {
+            seed: 42,
+            authority: None,
+            mint_x: Pubkey::new_unique(),
+            mint_y: Pubkey::new_unique(),
+            fee: 30,
+            locked: true,
+            config_bump: 254,
+            lp_bump: 253,
+        };
+        let (mint_x, mint_y, creator) = (legacy.mint_x, legacy.mint_y, Pubkey::new_unique());
+        let migrated = legacy.migrate(creator, 1_000);
+        assert_eq!((migrated.seed, migrated.authority, migrated.mint_x, migrated.mint_y), (42, None, mint_x, mint_y));
+        assert_eq!((migrated.fee, migrated.min_fee, migrated.max_fee, migrated.locked), (30, 30, 30, true));
+        assert_eq!((migrated.config_bump, migrated.lp_bump, migrated.creator), (254, 253, creator));
+        assert_eq!((migrated.curve_type, migrated.protocol_fee), (CurveType::ConstantProduct, 0));
+
+        let mut data = Vec::new();
+        migrated.try_serialize(&mut data).unwrap();
+        assert!(data.len() <= 8 + Config::INIT_SPACE);
+    }
+
+    #[test]
+    fn quote_deposit_needs_liquidity() {
+        let config = config(CurveType::ConstantProduct, 0);
+        assert!(config.quote_deposit(0, 0, 0, 1_000, 0).is_err());
+
+        let quote = config.quote_deposit(1_000_000, 2_000_000, 1_000_000, 1_000, 0).unwrap();
+        assert_eq!((quote.amount_x, quote.amount_y), (1_000, 2_000));
+        let quote = config.quote_withdraw(1_000_000, 2_000_000, 1_000_000, 1_000, 0).unwrap();
+        assert_eq!((quote.amount_x, quote.amount_y), (1_000, 2_000));
+    }
+}
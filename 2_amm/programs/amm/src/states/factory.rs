@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+
+/// Registry of the canonical pools of the program
+/// A single PDA derived from "factory", created once by anyone
+#[account]
+#[derive(InitSpace)]
+pub struct Factory {
+    /// Number of canonical pools created through the factory.
+    pub pool_count: u64,
+
+    /// Bump used to derive the PDA for this factory account.
+    pub bump: u8,
+}
+
+/// Pointer from a mint pair and fee tier to its canonical pool
+///
+/// Derived from "pool", the two mints in ascending order and the fee, so there
+/// is at most one canonical pool per pair and fee tier, and clients can find
+/// it without any lookup.
+#[account]
+#[derive(InitSpace)]
+pub struct PoolIndex {
+    /// Config account of the canonical pool.
+    pub config: Pubkey,
+
+    /// Seed of the canonical pool's config PDA.
+    pub seed: u64,
+
+    /// Bump used to derive the PDA for this index account.
+    pub bump: u8,
+}
@@ -0,0 +1,231 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::REWARD_PRECISION;
+use crate::errors::AmmError;
+
+/// Liquidity mining farm of a pool
+///
+/// LPs stake the pool's LP tokens and earn `reward_per_second` of the reward
+/// mint, shared pro rata to their stake. Rewards are tracked MasterChef style:
+/// `acc_reward_per_share` sums the rewards earned by one staked LP token since
+/// the farm started, and each stake records the part of it already accounted
+/// for in `reward_debt`.
+#[account]
+#[derive(InitSpace)]
+pub struct Farm {
+    /// Config account of the pool whose LP tokens are staked.
+    pub config: Pubkey,
+
+    /// Mint rewards are paid in.
+    pub reward_mint: Pubkey,
+
+    /// Rewards emitted per second, shared by all stakers.
+    pub reward_per_second: u64,
+
+    /// Rewards earned per staked LP token since creation, scaled by `REWARD_PRECISION`.
+    pub acc_reward_per_share: u128,
+
+    /// Unix timestamp `acc_reward_per_share` was last brought up to.
+    pub last_reward_time: i64,
+
+    /// LP tokens currently staked in the farm.
+    pub total_staked: u64,
+
+    /// Bump used to derive the PDA for this farm account.
+    pub bump: u8,
+}
+
+/// LP tokens staked in a farm by one user
+/// Uses PDA derived from "stake", the farm and the owner
+#[account]
+#[derive(InitSpace)]
+pub struct StakePosition {
+    /// Farm the tokens are staked in.
+    pub farm: Pubkey,
+
+    /// Owner of the stake.
+    pub owner: Pubkey,
+
+    /// LP tokens staked.
+    pub amount: u64,
+
+    /// Share of `acc_reward_per_share` already accounted for, scaled by `REWARD_PRECISION`.
+    pub reward_debt: u128,
+
+    /// Rewards earned but not paid yet because the reward vault ran short.
+    pub rewards_owed: u64,
+
+    /// Bump used to derive the PDA for this position account.
+    pub bump: u8,
+}
+
+impl Farm {
+    /// Accrues the rewards emitted since the last update to `acc_reward_per_share`
+    /// Nothing accrues while nothing is staked, those emissions are not paid out
+    ///
+    /// # Arguments
+    /// * `now` - Current unix timestamp
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn update(&mut self, now: i64) -> Result<()> {
+        if now <= self.last_reward_time {
+            return Ok(());
+        }
+
+        if self.total_staked > 0 {
+            let elapsed = (now - self.last_reward_time) as u128;
+            let rewards = elapsed
+                .checked_mul(self.reward_per_second as u128)
+                .ok_or(AmmError::Overflow)?;
+            self.acc_reward_per_share = rewards
+                .checked_mul(REWARD_PRECISION)
+                .ok_or(AmmError::Overflow)?
+                .checked_div(self.total_staked as u128)
+                .and_then(|per_share| self.acc_reward_per_share.checked_add(per_share))
+                .ok_or(AmmError::Overflow)?;
+        }
+        self.last_reward_time = now;
+
+        Ok(())
+    }
+
+    /// Rewards a stake of `amount` has earned since `acc_reward_per_share` was zero
+    fn accumulated(&self, amount: u64) -> Result<u128> {
+        Ok(self
+            .acc_reward_per_share
+            .checked_mul(amount as u128)
+            .ok_or(AmmError::Overflow)?
+            / REWARD_PRECISION)
+    }
+
+    /// Moves the rewards a position has earned since its last update into `rewards_owed`
+    /// The farm must be up to date (see `update`)
+    ///
+    /// # Arguments
+    /// * `position` - Stake to settle
+    ///
+    /// # Returns
+    /// * `Result<u64>` - Total rewards owed to the position
+    pub fn settle(&self, position: &mut StakePosition) -> Result<u64> {
+        let pending = self
+            .accumulated(position.amount)?
+            .checked_sub(position.reward_debt)
+            .ok_or(AmmError::Underflow)?;
+        position.rewards_owed = u64::try_from(pending)
+            .ok()
+            .and_then(|pending| position.rewards_owed.checked_add(pending))
+            .ok_or(AmmError::Overflow)?;
+        position.reward_debt = self.accumulated(position.amount)?;
+
+        Ok(position.rewards_owed)
+    }
+
+    /// Changes the stake of a settled position, keeping its rewards accounted for
+    ///
+    /// # Arguments
+    /// * `position` - Stake to change, settled at the current `acc_reward_per_share`
+    /// * `amount` - New amount of LP tokens staked
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn set_stake(&mut self, position: &mut StakePosition, amount: u64) -> Result<()> {
+        self.total_staked = (self.total_staked as u128 + amount as u128)
+            .checked_sub(position.amount as u128)
+            .and_then(|total| u64::try_from(total).ok())
+            .ok_or(AmmError::Overflow)?;
+        position.amount = amount;
+        position.reward_debt = self.accumulated(amount)?;
+
+        Ok(())
+    }
+
+    /// Rewards a position could harvest at `now`, without changing anything
+    ///
+    /// # Arguments
+    /// * `position` - Stake to price
+    /// * `now` - Current unix timestamp
+    ///
+    /// # Returns
+    /// * `Result<u64>` - Rewards owed to the position, paid or not
+    pub fn pending_rewards(&self, position: &StakePosition, now: i64) -> Result<u64> {
+        let mut farm = self.clone();
+        let mut position = position.clone();
+        farm.update(now)?;
+        farm.settle(&mut position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn farm(reward_per_second: u64) -> Farm {
+        Farm {
+            config: Pubkey::new_unique(),
+            reward_mint: Pubkey::new_unique(),
+            reward_per_second,
+            acc_reward_per_share: 0,
+            last_reward_time: 0,
+            total_staked: 0,
+            bump: 255,
+        }
+    }
+
+    fn position(farm: &Farm) -> StakePosition {
+        StakePosition {
+            farm: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            amount: 0,
+            reward_debt: farm.acc_reward_per_share,
+            rewards_owed: 0,
+            bump: 255,
+        }
+    }
+
+    fn stake(farm: &mut Farm, position: &mut StakePosition, amount: u64, now: i64) {
+        farm.update(now).unwrap();
+        farm.settle(position).unwrap();
+        farm.set_stake(position, position.amount + amount).unwrap();
+    }
+
+    #[test]
+    fn rewards_are_shared_pro_rata() {
+        let mut farm = farm(1_000);
+        let (mut alice, mut bob) = (position(&farm), position(&farm));
+
+        // Alice alone for 10 seconds, then Bob joins with three times her stake
+        stake(&mut farm, &mut alice, 100, 0);
+        stake(&mut farm, &mut bob, 300, 10);
+        assert_eq!(farm.pending_rewards(&alice, 10).unwrap(), 10_000);
+        assert_eq!(farm.pending_rewards(&bob, 10).unwrap(), 0);
+
+        assert_eq!(farm.pending_rewards(&alice, 20).unwrap(), 10_000 + 2_500);
+        assert_eq!(farm.pending_rewards(&bob, 20).unwrap(), 7_500);
+        assert_eq!(farm.total_staked, 400);
+    }
+
+    #[test]
+    fn nothing_accrues_while_nothing_is_staked() {
+        let mut farm = farm(1_000);
+        farm.update(100).unwrap();
+        assert_eq!((farm.acc_reward_per_share, farm.last_reward_time), (0, 100));
+
+        let mut alice = position(&farm);
+        stake(&mut farm, &mut alice, 1, 100);
+        assert_eq!(farm.pending_rewards(&alice, 101).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn unstaking_keeps_earned_rewards() {
+        let mut farm = farm(7);
+        let mut alice = position(&farm);
+        stake(&mut farm, &mut alice, 3, 0);
+
+        farm.update(10).unwrap();
+        assert_eq!(farm.settle(&mut alice).unwrap(), 69);
+        farm.set_stake(&mut alice, 0).unwrap();
+        assert_eq!((farm.total_staked, alice.amount, alice.rewards_owed), (0, 0, 69));
+        assert_eq!(farm.pending_rewards(&alice, 1_000).unwrap(), 69);
+    }
+}
@@ -0,0 +1,276 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::{OBSERVATION_CAPACITY, OBSERVATION_INTERVAL};
+use crate::errors::AmmError;
+use crate::math::price_q64;
+
+/// Time-weighted average price oracle of a pool
+///
+/// Keeps Uniswap v2 style cumulative prices (price * seconds, Q64.64) that are
+/// updated after every swap, deposit and withdrawal, plus a ring buffer of past snapshots to average over a time window.
+/// Accumulators wrap on overflow on purpose, only their differences matter.
+#[account]
+#[derive(InitSpace)]
+pub struct Oracle {
+    /// Config account of the pool this oracle belongs to.
+    pub config: Pubkey,
+
+    /// Unix timestamp of the last accumulator update.
+    pub last_update: i64,
+
+    /// Sum of (price of X in Y) * seconds since creation, Q64.64, wrapping.
+    pub price_x_cumulative: u128,
+
+    /// Sum of (price of Y in X) * seconds since creation, Q64.64, wrapping.
+    pub price_y_cumulative: u128,
+
+    /// Pool reserve of token X recorded by the last update.
+    pub reserve_x: u64,
+
+    /// Pool reserve of token Y recorded by the last update.
+    pub reserve_y: u64,
+
+    /// Index of the most recent observation in `observations`.
+    pub observation_index: u16,
+
+    /// Number of observations written so far (capped at the buffer capacity).
+    pub observation_count: u16,
+
+    /// Ring buffer of accumulator snapshots, one at most every `OBSERVATION_INTERVAL` seconds.
+    pub observations: [Observation; OBSERVATION_CAPACITY],
+
+    /// Bump used to derive the PDA for this oracle account.
+    pub bump: u8,
+}
+
+/// Snapshot of the oracle accumulators at a point in time
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct Observation {
+    /// Unix timestamp of the snapshot.
+    pub timestamp: i64,
+
+    /// Value of `price_x_cumulative` at `timestamp`.
+    pub price_x_cumulative: u128,
+
+    /// Value of `price_y_cumulative` at `timestamp`.
+    pub price_y_cumulative: u128,
+}
+
+/// Time-weighted average prices over a window
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Twap {
+    /// Average price of one X in Y, Q64.64.
+    pub price_x: u128,
+
+    /// Average price of one Y in X, Q64.64.
+    pub price_y: u128,
+
+    /// Length of the averaged window in seconds (at least the requested window).
+    pub window: i64,
+}
+
+impl Oracle {
+    /// Empty oracle of a pool
+    /// Accumulators stand still until the pool's next trade records its reserves
+    ///
+    /// # Arguments
+    /// * `config` - Config account of the pool
+    /// * `bump` - Bump of the oracle PDA
+    /// * `now` - Current unix timestamp
+    pub fn new(config: Pubkey, bump: u8, now: i64) -> Self {
+        Oracle {
+            config,
+            last_update: now,
+            price_x_cumulative: 0,
+            price_y_cumulative: 0,
+            reserve_x: 0,
+            reserve_y: 0,
+            observation_index: 0,
+            observation_count: 0,
+            observations: [Observation::default(); OBSERVATION_CAPACITY],
+            bump,
+        }
+    }
+
+    /// Folds the time elapsed since the last update into the price accumulators,
+    /// stores a new observation if enough time has passed since the last one and
+    /// records the reserves the pool trades at from now on
+    ///
+    /// The elapsed time is priced with the reserves recorded by the previous
+    /// update, never with vault balances, so tokens donated to the vaults in
+    /// between can't move the TWAP. Must be called *after* the trade.
+    ///
+    /// # Arguments
+    /// * `reserve_x` - Pool reserve of token X after the trade
+    /// * `reserve_y` - Pool reserve of token Y after the trade
+    /// * `now` - Current unix timestamp
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise
+    pub fn update(&mut self, reserve_x: u64, reserve_y: u64, now: i64) -> Result<()> {
+        let (price_x_cumulative, price_y_cumulative) = self.cumulatives_at(now)?;
+        self.price_x_cumulative = price_x_cumulative;
+        self.price_y_cumulative = price_y_cumulative;
+        self.last_update = now;
+        self.reserve_x = reserve_x;
+        self.reserve_y = reserve_y;
+
+        // Throttle observations so the buffer spans a useful amount of time
+        let last = self.observations[self.observation_index as usize];
+        if self.observation_count > 0 && now - last.timestamp < OBSERVATION_INTERVAL {
+            return Ok(());
+        }
+
+        if self.observation_count > 0 {
+            self.observation_index = ((self.observation_index as usize + 1) % OBSERVATION_CAPACITY) as u16;
+        }
+        self.observation_count = (self.observation_count + 1).min(OBSERVATION_CAPACITY as u16);
+        self.observations[self.observation_index as usize] = Observation {
+            timestamp: now,
+            price_x_cumulative,
+            price_y_cumulative,
+        };
+
+        Ok(())
+    }
+
+    /// Computes the time-weighted average prices over at least the last `window` seconds
+    ///
+    /// Averages from the newest observation that is at least `window` seconds old,
+    /// so the effective window can exceed the requested one by up to `OBSERVATION_INTERVAL`.
+    /// The time since the last update is priced with the reserves it recorded.
+    ///
+    /// # Arguments
+    /// * `window` - Minimum number of seconds to average over
+    /// * `now` - Current unix timestamp
+    ///
+    /// # Returns
+    /// * `Result<Twap>` - Average prices and the effective window
+    pub fn twap(&self, window: u32, now: i64) -> Result<Twap> {
+        require!(window > 0, AmmError::InvalidAmount);
+
+        let (price_x_cumulative, price_y_cumulative) = self.cumulatives_at(now)?;
+        let target = now - window as i64;
+
+        // Walk back from the newest observation to the first one old enough
+        let start = (0..self.observation_count as usize)
+            .map(|i| {
+                let index = (self.observation_index as usize + OBSERVATION_CAPACITY - i) % OBSERVATION_CAPACITY;
+                self.observations[index]
+            })
+            .find(|observation| observation.timestamp <= target)
+            .ok_or(AmmError::OracleHistoryTooShort)?;
+
+        let elapsed = now - start.timestamp;
+
+        Ok(Twap {
+            price_x: price_x_cumulative.wrapping_sub(start.price_x_cumulative) / elapsed as u128,
+            price_y: price_y_cumulative.wrapping_sub(start.price_y_cumulative) / elapsed as u128,
+            window: elapsed,
+        })
+    }
+
+    /// Returns the accumulators extrapolated to `now` with the reserves recorded by the last update
+    ///
+    /// # Returns
+    /// * `Result<(u128, u128)>` - (price X cumulative, price Y cumulative)
+    fn cumulatives_at(&self, now: i64) -> Result<(u128, u128)> {
+        let (reserve_x, reserve_y) = (self.reserve_x, self.reserve_y);
+        let elapsed = now.saturating_sub(self.last_update);
+
+        // An empty pool has no price, so the accumulators just stand still
+        if elapsed <= 0 || reserve_x == 0 || reserve_y == 0 {
+            return Ok((self.price_x_cumulative, self.price_y_cumulative));
+        }
+
+        Ok((
+            self.price_x_cumulative
+                .wrapping_add(price_q64(reserve_x, reserve_y)?.wrapping_mul(elapsed as u128)),
+            self.price_y_cumulative
+                .wrapping_add(price_q64(reserve_y, reserve_x)?.wrapping_mul(elapsed as u128)),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn oracle(now: i64) -> Oracle {
+        Oracle::new(Pubkey::default(), 0, now)
+    }
+
+    #[test]
+    fn twap_of_constant_price() {
+        let mut oracle = oracle(1_000);
+        for t in (1_000..=5_000).step_by(60) {
+            oracle.update(1_000, 2_000, t).unwrap();
+        }
+
+        let twap = oracle.twap(600, 5_000).unwrap();
+        assert_eq!(twap.price_x, 2 << 64);
+        assert_eq!(twap.price_y, 1 << 63);
+        assert!(twap.window >= 600 && twap.window < 600 + OBSERVATION_INTERVAL);
+    }
+
+    #[test]
+    fn twap_weights_prices_by_time() {
+        let mut oracle = oracle(0);
+        oracle.update(1_000, 1_000, 0).unwrap();
+        // Price of X is 1 for 300s, then a trade moves it to 3 for 100s
+        oracle.update(1_000, 3_000, 300).unwrap();
+        oracle.update(1_000, 3_000, 400).unwrap();
+
+        let twap = oracle.twap(400, 400).unwrap();
+        assert_eq!(twap.window, 400);
+        assert_eq!(twap.price_x, (1u128 << 64) * 6 / 4);
+    }
+
+    #[test]
+    fn twap_survives_accumulator_overflow() {
+        let mut oracle = oracle(0);
+        oracle.price_x_cumulative = u128::MAX - 5;
+        oracle.price_y_cumulative = u128::MAX - 5;
+        oracle.update(10, 20, 0).unwrap();
+        oracle.update(10, 20, 120).unwrap();
+
+        assert!(oracle.price_x_cumulative < u128::MAX - 5);
+        let twap = oracle.twap(120, 120).unwrap();
+        assert_eq!(twap.price_x, 2 << 64);
+    }
+
+    #[test]
+    fn twap_needs_enough_history() {
+        let mut oracle = oracle(0);
+        oracle.update(1_000, 1_000, 0).unwrap();
+        assert!(oracle.twap(60, 30).is_err());
+
+        // Once the buffer wraps the oldest observations are gone
+        for t in (60..=60 * (OBSERVATION_CAPACITY as i64 + 1)).step_by(60) {
+            oracle.update(1_000, 1_000, t).unwrap();
+        }
+        let now = 60 * (OBSERVATION_CAPACITY as i64 + 1);
+        assert_eq!(oracle.observation_count as usize, OBSERVATION_CAPACITY);
+        assert!(oracle.twap((now - 30) as u32, now).is_err());
+        assert!(oracle.twap(600, now).is_ok());
+    }
+
+    #[test]
+    fn twap_ignores_reserves_changed_since_the_last_update() {
+        let mut oracle = oracle(0);
+        oracle.update(1_000, 1_000, 0).unwrap();
+        oracle.update(1_000, 1_000, 600).unwrap();
+        let before = oracle.twap(600, 900).unwrap();
+
+        // A donation to the vaults doesn't go through `update`, so reading the
+        // TWAP later keeps pricing the time since with the recorded reserves
+        let (vault_x, vault_y) = (1_000, 1_000_000);
+        assert_ne!((vault_x, vault_y), (oracle.reserve_x, oracle.reserve_y));
+        assert_eq!(oracle.twap(600, 900).unwrap(), before);
+        assert_eq!(before.price_x, 1 << 64);
+
+        // The next trade records the new reserves, which only price the time after it
+        oracle.update(vault_x, vault_y, 900).unwrap();
+        assert_eq!(oracle.twap(600, 900).unwrap(), before);
+    }
+}
@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+
+/// Outcome of a swap, as priced by `Config::quote_swap`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapQuote {
+    /// Amount of input tokens paid, fee included.
+    pub amount_in: u64,
+
+    /// Amount of output tokens received.
+    pub amount_out: u64,
+
+    /// Fee paid in input tokens (LP and protocol shares).
+    pub fee: u64,
+
+    /// Swap fee rate applied, in basis points.
+    pub fee_bps: u16,
+
+    /// Protocol share of the fee, set aside for the treasury.
+    pub protocol_fee: u64,
+
+    /// Pool reserve of token X after the swap.
+    pub reserve_x: u64,
+
+    /// Pool reserve of token Y after the swap.
+    pub reserve_y: u64,
+}
+
+/// Outcome of a deposit or withdrawal, as priced by `Config::quote_deposit`
+/// and `Config::quote_withdraw`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LiquidityQuote {
+    /// Amount of token X deposited or withdrawn.
+    pub amount_x: u64,
+
+    /// Amount of token Y deposited or withdrawn.
+    pub amount_y: u64,
+
+    /// LP tokens minted or burned.
+    pub liquidity: u64,
+}
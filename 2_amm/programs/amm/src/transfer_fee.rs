@@ -0,0 +1,103 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{transfer_fee::TransferFeeConfig, BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+    state::Mint,
+};
+
+use crate::errors::AmmError;
+
+/// Mint extensions a pool token may have
+///
+/// Anything else could stop the vaults from moving tokens (non-transferable,
+/// transfer hooks needing extra accounts, default frozen accounts) or let a
+/// third party take them out (permanent delegate), so such mints are rejected.
+const SUPPORTED_EXTENSIONS: [ExtensionType; 4] = [
+    ExtensionType::TransferFeeConfig,
+    ExtensionType::MetadataPointer,
+    ExtensionType::TokenMetadata,
+    ExtensionType::InterestBearingConfig,
+];
+
+/// Reads the transfer fee extension of a mint, if it has one
+fn transfer_fee_config(mint: &AccountInfo) -> Result<Option<TransferFeeConfig>> {
+    // Only Token-2022 mints have extensions
+    if *mint.owner != spl_token_2022::ID {
+        return Ok(None);
+    }
+
+    let data = mint.try_borrow_data()?;
+    let mint = StateWithExtensions::<Mint>::unpack(&data)?;
+    Ok(mint.get_extension::<TransferFeeConfig>().ok().copied())
+}
+
+/// Checks that a mint can be pooled
+///
+/// # Arguments
+/// * `mint` - Mint of one of the pool tokens, owned by either token program
+///
+/// # Returns
+/// * `Result<()>` - Ok if every extension of the mint is supported
+pub fn check_supported_mint(mint: &AccountInfo) -> Result<()> {
+    if *mint.owner != spl_token_2022::ID {
+        return Ok(());
+    }
+
+    let data = mint.try_borrow_data()?;
+    let mint = StateWithExtensions::<Mint>::unpack(&data)?;
+    for extension in mint.get_extension_types()? {
+        require!(SUPPORTED_EXTENSIONS.contains(&extension), AmmError::UnsupportedMint);
+    }
+    Ok(())
+}
+
+/// Fee withheld by the token program when transferring `amount` of a mint
+///
+/// # Arguments
+/// * `mint` - Mint of the transferred token
+/// * `amount` - Amount sent, fee included
+///
+/// # Returns
+/// * `Result<u64>` - Fee in the current epoch, zero for mints without a transfer fee
+pub fn transfer_fee(mint: &AccountInfo, amount: u64) -> Result<u64> {
+    let Some(config) = transfer_fee_config(mint)? else {
+        return Ok(0);
+    };
+
+    let fee = config
+        .calculate_epoch_fee(Clock::get()?.epoch, amount)
+        .ok_or(AmmError::Overflow)?;
+    Ok(fee)
+}
+
+/// Amount arriving at the destination when sending `amount` of a mint
+///
+/// # Arguments
+/// * `mint` - Mint of the transferred token
+/// * `amount` - Amount sent, fee included
+///
+/// # Returns
+/// * `Result<u64>` - Amount received once the transfer fee is withheld
+pub fn amount_received(mint: &AccountInfo, amount: u64) -> Result<u64> {
+    let fee = transfer_fee(mint, amount)?;
+    Ok(amount.checked_sub(fee).ok_or(AmmError::Underflow)?)
+}
+
+/// Amount to send so that `received` arrives at the destination
+///
+/// # Arguments
+/// * `mint` - Mint of the transferred token
+/// * `received` - Amount that must arrive, fee excluded
+///
+/// # Returns
+/// * `Result<u64>` - Smallest amount to send, transfer fee included
+pub fn amount_to_send(mint: &AccountInfo, received: u64) -> Result<u64> {
+    let Some(config) = transfer_fee_config(mint)? else {
+        return Ok(received);
+    };
+
+    let fee = config
+        .calculate_inverse_epoch_fee(Clock::get()?.epoch, received)
+        .ok_or(AmmError::Overflow)?;
+    Ok(received.checked_add(fee).ok_or(AmmError::Overflow)?)
+}
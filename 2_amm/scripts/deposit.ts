@@ -51,6 +51,24 @@ async function main() {
 
   // Load pool info
   const poolInfo = JSON.parse(fs.readFileSync("pool-info.json", "utf8"));
+
+  // TWAP oracle PDA of the pool
+  const [oracle] = anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("oracle"),
+      new anchor.web3.PublicKey(poolInfo.configPda).toBuffer(),
+    ],
+    program.programId
+  );
+
+  // Holds the LP tokens locked forever by the first deposit
+  const [lockedLp] = anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("locked_lp"),
+      new anchor.web3.PublicKey(poolInfo.configPda).toBuffer(),
+    ],
+    program.programId
+  );
   console.log("\n📊 Pool Info:");
   console.log("Config PDA:", poolInfo.configPda);
   console.log("LP Mint:", poolInfo.lpMint);
@@ -264,21 +282,28 @@ async function main() {
 
   console.log("\n💧 Executing deposit...");
 
+  // Reject the deposit if it hasn't landed within 60 seconds
+  const expiration = new BN(Math.floor(Date.now() / 1000) + 60);
+
   try {
     const tx = await program.methods
-      .deposit(new BN(lpTokensToMint), new BN(maxX), new BN(maxY))
+      .deposit(new BN(lpTokensToMint), new BN(maxX), new BN(maxY), expiration, false)
       .accounts({
         user: admin.publicKey,
         mintX: new anchor.web3.PublicKey(poolInfo.mintX),
         mintY: new anchor.web3.PublicKey(poolInfo.mintY),
         config: new anchor.web3.PublicKey(poolInfo.configPda),
+        oracle,
         mintLp: new anchor.web3.PublicKey(poolInfo.lpMint),
         vaultX: new anchor.web3.PublicKey(poolInfo.vaultX),
         vaultY: new anchor.web3.PublicKey(poolInfo.vaultY),
         userAtaX: userAtaX.address,
         userAtaY: userAtaY.address,
         userAtaLp: userLpAta.address,
+        lockedLp,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
         associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
@@ -60,6 +60,17 @@ async function main() {
     program.programId
   );
 
+  const [oracle] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("oracle"), configPda.toBuffer()],
+    program.programId
+  );
+
+  // Holds the LP tokens locked forever by the first deposit
+  const [lockedLp] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("locked_lp"), configPda.toBuffer()],
+    program.programId
+  );
+
   const vaultX = await getAssociatedTokenAddress(mintX, configPda, true);
   const vaultY = await getAssociatedTokenAddress(mintY, configPda, true);
 
@@ -68,11 +79,12 @@ async function main() {
   console.log("LP Mint:", lpMint.toString());
   console.log("Vault X:", vaultX.toString());
   console.log("Vault Y:", vaultY.toString());
+  console.log("Oracle:", oracle.toString());
 
   // Initialize the pool
   console.log("\n⚡ Initializing pool...");
   const tx = await program.methods
-    .initialize(seed, fee, null)
+    .initialize(seed, fee, null, { constantProduct: {} }, new BN(0))
     .accounts({
       admin: admin.publicKey,
       mintX,
@@ -81,7 +93,10 @@ async function main() {
       mintLp: lpMint,
       vaultX,
       vaultY,
+      oracle,
       tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
       associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
       systemProgram: anchor.web3.SystemProgram.programId,
     })
@@ -127,24 +142,33 @@ async function main() {
   );
   console.log(`LP Tokens to mint: ${lpTokensToMint} (geometric mean)`);
 
+  // Reject the deposit if it hasn't landed within 60 seconds
+  const expiration = new BN(Math.floor(Date.now() / 1000) + 60);
+
   const depositTx = await program.methods
     .deposit(
       new BN(lpTokensToMint),
       new BN(TOKEN_A_AMOUNT_RAW),
-      new BN(TOKEN_B_AMOUNT_RAW)
+      new BN(TOKEN_B_AMOUNT_RAW),
+      expiration,
+      false
     )
     .accounts({
       user: admin.publicKey,
       mintX,
       mintY,
       config: configPda,
+      oracle,
       mintLp: lpMint,
       vaultX,
       vaultY,
       userAtaX: userAtaX.address,
       userAtaY: userAtaY.address,
       userAtaLp: userLpAta.address,
+      lockedLp,
       tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
       associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
       systemProgram: anchor.web3.SystemProgram.programId,
     })
@@ -162,6 +186,7 @@ async function main() {
     lpMint: lpMint.toString(),
     vaultX: vaultX.toString(),
     vaultY: vaultY.toString(),
+    oracle: oracle.toString(),
     mintX: mintX.toString(),
     mintY: mintY.toString(),
     seed: seed.toNumber(),
@@ -51,6 +51,15 @@ async function main() {
 
   // Load pool info
   const poolInfo = JSON.parse(fs.readFileSync("pool-info.json", "utf8"));
+
+  // TWAP oracle PDA of the pool
+  const [oracle] = anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("oracle"),
+      new anchor.web3.PublicKey(poolInfo.configPda).toBuffer(),
+    ],
+    program.programId
+  );
   console.log("\n📊 Pool Info:");
   console.log("Config PDA:", poolInfo.configPda);
   console.log("LP Mint:", poolInfo.lpMint);
@@ -180,14 +189,24 @@ async function main() {
 
   console.log("\n💸 Executing withdrawal...");
 
+  // Reject the withdrawal if it hasn't landed within 60 seconds
+  const expiration = new BN(Math.floor(Date.now() / 1000) + 60);
+
   try {
     const tx = await program.methods
-      .withdraw(new BN(lpAmountRaw), new BN(tokenXAmount), new BN(tokenYAmount))
+      .withdraw(
+        new BN(lpAmountRaw),
+        new BN(tokenXAmount),
+        new BN(tokenYAmount),
+        expiration,
+        false
+      )
       .accounts({
         user: admin.publicKey,
         mintX: new anchor.web3.PublicKey(poolInfo.mintX),
         mintY: new anchor.web3.PublicKey(poolInfo.mintY),
         config: new anchor.web3.PublicKey(poolInfo.configPda),
+        oracle,
         mintLp: new anchor.web3.PublicKey(poolInfo.lpMint),
         vaultX: new anchor.web3.PublicKey(poolInfo.vaultX),
         vaultY: new anchor.web3.PublicKey(poolInfo.vaultY),
@@ -195,6 +214,8 @@ async function main() {
         userAtaY: userAtaY.address,
         userAtaLp: userLpAta.address,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
         associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
@@ -0,0 +1,50 @@
+//! Builds the AMM program for the SVM tests
+//!
+//! The integration tests load the compiled program into LiteSVM. Building it
+//! here with `cargo build-sbf` keeps `cargo test` self-contained and the binary
+//! in step with the program sources. Without the Solana toolchain the tests
+//! fall back to the output of `anchor build` in `target/deploy`, and with
+//! neither they fail, naming the command to run.
+
+use std::{env, path::PathBuf, process::Command};
+
+fn main() {
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let program = manifest_dir.join("../programs/amm");
+    println!("cargo:rerun-if-changed={}", program.join("src").display());
+    println!("cargo:rerun-if-changed={}", program.join("Cargo.toml").display());
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let status = Command::new(cargo)
+        .arg("build-sbf")
+        .arg("--manifest-path")
+        .arg(program.join("Cargo.toml"))
+        .arg("--sbf-out-dir")
+        .arg(&out_dir)
+        // The outer build holds the lock on the workspace target directory
+        .env("CARGO_TARGET_DIR", out_dir.join("sbf-target"))
+        .env_remove("CARGO_ENCODED_RUSTFLAGS")
+        .status();
+
+    let so = match status {
+        Ok(status) if status.success() => out_dir.join("amm.so"),
+        _ => {
+            let deployed = manifest_dir.join("../target/deploy/amm.so");
+            // Pick up the program once `anchor build` writes it
+            println!("cargo:rerun-if-changed={}", deployed.display());
+            match deployed.exists() {
+                true => println!(
+                    "cargo:warning=`cargo build-sbf` failed or is not installed, \
+                     the SVM tests load target/deploy/amm.so from `anchor build` instead"
+                ),
+                false => println!(
+                    "cargo:warning=`cargo build-sbf` failed or is not installed and target/deploy/amm.so \
+                     doesn't exist, run `anchor build` before `cargo test` or the SVM tests fail"
+                ),
+            }
+            deployed
+        }
+    };
+    println!("cargo:rustc-env=AMM_PROGRAM={}", so.display());
+}
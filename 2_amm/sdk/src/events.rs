@@ -0,0 +1,169 @@
+//! Decoding of the events the AMM program emits in transaction logs
+
+use amm::events::{
+    AllowlistUpdated, ConfigMigrated, ConfigUpdated, FarmUpdated, FlashLoanRepaid, LiquidityAdded, LiquidityRemoved,
+    PoolClosed, PoolInitialized, ProtocolFeesCollected, RewardsHarvested, Staked, Swapped, Unstaked,
+};
+use anchor_lang::{prelude::Pubkey, Event};
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+/// Log line prefix `emit!` writes event data under
+const PROGRAM_DATA: &str = "Program data: ";
+
+/// Any event of the AMM program
+#[derive(Clone, Debug)]
+pub enum AmmEvent {
+    PoolInitialized(PoolInitialized),
+    LiquidityAdded(LiquidityAdded),
+    LiquidityRemoved(LiquidityRemoved),
+    Swapped(Swapped),
+    ConfigUpdated(ConfigUpdated),
+    ProtocolFeesCollected(ProtocolFeesCollected),
+    FlashLoanRepaid(FlashLoanRepaid),
+    ConfigMigrated(ConfigMigrated),
+    PoolClosed(PoolClosed),
+    FarmUpdated(FarmUpdated),
+    Staked(Staked),
+    Unstaked(Unstaked),
+    RewardsHarvested(RewardsHarvested),
+    AllowlistUpdated(AllowlistUpdated),
+}
+
+/// Decodes one event from its raw data (discriminator followed by the Borsh fields)
+///
+/// # Arguments
+/// * `data` - Event data, as carried by a `Program data:` log line once base64-decoded
+///
+/// # Returns
+/// * `Option<AmmEvent>` - The event, or None if the data isn't an AMM event
+pub fn decode_event(data: &[u8]) -> Option<AmmEvent> {
+    fn decode<T: Event>(data: &[u8]) -> Option<T> {
+        let fields = data.strip_prefix(T::DISCRIMINATOR)?;
+        T::try_from_slice(fields).ok()
+    }
+
+    decode(data)
+        .map(AmmEvent::PoolInitialized)
+        .or_else(|| decode(data).map(AmmEvent::LiquidityAdded))
+        .or_else(|| decode(data).map(AmmEvent::LiquidityRemoved))
+        .or_else(|| decode(data).map(AmmEvent::Swapped))
+        .or_else(|| decode(data).map(AmmEvent::ConfigUpdated))
+        .or_else(|| decode(data).map(AmmEvent::ProtocolFeesCollected))
+        .or_else(|| decode(data).map(AmmEvent::FlashLoanRepaid))
+        .or_else(|| decode(data).map(AmmEvent::ConfigMigrated))
+        .or_else(|| decode(data).map(AmmEvent::PoolClosed))
+        .or_else(|| decode(data).map(AmmEvent::FarmUpdated))
+        .or_else(|| decode(data).map(AmmEvent::Staked))
+        .or_else(|| decode(data).map(AmmEvent::Unstaked))
+        .or_else(|| decode(data).map(AmmEvent::RewardsHarvested))
+        .or_else(|| decode(data).map(AmmEvent::AllowlistUpdated))
+}
+
+/// Extracts the AMM events from the log messages of a transaction
+///
+/// Follows the `invoke`/`success` lines to know which program is running, so
+/// data logged by other programs (including ones the AMM calls into, or ones
+/// calling the AMM) is never mistaken for an AMM event.
+///
+/// # Arguments
+/// * `logs` - Log messages of the transaction, in order
+///
+/// # Returns
+/// * `Vec<AmmEvent>` - Events emitted by the AMM program, in order
+pub fn parse_logs<S: AsRef<str>>(logs: &[S]) -> Vec<AmmEvent> {
+    let program_id = amm::ID.to_string();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+
+    for log in logs {
+        let log = log.as_ref();
+        if let Some(data) = log.strip_prefix(PROGRAM_DATA) {
+            if stack.last() == Some(&program_id.as_str()) {
+                if let Some(event) = STANDARD.decode(data).ok().and_then(|data| decode_event(&data)) {
+                    events.push(event);
+                }
+            }
+            continue;
+        }
+
+        // "Program <id> invoke [depth]", "Program <id> success" or "Program <id> failed: ..."
+        let mut words = log.split(' ');
+        if let (Some("Program"), Some(id), Some(status)) = (words.next(), words.next(), words.next()) {
+            if id.parse::<Pubkey>().is_err() {
+                continue;
+            }
+            match status {
+                "invoke" => stack.push(id),
+                "success" | "failed:" => {
+                    stack.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::Discriminator;
+
+    fn data_log(event: &impl Event) -> String {
+        format!("{PROGRAM_DATA}{}", STANDARD.encode(event.data()))
+    }
+
+    fn swapped() -> Swapped {
+        Swapped {
+            seed: 7,
+            user: Pubkey::new_unique(),
+            is_x: true,
+            amount_in: 1_000,
+            amount_out: 990,
+            fee: 3,
+            fee_bps: 30,
+            referrer: None,
+            referral_fee: 0,
+            reserve_x: 101_000,
+            reserve_y: 99_010,
+        }
+    }
+
+    #[test]
+    fn decodes_events_of_the_amm_only() {
+        let other = Pubkey::new_unique();
+        let token = anchor_lang::solana_program::pubkey!("TokenkegQfeZyiNwAJbNbGqPXxZU6Vw8HsHAv3P2dHY");
+        let logs = vec![
+            format!("Program {} invoke [1]", amm::ID),
+            "Program log: Instruction: Swap".to_string(),
+            format!("Program {token} invoke [2]"),
+            data_log(&swapped()),
+            format!("Program {token} success"),
+            data_log(&swapped()),
+            format!("Program {} consumed 20000 of 200000 compute units", amm::ID),
+            format!("Program {} success", amm::ID),
+            format!("Program {other} invoke [1]"),
+            data_log(&swapped()),
+            format!("Program {} invoke [2]", amm::ID),
+            data_log(&ProtocolFeesCollected { seed: 7, amount_x: 5, amount_y: 0 }),
+            format!("Program {} success", amm::ID),
+            format!("Program {other} failed: custom program error: 0x1"),
+        ];
+
+        let events = parse_logs(&logs);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], AmmEvent::Swapped(event) if event.amount_out == 990 && event.reserve_y == 99_010));
+        assert!(matches!(&events[1], AmmEvent::ProtocolFeesCollected(event) if event.amount_x == 5));
+    }
+
+    #[test]
+    fn ignores_unknown_data() {
+        assert!(decode_event(&[0; 8]).is_none());
+        assert!(decode_event(&[]).is_none());
+        // Right discriminator, truncated fields
+        let mut data = Swapped::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&[1, 2, 3]);
+        assert!(decode_event(&data).is_none());
+    }
+}
@@ -0,0 +1,8 @@
+//! Off-chain helpers for clients of the AMM program
+
+pub mod events;
+pub mod pda;
+pub mod quote;
+
+pub use events::{decode_event, parse_logs, AmmEvent};
+pub use quote::PoolState;
@@ -0,0 +1,66 @@
+//! Addresses of the AMM program accounts
+
+use anchor_lang::prelude::Pubkey;
+
+/// Address of the config of the pool created with `seed`
+pub fn config_address(seed: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &amm::ID).0
+}
+
+/// Address of the TWAP oracle of the pool whose config is `config`
+pub fn oracle_address(config: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"oracle", config.as_ref()], &amm::ID).0
+}
+
+/// Address of the pool factory
+pub fn factory_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"factory"], &amm::ID).0
+}
+
+/// Address of the farm of the pool whose config is `config`
+pub fn farm_address(config: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"farm", config.as_ref()], &amm::ID).0
+}
+
+/// Address of the stake of `owner` in `farm`
+pub fn stake_position_address(farm: Pubkey, owner: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"stake", farm.as_ref(), owner.as_ref()], &amm::ID).0
+}
+
+/// Address of the allowlist entry admitting `user` to the pool whose config is `config`
+pub fn allowlist_entry_address(config: Pubkey, user: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"allowlist", config.as_ref(), user.as_ref()], &amm::ID).0
+}
+
+/// Mints of a pair in the order canonical pools use (ascending)
+pub fn sorted_mints(mint_a: Pubkey, mint_b: Pubkey) -> (Pubkey, Pubkey) {
+    match mint_a < mint_b {
+        true => (mint_a, mint_b),
+        false => (mint_b, mint_a),
+    }
+}
+
+/// Address of the `PoolIndex` of a pair and fee tier, in either mint order
+///
+/// The account exists once the canonical pool has been created with
+/// `create_pool`, and points to its config.
+pub fn pool_index_address(mint_a: Pubkey, mint_b: Pubkey, fee: u16) -> Pubkey {
+    let (mint_x, mint_y) = sorted_mints(mint_a, mint_b);
+    Pubkey::find_program_address(
+        &[b"pool", mint_x.as_ref(), mint_y.as_ref(), &fee.to_le_bytes()],
+        &amm::ID,
+    )
+    .0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pool_index_ignores_mint_order() {
+        let (mint_a, mint_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert_eq!(pool_index_address(mint_a, mint_b, 30), pool_index_address(mint_b, mint_a, 30));
+        assert_ne!(pool_index_address(mint_a, mint_b, 30), pool_index_address(mint_a, mint_b, 5));
+    }
+}
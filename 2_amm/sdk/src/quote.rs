@@ -0,0 +1,318 @@
+//! Off-chain pricing of AMM trades from snapshots of the pool accounts
+
+use amm::constants::MINIMUM_LIQUIDITY;
+use amm::errors::AmmError;
+use amm::states::{Config, LiquidityQuote, SwapQuote};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+/// Snapshot of the accounts a pool prices its trades from
+///
+/// Quotes run the program's own math (`Config::quote_*`) behind the same
+/// checks as the instructions, so they match what a transaction against the
+/// same state would execute, fee rounding included.
+///
+/// Amounts are the ones entering and leaving the vaults. For Token-2022 mints
+/// with a transfer fee the user pays or receives that fee on top, which the
+/// `quote_*` instructions of the program account for.
+#[derive(Clone, Debug)]
+pub struct PoolState {
+    /// Decoded config account of the pool.
+    pub config: Config,
+
+    /// Balance of the X vault, protocol fees included.
+    pub vault_x: u64,
+
+    /// Balance of the Y vault, protocol fees included.
+    pub vault_y: u64,
+
+    /// Supply of the LP mint.
+    pub lp_supply: u64,
+}
+
+impl PoolState {
+    /// Decodes a snapshot from the raw data of the pool accounts
+    ///
+    /// # Arguments
+    /// * `config` - Data of the config account
+    /// * `vault_x` - Data of the X vault, an SPL Token or Token-2022 account
+    /// * `vault_y` - Data of the Y vault, an SPL Token or Token-2022 account
+    /// * `mint_lp` - Data of the LP mint
+    ///
+    /// # Returns
+    /// * `Result<PoolState>` - The snapshot, or an error if an account doesn't decode
+    pub fn decode(config: &[u8], vault_x: &[u8], vault_y: &[u8], mint_lp: &[u8]) -> Result<Self> {
+        Ok(Self {
+            config: Config::try_deserialize(&mut &config[..])?,
+            vault_x: TokenAccount::try_deserialize(&mut &vault_x[..])?.amount,
+            vault_y: TokenAccount::try_deserialize(&mut &vault_y[..])?.amount,
+            lp_supply: Mint::try_deserialize(&mut &mint_lp[..])?.supply,
+        })
+    }
+
+    /// Pool reserves backing LP tokens, net of uncollected protocol fees
+    pub fn reserves(&self) -> Result<(u64, u64)> {
+        self.config.reserves(self.vault_x, self.vault_y)
+    }
+
+    /// Prices `swap`
+    ///
+    /// # Arguments
+    /// * `is_x` - true if swapping token X for Y, false if swapping Y for X
+    /// * `amount_in` - Amount of input tokens to swap
+    /// * `min_amount_out` - Minimum amount of output tokens expected
+    /// * `now` - Unix timestamp the swap would execute at
+    /// * `slot` - Slot the swap would execute in, for the price band
+    ///
+    /// # Returns
+    /// * `Result<SwapQuote>` - Amounts, fees and post-trade reserves, or the error the swap would fail with
+    pub fn quote_swap(&self, is_x: bool, amount_in: u64, min_amount_out: u64, now: i64, slot: u64) -> Result<SwapQuote> {
+        require!(amount_in > 0, AmmError::InvalidAmount);
+        self.check_tradable()?;
+        let (reserve_x, reserve_y) = self.reserves()?;
+
+        let quote = self.config.quote_swap(reserve_x, reserve_y, is_x, amount_in, now, slot)?;
+        require!(quote.amount_out >= min_amount_out, AmmError::SlippageExceeded);
+
+        Ok(quote)
+    }
+
+    /// Prices `deposit`
+    ///
+    /// # Arguments
+    /// * `amount` - Amount of LP tokens to mint
+    /// * `max_x` - Maximum amount of token X to deposit
+    /// * `max_y` - Maximum amount of token Y to deposit
+    /// * `now` - Unix timestamp the deposit would execute at
+    ///
+    /// # Returns
+    /// * `Result<LiquidityQuote>` - Token amounts taken, or the error the deposit would fail with
+    pub fn quote_deposit(&self, amount: u64, max_x: u64, max_y: u64, now: i64) -> Result<LiquidityQuote> {
+        self.check_tradable()?;
+        require!(amount != 0, AmmError::InvalidAmount);
+        let (reserve_x, reserve_y) = self.reserves()?;
+
+        // The first deposit takes the amounts as given and sets the price,
+        // whatever was sent to the vaults before it stays in the pool
+        if self.lp_supply == 0 {
+            require!(
+                amount >= MINIMUM_LIQUIDITY && max_x >= MINIMUM_LIQUIDITY && max_y >= MINIMUM_LIQUIDITY,
+                AmmError::LiquidityLessThanMinimum
+            );
+            return Ok(LiquidityQuote { amount_x: max_x, amount_y: max_y, liquidity: amount });
+        }
+
+        let quote = self
+            .config
+            .quote_deposit(reserve_x, reserve_y, self.lp_supply, amount, now)?;
+        require!(quote.amount_x <= max_x && quote.amount_y <= max_y, AmmError::SlippageExceeded);
+
+        Ok(quote)
+    }
+
+    /// Prices `withdraw`
+    ///
+    /// # Arguments
+    /// * `amount` - Amount of LP tokens to burn
+    /// * `min_x` - Minimum amount of token X to receive
+    /// * `min_y` - Minimum amount of token Y to receive
+    /// * `now` - Unix timestamp the withdrawal would execute at
+    ///
+    /// # Returns
+    /// * `Result<LiquidityQuote>` - Token amounts released, or the error the withdrawal would fail with
+    pub fn quote_withdraw(&self, amount: u64, min_x: u64, min_y: u64, now: i64) -> Result<LiquidityQuote> {
+        self.check_tradable()?;
+        require!(amount != 0, AmmError::InvalidAmount);
+        let (reserve_x, reserve_y) = self.reserves()?;
+
+        let quote = self
+            .config
+            .quote_withdraw(reserve_x, reserve_y, self.lp_supply, amount, now)?;
+        require!(quote.amount_x >= min_x && quote.amount_y >= min_y, AmmError::SlippageExceeded);
+
+        Ok(quote)
+    }
+
+    /// Pool-wide checks every trading instruction makes first
+    fn check_tradable(&self) -> Result<()> {
+        require!(!self.config.locked, AmmError::PoolLocked);
+        require!(!self.config.flash_loan_active, AmmError::FlashLoanActive);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amm::constants::{CONFIG_RESERVED_BYTES, CONFIG_VERSION};
+    use amm::CurveType;
+    use anchor_lang::solana_program::program_option::COption;
+    use anchor_lang::solana_program::program_pack::Pack;
+    use anchor_spl::token::spl_token;
+    use proptest::prelude::*;
+
+    fn pool(curve_type: CurveType, fee: u16, protocol_fee: u16, vaults: (u64, u64), lp_supply: u64) -> PoolState {
+        PoolState {
+            config: Config {
+                seed: 42,
+                authority: None,
+                mint_x: Pubkey::new_unique(),
+                mint_y: Pubkey::new_unique(),
+                fee,
+                locked: false,
+                config_bump: 255,
+                lp_bump: 255,
+                pending_authority: None,
+                protocol_fee,
+                protocol_fees_x: 3,
+                protocol_fees_y: 5,
+                curve_type,
+                initial_amp: 100,
+                target_amp: 100,
+                ramp_start: 0,
+                ramp_stop: 0,
+                flash_loan_active: false,
+                flash_loan_repayment: 0,
+                flash_loan_is_x: false,
+                dynamic_fee: false,
+                min_fee: fee,
+                max_fee: fee,
+                volatility: 0,
+                volatility_updated_at: 0,
+                creator: Pubkey::new_unique(),
+                version: CONFIG_VERSION,
+                permissioned: false,
+                gatekeeper: Pubkey::default(),
+                referral_fee: 0,
+                max_price_impact: 0,
+                max_slot_deviation: 0,
+                band_slot: 0,
+                band_reserve_x: 0,
+                band_reserve_y: 0,
+                canonical: false,
+                has_oracle: false,
+                reserved: [0; CONFIG_RESERVED_BYTES],
+            },
+            vault_x: vaults.0,
+            vault_y: vaults.1,
+            lp_supply,
+        }
+    }
+
+    fn curve_type() -> impl Strategy<Value = CurveType> {
+        prop_oneof![Just(CurveType::ConstantProduct), Just(CurveType::StableSwap)]
+    }
+
+    proptest! {
+        #[test]
+        fn swap_quotes_match_the_program(
+            curve_type in curve_type(),
+            fee in 0u16..1_000,
+            protocol_fee in 0u16..=10_000,
+            vault_x in 1_000u64..1_000_000_000_000,
+            vault_y in 1_000u64..1_000_000_000_000,
+            is_x: bool,
+            amount_in in 1u64..1_000_000_000_000,
+        ) {
+            let pool = pool(curve_type, fee, protocol_fee, (vault_x, vault_y), 1_000_000);
+            let (reserve_x, reserve_y) = pool.reserves().unwrap();
+
+            // What the swap instruction runs against the same state
+            let mut config = pool.config.clone();
+            let executed = config.swap_exact_in(reserve_x, reserve_y, is_x, amount_in, 0, 0);
+
+            match (pool.quote_swap(is_x, amount_in, 0, 0, 0), executed) {
+                (Ok(quote), Ok(result)) => {
+                    prop_assert_eq!(quote.amount_in, result.deposit);
+                    prop_assert_eq!(quote.amount_out, result.withdraw);
+                    prop_assert_eq!(quote.fee, result.fee);
+                    prop_assert_eq!(quote.fee_bps, fee);
+                    let accrued = (config.protocol_fees_x - pool.config.protocol_fees_x)
+                        + (config.protocol_fees_y - pool.config.protocol_fees_y);
+                    prop_assert_eq!(quote.protocol_fee, accrued);
+                }
+                (Err(_), Err(_)) => {}
+                (quote, executed) => prop_assert!(false, "quote {:?} but swap {:?}", quote, executed),
+            }
+        }
+
+        #[test]
+        fn liquidity_quotes_match_the_program(
+            curve_type in curve_type(),
+            vault_x in 1_000u64..1_000_000_000_000,
+            vault_y in 1_000u64..1_000_000_000_000,
+            lp_supply in 1_000u64..1_000_000_000_000,
+            amount in 1u64..1_000_000_000_000,
+        ) {
+            let pool = pool(curve_type, 30, 0, (vault_x, vault_y), lp_supply);
+            let (reserve_x, reserve_y) = pool.reserves().unwrap();
+            let curve = pool.config.curve(0);
+
+            let deposit = pool.quote_deposit(amount, u64::MAX, u64::MAX, 0).map(|q| (q.amount_x, q.amount_y));
+            let executed = curve.deposit_amounts(reserve_x, reserve_y, lp_supply, amount);
+            prop_assert_eq!(deposit.ok(), executed.ok());
+
+            let withdraw = pool.quote_withdraw(amount, 0, 0, 0).map(|q| (q.amount_x, q.amount_y));
+            let executed = curve.withdraw_amounts(reserve_x, reserve_y, lp_supply, amount);
+            prop_assert_eq!(withdraw.ok(), executed.ok());
+        }
+    }
+
+    #[test]
+    fn rejects_what_the_program_rejects() {
+        let mut locked = pool(CurveType::ConstantProduct, 30, 0, (1_000_003, 1_000_005), 1_000_000);
+        locked.config.locked = true;
+        assert!(locked.quote_swap(true, 1_000, 0, 0, 0).is_err());
+        assert!(locked.quote_withdraw(1_000, 0, 0, 0).is_err());
+
+        let pool = pool(CurveType::ConstantProduct, 30, 0, (1_000_003, 1_000_005), 1_000_000);
+        let quote = pool.quote_swap(true, 1_000, 0, 0, 0).unwrap();
+        assert!(pool.quote_swap(true, 1_000, quote.amount_out + 1, 0, 0).is_err());
+        assert!(pool.quote_deposit(1_000, 999, 1_000, 0).is_err());
+
+        // The first deposit must clear the minimum liquidity
+        let mut empty = pool.clone();
+        (empty.vault_x, empty.vault_y, empty.lp_supply) = (3, 5, 0);
+        assert!(empty.quote_deposit(MINIMUM_LIQUIDITY, MINIMUM_LIQUIDITY - 1, MINIMUM_LIQUIDITY, 0).is_err());
+        let first = empty.quote_deposit(MINIMUM_LIQUIDITY, 5_000, 7_000, 0).unwrap();
+        assert_eq!((first.amount_x, first.amount_y, first.liquidity), (5_000, 7_000, MINIMUM_LIQUIDITY));
+
+        // Tokens sent to the vaults before it don't stop it
+        (empty.vault_x, empty.vault_y) = (3 + 1, 5 + 1);
+        let first = empty.quote_deposit(MINIMUM_LIQUIDITY, 5_000, 7_000, 0).unwrap();
+        assert_eq!((first.amount_x, first.amount_y, first.liquidity), (5_000, 7_000, MINIMUM_LIQUIDITY));
+    }
+
+    #[test]
+    fn decodes_account_data() {
+        let expected = pool(CurveType::StableSwap, 4, 2_500, (10_003, 20_005), 7_000);
+
+        let mut config = Vec::new();
+        expected.config.try_serialize(&mut config).unwrap();
+        let vault = |amount| {
+            let mut data = vec![0; spl_token::state::Account::LEN];
+            spl_token::state::Account {
+                amount,
+                state: spl_token::state::AccountState::Initialized,
+                ..Default::default()
+            }
+            .pack_into_slice(&mut data);
+            data
+        };
+        let mut mint_lp = vec![0; spl_token::state::Mint::LEN];
+        spl_token::state::Mint {
+            mint_authority: COption::Some(Pubkey::new_unique()),
+            supply: 7_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        }
+        .pack_into_slice(&mut mint_lp);
+
+        let decoded = PoolState::decode(&config, &vault(10_003), &vault(20_005), &mint_lp).unwrap();
+        assert_eq!((decoded.vault_x, decoded.vault_y, decoded.lp_supply), (10_003, 20_005, 7_000));
+        assert_eq!(decoded.config.curve_type, CurveType::StableSwap);
+        assert_eq!(decoded.reserves().unwrap(), (10_000, 20_000));
+        assert!(PoolState::decode(&config[1..], &vault(1), &vault(1), &mint_lp).is_err());
+    }
+}
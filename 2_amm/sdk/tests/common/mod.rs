@@ -0,0 +1,644 @@
+//! In-process SVM harness running the compiled AMM program
+//!
+//! Loads the program built by `build.rs`, or `target/deploy/amm.so` from
+//! `anchor build` when the Solana toolchain isn't available to it.
+
+// Each test binary uses a different part of the harness
+#![allow(dead_code)]
+
+use amm::errors::AmmError;
+use amm::{CurveType, Twap};
+use amm_sdk::PoolState;
+use anchor_lang::{AnchorDeserialize, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::{
+    get_associated_token_address_with_program_id, spl_associated_token_account,
+};
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{transfer_fee, ExtensionType, StateWithExtensions},
+};
+use litesvm::{types::TransactionResult, LiteSVM};
+use solana_sdk::{
+    clock::Clock,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction, system_program, sysvar,
+    transaction::{Transaction, TransactionError},
+};
+
+/// Decimals of both pool tokens
+pub const DECIMALS: u8 = 6;
+
+/// Token program and extensions of a pool token
+#[derive(Clone, Copy, Debug)]
+pub enum Token {
+    /// SPL Token mint
+    Spl,
+    /// Token-2022 mint withholding a transfer fee, in basis points
+    TransferFee(u16),
+    /// Wrapped SOL, the SPL Token native mint
+    NativeSol,
+}
+
+/// The farm of a pool, paying rewards in an SPL Token mint
+pub struct Farm {
+    pub address: Pubkey,
+    pub reward_mint: Pubkey,
+    pub lp_vault: Pubkey,
+    pub reward_vault: Pubkey,
+}
+
+/// A pool of the AMM running in a fresh SVM
+pub struct Pool {
+    pub svm: LiteSVM,
+    pub admin: Keypair,
+    pub seed: u64,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub token_program_x: Pubkey,
+    pub token_program_y: Pubkey,
+    pub config: Pubkey,
+    pub oracle: Pubkey,
+    pub mint_lp: Pubkey,
+    pub vault_x: Pubkey,
+    pub vault_y: Pubkey,
+    pub locked_lp: Pubkey,
+}
+
+impl Pool {
+    /// Deploys the program and creates a pool of two SPL Token mints with its
+    /// oracle, `admin` being its authority
+    pub fn new(fee: u16, curve_type: CurveType, amp: u64) -> Self {
+        Self::with_tokens(fee, curve_type, amp, Token::Spl, Token::Spl)
+    }
+
+    /// Same as `new`, with token X and token Y of the given kinds
+    pub fn with_tokens(fee: u16, curve_type: CurveType, amp: u64, token_x: Token, token_y: Token) -> Self {
+        let mut svm = LiteSVM::new();
+        let program = env!("AMM_PROGRAM");
+        assert!(
+            std::path::Path::new(program).exists(),
+            "the AMM program isn't built: run `anchor build` (or install the Solana toolchain \
+             for `cargo build-sbf`), then `cargo test` again"
+        );
+        svm.add_program_from_file(amm::ID, program).unwrap_or_else(|err| {
+            panic!("can't load {program} ({err}), rebuild it with `anchor build`")
+        });
+
+        let admin = Keypair::new();
+        svm.airdrop(&admin.pubkey(), 100_000_000_000).unwrap();
+
+        let seed: u64 = 42;
+        let (mint_x, token_program_x) = create_mint(&mut svm, &admin, token_x);
+        let (mint_y, token_program_y) = create_mint(&mut svm, &admin, token_y);
+        let config = Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &amm::ID).0;
+        let mut pool = Self {
+            seed,
+            mint_x,
+            mint_y,
+            token_program_x,
+            token_program_y,
+            config,
+            oracle: Pubkey::find_program_address(&[b"oracle", config.as_ref()], &amm::ID).0,
+            mint_lp: Pubkey::find_program_address(&[b"lp", config.as_ref()], &amm::ID).0,
+            vault_x: get_associated_token_address_with_program_id(&config, &mint_x, &token_program_x),
+            vault_y: get_associated_token_address_with_program_id(&config, &mint_y, &token_program_y),
+            locked_lp: Pubkey::find_program_address(&[b"locked_lp", config.as_ref()], &amm::ID).0,
+            svm,
+            admin,
+        };
+
+        let initialize = pool.instruction(
+            amm::accounts::Initialize {
+                admin: pool.admin.pubkey(),
+                mint_x,
+                mint_y,
+                config,
+                mint_lp: pool.mint_lp,
+                vault_x: pool.vault_x,
+                vault_y: pool.vault_y,
+                oracle: pool.oracle,
+                token_program: spl_token::ID,
+                token_program_x,
+                token_program_y,
+                associated_token_program: spl_associated_token_account::ID,
+                system_program: system_program::ID,
+            },
+            amm::instruction::Initialize {
+                seed,
+                fee,
+                authority: Some(pool.admin.pubkey()),
+                curve_type,
+                amp,
+            },
+        );
+        let admin = pool.admin.insecure_clone();
+        pool.send(&[initialize], &admin).unwrap();
+
+        pool
+    }
+
+    /// Creates a user holding `amount_x` of token X and `amount_y` of token Y
+    pub fn user(&mut self, amount_x: u64, amount_y: u64) -> Keypair {
+        let user = Keypair::new();
+        self.svm.airdrop(&user.pubkey(), 10_000_000_000).unwrap();
+
+        let admin = self.admin.insecure_clone();
+        for (mint, amount) in [(self.mint_x, amount_x), (self.mint_y, amount_y)] {
+            let program = self.token_program(mint);
+            let ata = self.ata(&user, mint);
+            let create = spl_associated_token_account::instruction::create_associated_token_account(
+                &admin.pubkey(),
+                &user.pubkey(),
+                &mint,
+                &program,
+            );
+            let mut instructions = vec![create];
+            if mint == spl_token::native_mint::ID {
+                // Wrapped SOL is minted by syncing lamports sent to the account
+                instructions.push(system_instruction::transfer(&admin.pubkey(), &ata, amount));
+                instructions.push(spl_token::instruction::sync_native(&program, &ata).unwrap());
+            } else {
+                instructions.push(
+                    spl_token_2022::instruction::mint_to(&program, &mint, &ata, &admin.pubkey(), &[], amount)
+                        .unwrap(),
+                );
+            }
+            self.send(&instructions, &admin).unwrap();
+        }
+
+        user
+    }
+
+    pub fn deposit(&mut self, user: &Keypair, amount: u64, max_x: u64, max_y: u64) -> TransactionResult {
+        let accounts = self.deposit_accounts(user);
+        let expiration = self.now() + 60;
+        let deposit = self.instruction(
+            accounts,
+            amm::instruction::Deposit { amount, max_x, max_y, expiration, native_sol: false },
+        );
+        self.send(&[deposit], user)
+    }
+
+    pub fn deposit_accounts(&self, user: &Keypair) -> amm::accounts::Deposit {
+        amm::accounts::Deposit {
+            user: user.pubkey(),
+            mint_x: self.mint_x,
+            mint_y: self.mint_y,
+            config: self.config,
+            oracle: self.oracle,
+            mint_lp: self.mint_lp,
+            vault_x: self.vault_x,
+            vault_y: self.vault_y,
+            user_ata_x: self.ata(user, self.mint_x),
+            user_ata_y: self.ata(user, self.mint_y),
+            user_ata_lp: self.ata(user, self.mint_lp),
+            allowlist_entry: None,
+            instructions: None,
+            locked_lp: self.locked_lp,
+            token_program: spl_token::ID,
+            token_program_x: self.token_program_x,
+            token_program_y: self.token_program_y,
+            associated_token_program: spl_associated_token_account::ID,
+            system_program: system_program::ID,
+        }
+    }
+
+    pub fn withdraw(&mut self, user: &Keypair, amount: u64, min_x: u64, min_y: u64) -> TransactionResult {
+        let accounts = self.withdraw_accounts(user);
+        let expiration = self.now() + 60;
+        let withdraw = self.instruction(
+            accounts,
+            amm::instruction::Withdraw { amount, min_x, min_y, expiration, native_sol: false },
+        );
+        self.send(&[withdraw], user)
+    }
+
+    pub fn withdraw_accounts(&self, user: &Keypair) -> amm::accounts::Withdraw {
+        amm::accounts::Withdraw {
+            user: user.pubkey(),
+            mint_x: self.mint_x,
+            mint_y: self.mint_y,
+            config: self.config,
+            oracle: self.oracle,
+            mint_lp: self.mint_lp,
+            vault_x: self.vault_x,
+            vault_y: self.vault_y,
+            user_ata_x: self.ata(user, self.mint_x),
+            user_ata_y: self.ata(user, self.mint_y),
+            user_ata_lp: self.ata(user, self.mint_lp),
+            token_program: spl_token::ID,
+            token_program_x: self.token_program_x,
+            token_program_y: self.token_program_y,
+            associated_token_program: spl_associated_token_account::ID,
+            system_program: system_program::ID,
+        }
+    }
+
+    pub fn swap(&mut self, user: &Keypair, is_x: bool, amount_in: u64, min_amount_out: u64) -> TransactionResult {
+        let accounts = self.swap_accounts(user);
+        let expiration = self.now() + 60;
+        let swap = self.instruction(
+            accounts,
+            amm::instruction::Swap { is_x, amount_in, min_amount_out, expiration, native_sol: false },
+        );
+        self.send(&[swap], user)
+    }
+
+    pub fn swap_accounts(&self, user: &Keypair) -> amm::accounts::Swap {
+        amm::accounts::Swap {
+            user: user.pubkey(),
+            mint_x: self.mint_x,
+            mint_y: self.mint_y,
+            config: self.config,
+            oracle: self.oracle,
+            mint_lp: self.mint_lp,
+            vault_x: self.vault_x,
+            vault_y: self.vault_y,
+            user_ata_x: self.ata(user, self.mint_x),
+            user_ata_y: self.ata(user, self.mint_y),
+            allowlist_entry: None,
+            instructions: None,
+            referrer_token_account: None,
+            token_program_x: self.token_program_x,
+            token_program_y: self.token_program_y,
+            associated_token_program: spl_associated_token_account::ID,
+            system_program: system_program::ID,
+        }
+    }
+
+    /// Remaining accounts of a route leg selling token X (`is_x`) or Y through the pool
+    pub fn route_leg(&self, user: &Keypair, is_x: bool) -> Vec<AccountMeta> {
+        let (mint_in, mint_out, vault_in, vault_out) = match is_x {
+            true => (self.mint_x, self.mint_y, self.vault_x, self.vault_y),
+            false => (self.mint_y, self.mint_x, self.vault_y, self.vault_x),
+        };
+        vec![
+            AccountMeta::new(self.config, false),
+            AccountMeta::new(self.oracle, false),
+            AccountMeta::new_readonly(mint_in, false),
+            AccountMeta::new_readonly(mint_out, false),
+            AccountMeta::new(vault_in, false),
+            AccountMeta::new(vault_out, false),
+            AccountMeta::new(self.ata(user, mint_out), false),
+        ]
+    }
+
+    /// Swaps `amount_in` of the first leg's input token through `legs`
+    pub fn route_swap(
+        &mut self,
+        user: &Keypair,
+        legs: &[Vec<AccountMeta>],
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> TransactionResult {
+        let accounts = amm::accounts::RouteSwap {
+            user: user.pubkey(),
+            user_ata_in: self.ata(user, legs[0][2].pubkey),
+            token_program: spl_token::ID,
+            token_2022_program: spl_token_2022::ID,
+        };
+        let expiration = self.now() + 60;
+        let mut route = self.instruction(
+            accounts,
+            amm::instruction::RouteSwap { amount_in, min_amount_out, expiration },
+        );
+        route.accounts.extend(legs.iter().flatten().cloned());
+        self.send(&[route], user)
+    }
+
+    pub fn flash_accounts(&self, user: &Keypair) -> amm::accounts::FlashLoan {
+        amm::accounts::FlashLoan {
+            user: user.pubkey(),
+            mint_x: self.mint_x,
+            mint_y: self.mint_y,
+            config: self.config,
+            oracle: self.oracle,
+            vault_x: self.vault_x,
+            vault_y: self.vault_y,
+            user_ata_x: self.ata(user, self.mint_x),
+            user_ata_y: self.ata(user, self.mint_y),
+            instructions: sysvar::instructions::ID,
+            token_program_x: self.token_program_x,
+            token_program_y: self.token_program_y,
+        }
+    }
+
+    /// Creates the farm of the pool and funds its reward vault with `funding` rewards
+    pub fn farm(&mut self, reward_per_second: u64, funding: u64) -> Farm {
+        let admin = self.admin.insecure_clone();
+        let (reward_mint, _) = create_mint(&mut self.svm, &admin, Token::Spl);
+        let address = Pubkey::find_program_address(&[b"farm", self.config.as_ref()], &amm::ID).0;
+        let farm = Farm {
+            address,
+            reward_mint,
+            lp_vault: get_associated_token_address_with_program_id(&address, &self.mint_lp, &spl_token::ID),
+            reward_vault: get_associated_token_address_with_program_id(&address, &reward_mint, &spl_token::ID),
+        };
+
+        let initialize = self.instruction(
+            amm::accounts::InitializeFarm {
+                authority: admin.pubkey(),
+                config: self.config,
+                mint_lp: self.mint_lp,
+                reward_mint,
+                farm: farm.address,
+                farm_lp_vault: farm.lp_vault,
+                reward_vault: farm.reward_vault,
+                token_program: spl_token::ID,
+                reward_token_program: spl_token::ID,
+                associated_token_program: spl_associated_token_account::ID,
+                system_program: system_program::ID,
+            },
+            amm::instruction::InitializeFarm { reward_per_second },
+        );
+        self.send(&[initialize], &admin).unwrap();
+        self.fund_farm(&farm, funding);
+
+        farm
+    }
+
+    /// Mints `amount` rewards into the reward vault of `farm`
+    pub fn fund_farm(&mut self, farm: &Farm, amount: u64) {
+        let admin = self.admin.insecure_clone();
+        let mint_to = spl_token_2022::instruction::mint_to(
+            &spl_token::ID,
+            &farm.reward_mint,
+            &farm.reward_vault,
+            &admin.pubkey(),
+            &[],
+            amount,
+        )
+        .unwrap();
+        self.send(&[mint_to], &admin).unwrap();
+    }
+
+    /// Runs a staking instruction (`Stake`, `Unstake`, `Harvest`, `EmergencyWithdraw`) for `user`
+    pub fn stake(&mut self, farm: &Farm, user: &Keypair, data: impl InstructionData) -> TransactionResult {
+        let accounts = amm::accounts::Stake {
+            user: user.pubkey(),
+            config: self.config,
+            farm: farm.address,
+            position: Pubkey::find_program_address(
+                &[b"stake", farm.address.as_ref(), user.pubkey().as_ref()],
+                &amm::ID,
+            )
+            .0,
+            mint_lp: self.mint_lp,
+            reward_mint: farm.reward_mint,
+            farm_lp_vault: farm.lp_vault,
+            reward_vault: farm.reward_vault,
+            user_ata_lp: self.ata(user, self.mint_lp),
+            user_ata_reward: self.ata(user, farm.reward_mint),
+            token_program: spl_token::ID,
+            reward_token_program: spl_token::ID,
+            associated_token_program: spl_associated_token_account::ID,
+            system_program: system_program::ID,
+        };
+        let stake = self.instruction(accounts, data);
+        self.send(&[stake], user)
+    }
+
+    /// Closes the pool, signed by `signer`, sending what's left in the vaults to
+    /// token accounts of the admin (the creator)
+    pub fn close(&mut self, signer: &Keypair) -> TransactionResult {
+        let admin = self.admin.insecure_clone();
+        for mint in [self.mint_x, self.mint_y] {
+            let create = spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                &admin.pubkey(),
+                &admin.pubkey(),
+                &mint,
+                &self.token_program(mint),
+            );
+            self.send(&[create], &admin).unwrap();
+        }
+
+        let accounts = self.close_accounts(signer);
+        let close = self.instruction(accounts, amm::instruction::ClosePool {});
+        self.send(&[close], signer)
+    }
+
+    /// Accounts closing the pool, with the admin's token accounts receiving the reserves
+    pub fn close_accounts(&self, signer: &Keypair) -> amm::accounts::ClosePool {
+        amm::accounts::ClosePool {
+            signer: signer.pubkey(),
+            creator: self.admin.pubkey(),
+            mint_x: self.mint_x,
+            mint_y: self.mint_y,
+            config: self.config,
+            mint_lp: self.mint_lp,
+            locked_lp: self.svm.get_account(&self.locked_lp).map(|_| self.locked_lp),
+            vault_x: self.vault_x,
+            vault_y: self.vault_y,
+            creator_ata_x: self.ata(&self.admin, self.mint_x),
+            creator_ata_y: self.ata(&self.admin, self.mint_y),
+            oracle: Some(self.oracle),
+            pool_index: None,
+            token_program: spl_token::ID,
+            token_program_x: self.token_program_x,
+            token_program_y: self.token_program_y,
+            associated_token_program: spl_associated_token_account::ID,
+        }
+    }
+
+    /// Adds `user` to the pool's allowlist, returning their entry
+    pub fn allow_trader(&mut self, user: &Keypair) -> Pubkey {
+        let entry = amm_sdk::pda::allowlist_entry_address(self.config, user.pubkey());
+        let accounts = amm::accounts::AllowTrader {
+            authority: self.admin.pubkey(),
+            config: self.config,
+            allowlist_entry: entry,
+            system_program: system_program::ID,
+        };
+        let allow = self.instruction(accounts, amm::instruction::AllowTrader { user: user.pubkey() });
+        let admin = self.admin.insecure_clone();
+        self.send(&[allow], &admin).unwrap();
+        entry
+    }
+
+    /// Runs an admin instruction signed by the pool authority
+    pub fn update_config(&mut self, data: impl InstructionData) -> TransactionResult {
+        let accounts = amm::accounts::UpdateConfig {
+            authority: self.admin.pubkey(),
+            config: self.config,
+        };
+        let update = self.instruction(accounts, data);
+        let admin = self.admin.insecure_clone();
+        self.send(&[update], &admin)
+    }
+
+    /// Reads the pool's TWAP over at least `window` seconds, as another program would via CPI
+    pub fn twap(&mut self, window: u32) -> Twap {
+        let accounts = amm::accounts::GetTwap {
+            config: self.config,
+            oracle: self.oracle,
+        };
+        let get_twap = self.instruction(accounts, amm::instruction::GetTwap { window });
+        let admin = self.admin.insecure_clone();
+        let meta = self.send(&[get_twap], &admin).unwrap();
+        Twap::try_from_slice(&meta.return_data.data).unwrap()
+    }
+
+    /// Runs one of the quote instructions, whose result is in the return data
+    pub fn quote(&mut self, data: impl InstructionData) -> TransactionResult {
+        let accounts = amm::accounts::Quote {
+            mint_x: self.mint_x,
+            mint_y: self.mint_y,
+            config: self.config,
+            mint_lp: self.mint_lp,
+            vault_x: self.vault_x,
+            vault_y: self.vault_y,
+            token_program_x: self.token_program_x,
+            token_program_y: self.token_program_y,
+            associated_token_program: spl_associated_token_account::ID,
+        };
+        let quote = self.instruction(accounts, data);
+        let admin = self.admin.insecure_clone();
+        self.send(&[quote], &admin)
+    }
+
+    /// Snapshot of the pool accounts, as an off-chain client would fetch it
+    pub fn state(&self) -> PoolState {
+        let data = |key: &Pubkey| self.svm.get_account(key).unwrap().data;
+        PoolState::decode(
+            &data(&self.config),
+            &data(&self.vault_x),
+            &data(&self.vault_y),
+            &data(&self.mint_lp),
+        )
+        .unwrap()
+    }
+
+    /// Lamports held by `address` (zero if it doesn't exist)
+    pub fn lamports(&self, address: &Pubkey) -> u64 {
+        self.svm.get_account(address).map_or(0, |account| account.lamports)
+    }
+
+    /// Token balance of `owner` in its associated account for `mint` (zero if it doesn't exist)
+    pub fn balance(&self, owner: &Keypair, mint: Pubkey) -> u64 {
+        self.svm
+            .get_account(&self.ata(owner, mint))
+            .map_or(0, |account| {
+                StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data)
+                    .unwrap()
+                    .base
+                    .amount
+            })
+    }
+
+    pub fn ata(&self, owner: &Keypair, mint: Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(&owner.pubkey(), &mint, &self.token_program(mint))
+    }
+
+    /// Token program owning `mint`
+    pub fn token_program(&self, mint: Pubkey) -> Pubkey {
+        self.svm.get_account(&mint).unwrap().owner
+    }
+
+    pub fn now(&self) -> i64 {
+        self.svm.get_sysvar::<Clock>().unix_timestamp
+    }
+
+    pub fn slot(&self) -> u64 {
+        self.svm.get_sysvar::<Clock>().slot
+    }
+
+    /// Moves the clock forward by `seconds`
+    pub fn warp(&mut self, seconds: i64) {
+        let mut clock = self.svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += seconds;
+        self.svm.set_sysvar(&clock);
+    }
+
+    /// Moves the clock to the next slot
+    pub fn next_slot(&mut self) {
+        let mut clock = self.svm.get_sysvar::<Clock>();
+        clock.slot += 1;
+        self.svm.set_sysvar(&clock);
+    }
+
+    pub fn instruction(&self, accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+        Instruction {
+            program_id: amm::ID,
+            accounts: accounts.to_account_metas(None),
+            data: data.data(),
+        }
+    }
+
+    /// Sends `instructions` in one transaction paid and signed by `signer`
+    pub fn send(&mut self, instructions: &[Instruction], signer: &Keypair) -> TransactionResult {
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&signer.pubkey()),
+            &[signer],
+            self.svm.latest_blockhash(),
+        );
+        let result = self.svm.send_transaction(transaction);
+        // Identical transactions later on must not be rejected as duplicates
+        self.svm.expire_blockhash();
+        result
+    }
+}
+
+/// Creates a mint of `token` kind with `DECIMALS` decimals and `authority` as
+/// mint authority, returning it and its token program
+fn create_mint(svm: &mut LiteSVM, authority: &Keypair, token: Token) -> (Pubkey, Pubkey) {
+    let mint = Keypair::new();
+    let (program, extensions) = match token {
+        Token::Spl => (spl_token::ID, vec![]),
+        Token::TransferFee(_) => (spl_token_2022::ID, vec![ExtensionType::TransferFeeConfig]),
+        // LiteSVM ships the native mint
+        Token::NativeSol => return (spl_token::native_mint::ID, spl_token::ID),
+    };
+    let space =
+        ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&extensions).unwrap();
+    let rent = svm.minimum_balance_for_rent_exemption(space);
+
+    let mut instructions = vec![system_instruction::create_account(
+        &authority.pubkey(),
+        &mint.pubkey(),
+        rent,
+        space as u64,
+        &program,
+    )];
+    if let Token::TransferFee(basis_points) = token {
+        instructions.push(
+            transfer_fee::instruction::initialize_transfer_fee_config(
+                &program,
+                &mint.pubkey(),
+                None,
+                None,
+                basis_points,
+                u64::MAX,
+            )
+            .unwrap(),
+        );
+    }
+    instructions.push(
+        spl_token_2022::instruction::initialize_mint2(&program, &mint.pubkey(), &authority.pubkey(), None, DECIMALS)
+            .unwrap(),
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&authority.pubkey()),
+        &[authority, &mint],
+        svm.latest_blockhash(),
+    );
+    svm.send_transaction(transaction).unwrap();
+
+    (mint.pubkey(), program)
+}
+
+/// Asserts the transaction failed with `error` from the AMM
+pub fn assert_error(result: TransactionResult, error: AmmError) {
+    let failed = result.expect_err("transaction should have failed");
+    match failed.err {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => {
+            assert_eq!(code, u32::from(error), "{:#?}", failed.meta.logs)
+        }
+        err => panic!("expected {error:?}, got {err:?}"),
+    }
+}
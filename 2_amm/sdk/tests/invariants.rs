@@ -0,0 +1,192 @@
+//! Fuzzes the compiled program with random trades from several users
+//!
+//! Every step runs a deposit, withdrawal, swap or round trip and checks that
+//! the pool never loses value to a trader: the curve invariant never decreases
+//! on swaps, LP tokens never redeem for more than their share of the reserves,
+//! round trips never profit, and failures are always clean errors rather than
+//! panics or overflows.
+
+mod common;
+
+use amm::curves::{StableSwapCurve, U256};
+use amm::CurveType;
+use common::Pool;
+use litesvm::types::TransactionResult;
+use proptest::prelude::*;
+use proptest::test_runner::TestCaseError;
+use solana_sdk::{instruction::InstructionError, signature::Keypair, transaction::TransactionError};
+
+/// Number of traders sharing the pool
+const USERS: usize = 3;
+
+#[derive(Clone, Debug)]
+enum Op {
+    Swap { user: usize, is_x: bool, amount_in: u64 },
+    Deposit { user: usize, amount: u64 },
+    Withdraw { user: usize, amount: u64 },
+    SwapRoundTrip { user: usize, is_x: bool, amount_in: u64 },
+    LiquidityRoundTrip { user: usize, amount: u64 },
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    let user = 0..USERS;
+    prop_oneof![
+        4 => (user.clone(), any::<bool>(), 1u64..50_000_000_000)
+            .prop_map(|(user, is_x, amount_in)| Op::Swap { user, is_x, amount_in }),
+        2 => (user.clone(), 1u64..20_000_000_000).prop_map(|(user, amount)| Op::Deposit { user, amount }),
+        2 => (user.clone(), 1u64..20_000_000_000).prop_map(|(user, amount)| Op::Withdraw { user, amount }),
+        1 => (user.clone(), any::<bool>(), 1u64..50_000_000_000)
+            .prop_map(|(user, is_x, amount_in)| Op::SwapRoundTrip { user, is_x, amount_in }),
+        1 => (user, 1u64..20_000_000_000).prop_map(|(user, amount)| Op::LiquidityRoundTrip { user, amount }),
+    ]
+}
+
+fn curve() -> impl Strategy<Value = (CurveType, u64)> {
+    prop_oneof![
+        Just((CurveType::ConstantProduct, 0)),
+        (1u64..5_000).prop_map(|amp| (CurveType::StableSwap, amp)),
+    ]
+}
+
+/// Invariant of the pool reserves: x * y, or the StableSwap D
+fn invariant(pool: &Pool, curve_type: CurveType, amp: u64) -> U256 {
+    let (x, y) = pool.state().reserves().unwrap();
+    match curve_type {
+        CurveType::ConstantProduct => U256::from(x) * U256::from(y),
+        CurveType::StableSwap => StableSwapCurve { amp }.invariant(x, y).unwrap(),
+    }
+}
+
+/// Balances of token X, token Y and LP tokens of `user`
+fn balances(pool: &Pool, user: &Keypair) -> (u64, u64, u64) {
+    (
+        pool.balance(user, pool.mint_x),
+        pool.balance(user, pool.mint_y),
+        pool.balance(user, pool.mint_lp),
+    )
+}
+
+/// Fails the case if a transaction failed any other way than with a program error,
+/// which is what a panic or an arithmetic overflow would look like
+fn check_clean(result: &TransactionResult) -> Result<(), TestCaseError> {
+    if let Err(failed) = result {
+        prop_assert!(
+            matches!(failed.err, TransactionError::InstructionError(_, InstructionError::Custom(_))),
+            "{:?}: {:#?}",
+            failed.err,
+            failed.meta.logs
+        );
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn trades_preserve_pool_invariants(
+        (curve_type, amp) in curve(),
+        fee in 0u16..1_000,
+        protocol_fee in 0u16..=10_000,
+        seed_x in 1_000u64..100_000_000_000,
+        seed_y in 1_000u64..100_000_000_000,
+        ops in prop::collection::vec(op(), 1..24),
+    ) {
+        let mut pool = Pool::new(fee, curve_type, amp);
+        pool.update_config(amm::instruction::UpdateProtocolFee { protocol_fee }).unwrap();
+
+        let users: Vec<Keypair> = (0..USERS).map(|_| pool.user(u64::MAX / 8, u64::MAX / 8)).collect();
+        pool.deposit(&users[0], seed_x.min(seed_y), seed_x, seed_y).unwrap();
+
+        for op in ops {
+            let state = pool.state();
+            let (reserve_x, reserve_y) = state.reserves().unwrap();
+            let supply = state.lp_supply as u128;
+
+            match op {
+                Op::Swap { user, is_x, amount_in } => {
+                    let before = invariant(&pool, curve_type, amp);
+                    let result = pool.swap(&users[user], is_x, amount_in, 0);
+                    check_clean(&result)?;
+
+                    // Rounding and fees only ever leave value in the pool
+                    // (StableSwap's D is found iteratively, to within one unit)
+                    let after = invariant(&pool, curve_type, amp);
+                    prop_assert!(after + 1 >= before, "invariant fell from {} to {}", before, after);
+                    if curve_type == CurveType::ConstantProduct {
+                        prop_assert!(after >= before, "k fell from {} to {}", before, after);
+                    }
+                }
+                Op::Deposit { user, amount } => {
+                    let (x, y, lp) = balances(&pool, &users[user]);
+                    let result = pool.deposit(&users[user], amount, u64::MAX, u64::MAX);
+                    check_clean(&result)?;
+                    if result.is_err() {
+                        continue;
+                    }
+
+                    // LP tokens never cost less than their share of the reserves
+                    let (new_x, new_y, new_lp) = balances(&pool, &users[user]);
+                    prop_assert_eq!(new_lp - lp, amount);
+                    prop_assert!((x - new_x) as u128 * supply >= amount as u128 * reserve_x as u128);
+                    prop_assert!((y - new_y) as u128 * supply >= amount as u128 * reserve_y as u128);
+                }
+                Op::Withdraw { user, amount } => {
+                    let (x, y, lp) = balances(&pool, &users[user]);
+                    let amount = amount.min(lp);
+                    let result = pool.withdraw(&users[user], amount, 0, 0);
+                    check_clean(&result)?;
+                    if result.is_err() {
+                        continue;
+                    }
+
+                    // LP tokens never redeem for more than their share of the reserves
+                    let (new_x, new_y, _) = balances(&pool, &users[user]);
+                    prop_assert!((new_x - x) as u128 * supply <= amount as u128 * reserve_x as u128);
+                    prop_assert!((new_y - y) as u128 * supply <= amount as u128 * reserve_y as u128);
+                }
+                Op::SwapRoundTrip { user, is_x, amount_in } => {
+                    let (x, y, _) = balances(&pool, &users[user]);
+                    let result = pool.swap(&users[user], is_x, amount_in, 0);
+                    check_clean(&result)?;
+                    if result.is_err() {
+                        continue;
+                    }
+
+                    // Sell everything bought straight back
+                    let (mid_x, mid_y, _) = balances(&pool, &users[user]);
+                    let bought = match is_x {
+                        true => mid_y - y,
+                        false => mid_x - x,
+                    };
+                    let result = pool.swap(&users[user], !is_x, bought, 0);
+                    check_clean(&result)?;
+                    if result.is_err() {
+                        continue;
+                    }
+
+                    let (new_x, new_y, _) = balances(&pool, &users[user]);
+                    prop_assert!(new_x <= x && new_y <= y, "round trip turned {}/{} into {}/{}", x, y, new_x, new_y);
+                }
+                Op::LiquidityRoundTrip { user, amount } => {
+                    let (x, y, lp) = balances(&pool, &users[user]);
+                    let result = pool.deposit(&users[user], amount, u64::MAX, u64::MAX);
+                    check_clean(&result)?;
+                    if result.is_err() {
+                        continue;
+                    }
+
+                    let result = pool.withdraw(&users[user], amount, 0, 0);
+                    check_clean(&result)?;
+                    if result.is_err() {
+                        continue;
+                    }
+
+                    let (new_x, new_y, new_lp) = balances(&pool, &users[user]);
+                    prop_assert_eq!(new_lp, lp);
+                    prop_assert!(new_x <= x && new_y <= y, "round trip turned {}/{} into {}/{}", x, y, new_x, new_y);
+                }
+            }
+        }
+    }
+}
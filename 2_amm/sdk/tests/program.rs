@@ -0,0 +1,1078 @@
+//! Behaviour of the compiled AMM program in an in-process SVM
+
+mod common;
+
+use amm::constants::{CONFIG_VERSION, MINIMUM_LIQUIDITY, VOLATILITY_HALF_LIFE};
+use amm::errors::AmmError;
+use amm::permissions::attestation_message;
+use amm::states::{Config, Factory, LegacyConfig, PoolIndex};
+use amm::CurveType;
+use anchor_lang::{AccountDeserialize, AccountSerialize, AnchorSerialize, Discriminator, Space};
+use anchor_spl::associated_token::{get_associated_token_address, spl_associated_token_account};
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022::{self, extension::transfer_fee};
+use common::{assert_error, Pool, Token};
+use solana_sdk::{
+    ed25519_program,
+    instruction::{Instruction, InstructionError},
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_program, sysvar,
+    transaction::TransactionError,
+};
+
+/// Creates a constant product pool seeded with `reserve_x`/`reserve_y`, returning the seeding LP
+fn seeded_pool(reserve_x: u64, reserve_y: u64) -> (Pool, Keypair) {
+    let mut pool = Pool::new(30, CurveType::ConstantProduct, 0);
+    let lp = pool.user(reserve_x, reserve_y);
+    pool.deposit(&lp, 1_000_000, reserve_x, reserve_y).unwrap();
+    (pool, lp)
+}
+
+#[test]
+fn initializes_pool() {
+    let mut pool = Pool::new(30, CurveType::ConstantProduct, 0);
+
+    let account = pool.svm.get_account(&pool.config).unwrap();
+    let config = Config::try_deserialize(&mut &account.data[..]).unwrap();
+    assert_eq!((config.seed, config.fee, config.locked), (42, 30, false));
+    assert_eq!((config.mint_x, config.mint_y), (pool.mint_x, pool.mint_y));
+    assert_eq!(config.authority, Some(pool.admin.pubkey()));
+
+    let state = pool.state();
+    assert_eq!((state.vault_x, state.vault_y, state.lp_supply), (0, 0, 0));
+
+    // The oracle comes with the pool, `initialize_oracle` is only for older pools
+    assert!(pool.svm.get_account(&pool.oracle).is_some());
+    assert!(config.has_oracle);
+    let accounts = amm::accounts::InitializeOracle {
+        payer: pool.admin.pubkey(),
+        config: pool.config,
+        oracle: pool.oracle,
+        system_program: system_program::ID,
+    };
+    let initialize_oracle = pool.instruction(accounts, amm::instruction::InitializeOracle {});
+    assert!(pool.send(&[initialize_oracle], &pool.admin.insecure_clone()).is_err());
+}
+
+#[test]
+fn first_deposit_locks_minimum_liquidity() {
+    let mut pool = Pool::new(30, CurveType::ConstantProduct, 0);
+    let user = pool.user(10_000_000, 10_000_000);
+
+    // Dust pools are rejected
+    assert_error(
+        pool.deposit(&user, MINIMUM_LIQUIDITY - 1, 10_000, 10_000),
+        AmmError::LiquidityLessThanMinimum,
+    );
+
+    pool.deposit(&user, 1_000_000, 2_000_000, 5_000_000).unwrap();
+    assert_eq!(pool.balance(&user, pool.mint_lp), 1_000_000);
+    assert_eq!(pool.state().lp_supply, 1_000_000 + MINIMUM_LIQUIDITY);
+    assert_eq!((pool.state().vault_x, pool.state().vault_y), (2_000_000, 5_000_000));
+}
+
+#[test]
+fn donations_cant_inflate_the_lp_price_against_later_depositors() {
+    let mut pool = Pool::new(30, CurveType::ConstantProduct, 0);
+    let attacker = pool.user(10_001_000, 10_001_000);
+    let victim = pool.user(1_000_000, 1_000_000);
+
+    // Open the pool with the smallest deposit allowed, then donate to the vaults
+    // to push the price of each LP token up
+    pool.deposit(&attacker, MINIMUM_LIQUIDITY, MINIMUM_LIQUIDITY, MINIMUM_LIQUIDITY).unwrap();
+    let donations: Vec<_> = [(pool.mint_x, pool.vault_x), (pool.mint_y, pool.vault_y)]
+        .into_iter()
+        .map(|(mint, vault)| {
+            let from = pool.ata(&attacker, mint);
+            spl_token::instruction::transfer(&spl_token::ID, &from, &vault, &attacker.pubkey(), &[], 10_000_000)
+                .unwrap()
+        })
+        .collect();
+    pool.send(&donations, &attacker).unwrap();
+    assert_eq!(pool.state().lp_supply, 2 * MINIMUM_LIQUIDITY);
+
+    // Depositors name the LP amount they get and pay for it rounded up, so they
+    // can't be minted zero LP tokens: a budget below one LP token is rejected
+    assert_error(pool.deposit(&victim, 1, 5_000, 5_000), AmmError::SlippageExceeded);
+    pool.deposit(&victim, 199, 1_000_000, 1_000_000).unwrap();
+    assert_eq!(pool.balance(&victim, pool.mint_lp), 199);
+
+    // The victim gets back what they paid, give or take rounding, while the
+    // locked liquidity kept half of the donation from the attacker
+    pool.withdraw(&victim, 199, 0, 0).unwrap();
+    assert!(pool.balance(&victim, pool.mint_x) >= 1_000_000 - 2);
+    assert!(pool.balance(&victim, pool.mint_y) >= 1_000_000 - 2);
+    pool.withdraw(&attacker, MINIMUM_LIQUIDITY, 0, 0).unwrap();
+    assert!(pool.balance(&attacker, pool.mint_x) < 10_001_000 / 2 + MINIMUM_LIQUIDITY);
+    assert!(pool.balance(&attacker, pool.mint_y) < 10_001_000 / 2 + MINIMUM_LIQUIDITY);
+}
+
+#[test]
+fn donations_before_the_first_deposit_dont_block_it() {
+    let mut pool = Pool::new(30, CurveType::ConstantProduct, 0);
+    let griefer = pool.user(1, 1);
+    let user = pool.user(1_000_000, 1_000_000);
+
+    // A token sent to a vault of the fresh pool leaves it with reserves but no LP supply
+    let donation = spl_token::instruction::transfer(
+        &spl_token::ID,
+        &pool.ata(&griefer, pool.mint_x),
+        &pool.vault_x,
+        &griefer.pubkey(),
+        &[],
+        1,
+    )
+    .unwrap();
+    pool.send(&[donation], &griefer).unwrap();
+
+    // The first deposit still sets the price, and the donation stays in the pool
+    pool.deposit(&user, 1_000_000, 1_000_000, 1_000_000).unwrap();
+    assert_eq!(pool.balance(&user, pool.mint_lp), 1_000_000);
+    assert_eq!((pool.state().vault_x, pool.state().vault_y), (1_000_001, 1_000_000));
+    pool.deposit(&user, 1_000, 1_000_000, 1_000_000).unwrap();
+}
+
+#[test]
+fn deposits_and_withdraws_proportionally() {
+    let (mut pool, _) = seeded_pool(2_000_000, 5_000_000);
+    let user = pool.user(1_000_000, 1_000_000);
+    let supply = pool.state().lp_supply;
+
+    // A tenth of the supply takes a tenth of each reserve, rounded up
+    pool.deposit(&user, supply / 10, 1_000_000, 1_000_000).unwrap();
+    assert_eq!(pool.balance(&user, pool.mint_x), 1_000_000 - 200_000);
+    assert_eq!(pool.balance(&user, pool.mint_y), 1_000_000 - 500_000);
+
+    // Withdrawing it back returns no more than was put in
+    pool.withdraw(&user, supply / 10, 0, 0).unwrap();
+    assert!(pool.balance(&user, pool.mint_x) <= 1_000_000);
+    assert!(pool.balance(&user, pool.mint_y) <= 1_000_000);
+    assert!(pool.balance(&user, pool.mint_x) >= 1_000_000 - 1);
+    assert!(pool.balance(&user, pool.mint_y) >= 1_000_000 - 1);
+    assert_eq!(pool.balance(&user, pool.mint_lp), 0);
+}
+
+#[test]
+fn swaps_in_both_directions() {
+    let (mut pool, _) = seeded_pool(10_000_000, 10_000_000);
+    let user = pool.user(1_000_000, 1_000_000);
+    let k = |pool: &Pool| {
+        let (x, y) = pool.state().reserves().unwrap();
+        x as u128 * y as u128
+    };
+
+    let k_before = k(&pool);
+    pool.swap(&user, true, 100_000, 1).unwrap();
+    assert_eq!(pool.balance(&user, pool.mint_x), 900_000);
+    let bought_y = pool.balance(&user, pool.mint_y) - 1_000_000;
+    // Fewer than 1:1 because of the curve and the 0.3% fee
+    assert!(bought_y > 0 && bought_y < 100_000 * 997 / 1_000);
+    assert!(k(&pool) > k_before);
+
+    let k_before = k(&pool);
+    pool.swap(&user, false, bought_y, 1).unwrap();
+    assert_eq!(pool.balance(&user, pool.mint_y), 1_000_000);
+    // Round-tripping pays the fee twice
+    assert!(pool.balance(&user, pool.mint_x) < 1_000_000);
+    assert!(k(&pool) > k_before);
+}
+
+#[test]
+fn locked_pool_rejects_trading() {
+    let (mut pool, lp) = seeded_pool(10_000_000, 10_000_000);
+    let user = pool.user(1_000_000, 1_000_000);
+
+    pool.update_config(amm::instruction::Lock {}).unwrap();
+    assert_error(pool.swap(&user, true, 1_000, 0), AmmError::PoolLocked);
+    assert_error(pool.deposit(&user, 1_000, 1_000_000, 1_000_000), AmmError::PoolLocked);
+    assert_error(pool.withdraw(&lp, 1_000, 0, 0), AmmError::PoolLocked);
+
+    // Quotes fail the same way
+    assert_error(pool.quote(amm::instruction::QuoteSwap { is_x: true, amount_in: 1_000 }), AmmError::PoolLocked);
+    assert_error(pool.quote(amm::instruction::QuoteDeposit { amount: 1_000 }), AmmError::PoolLocked);
+    assert_error(pool.quote(amm::instruction::QuoteWithdraw { amount: 1_000 }), AmmError::PoolLocked);
+
+    // Only the authority can lock or unlock
+    let accounts = amm::accounts::UpdateConfig {
+        authority: user.pubkey(),
+        config: pool.config,
+    };
+    let unlock = pool.instruction(accounts, amm::instruction::Unlock {});
+    assert!(pool.send(&[unlock], &user).is_err());
+
+    pool.update_config(amm::instruction::Unlock {}).unwrap();
+    pool.quote(amm::instruction::QuoteSwap { is_x: true, amount_in: 1_000 }).unwrap();
+    pool.swap(&user, true, 1_000, 0).unwrap();
+}
+
+#[test]
+fn authority_is_handed_over_in_two_steps_and_can_be_renounced() {
+    let mut pool = Pool::new(30, CurveType::ConstantProduct, 0);
+    let (outsider, successor) = (Keypair::new(), Keypair::new());
+    for key in [outsider.pubkey(), successor.pubkey()] {
+        pool.svm.airdrop(&key, 1_000_000_000).unwrap();
+    }
+    let update = |pool: &Pool, signer: &Keypair, data: amm::instruction::UpdateFee| {
+        let accounts = amm::accounts::UpdateConfig { authority: signer.pubkey(), config: pool.config };
+        pool.instruction(accounts, data)
+    };
+    let accept = |pool: &Pool, signer: &Keypair| {
+        let accounts = amm::accounts::AcceptAuthority { pending_authority: signer.pubkey(), config: pool.config };
+        pool.instruction(accounts, amm::instruction::AcceptAuthority {})
+    };
+
+    // Admin instructions are reserved to the authority
+    let fee = update(&pool, &outsider, amm::instruction::UpdateFee { fee: 100 });
+    assert_error(pool.send(&[fee], &outsider), AmmError::InvalidAuthority);
+    let nominate = amm::instruction::TransferAuthority { new_authority: outsider.pubkey() };
+    let accounts = amm::accounts::UpdateConfig { authority: outsider.pubkey(), config: pool.config };
+    let nominate = pool.instruction(accounts, nominate);
+    assert_error(pool.send(&[nominate], &outsider), AmmError::InvalidAuthority);
+
+    // Nothing to accept before a nomination
+    let ix = accept(&pool, &successor);
+    assert_error(pool.send(&[ix], &successor), AmmError::NoPendingAuthority);
+
+    // A nomination changes nothing until the nominee accepts it, and nobody else can
+    pool.update_config(amm::instruction::TransferAuthority { new_authority: successor.pubkey() }).unwrap();
+    assert_eq!(pool.state().config.authority, Some(pool.admin.pubkey()));
+    let ix = update(&pool, &successor, amm::instruction::UpdateFee { fee: 100 });
+    assert_error(pool.send(&[ix], &successor), AmmError::InvalidAuthority);
+    let ix = accept(&pool, &outsider);
+    assert_error(pool.send(&[ix], &outsider), AmmError::InvalidAuthority);
+    let admin = pool.admin.insecure_clone();
+    let ix = accept(&pool, &admin);
+    assert_error(pool.send(&[ix], &admin), AmmError::InvalidAuthority);
+
+    let ix = accept(&pool, &successor);
+    pool.send(&[ix], &successor).unwrap();
+    assert_eq!(pool.state().config.authority, Some(successor.pubkey()));
+    assert_error(pool.update_config(amm::instruction::UpdateFee { fee: 100 }), AmmError::InvalidAuthority);
+    let ix = update(&pool, &successor, amm::instruction::UpdateFee { fee: 100 });
+    pool.send(&[ix], &successor).unwrap();
+    assert_eq!(pool.state().config.fee, 100);
+
+    // Once renounced, the pool can't be changed by anyone
+    let accounts = amm::accounts::UpdateConfig { authority: successor.pubkey(), config: pool.config };
+    let renounce = pool.instruction(accounts, amm::instruction::RenounceAuthority {});
+    pool.send(&[renounce], &successor).unwrap();
+    assert_eq!(pool.state().config.authority, None);
+    let ix = update(&pool, &successor, amm::instruction::UpdateFee { fee: 30 });
+    assert_error(pool.send(&[ix], &successor), AmmError::NoAuthoritySet);
+    assert_error(pool.update_config(amm::instruction::Lock {}), AmmError::NoAuthoritySet);
+    assert_eq!(pool.state().config.fee, 100);
+}
+
+#[test]
+fn slippage_limits_are_enforced() {
+    let (mut pool, lp) = seeded_pool(10_000_000, 10_000_000);
+    let user = pool.user(1_000_000, 1_000_000);
+
+    let quote = pool.state().quote_swap(true, 100_000, 0, pool.now(), pool.slot()).unwrap();
+    assert_error(pool.swap(&user, true, 100_000, quote.amount_out + 1), AmmError::SlippageExceeded);
+    pool.swap(&user, true, 100_000, quote.amount_out).unwrap();
+
+    let supply = pool.state().lp_supply;
+    assert_error(pool.deposit(&user, supply / 100, 1, 1_000_000), AmmError::SlippageExceeded);
+    assert_error(pool.withdraw(&lp, 1_000, 1_000_000, 0), AmmError::SlippageExceeded);
+
+    // Nothing moved on failure
+    assert_eq!(pool.balance(&user, pool.mint_x), 900_000);
+}
+
+#[test]
+fn rejects_zero_and_oversized_amounts() {
+    let (mut pool, lp) = seeded_pool(10_000_000, 10_000_000);
+    let user = pool.user(1_000_000, 1_000_000);
+
+    assert_error(pool.swap(&user, true, 0, 0), AmmError::InvalidAmount);
+    assert_error(pool.deposit(&user, 0, 1_000, 1_000), AmmError::InvalidAmount);
+    assert_error(pool.withdraw(&lp, 0, 0, 0), AmmError::InvalidAmount);
+
+    // More LP tokens than the supply, or than the user holds
+    assert!(pool.withdraw(&lp, u64::MAX, 0, 0).is_err());
+    assert!(pool.withdraw(&user, 1, 0, 0).is_err());
+    // More tokens than the user holds
+    assert!(pool.swap(&user, true, 1_000_001, 0).is_err());
+}
+
+#[test]
+fn extreme_reserves_fail_cleanly() {
+    // Reserves whose product overflows u64 many times over
+    let big = u64::MAX / 4;
+    let (mut pool, lp) = seeded_pool(big, big);
+    let user = pool.user(big, 1);
+
+    // Huge trades either execute or fail with a program error, never a panic
+    for amount_in in [big, big / 2, 1] {
+        if let Err(failed) = pool.swap(&user, true, amount_in, 0) {
+            assert!(
+                matches!(failed.err, TransactionError::InstructionError(_, InstructionError::Custom(_))),
+                "{:?}",
+                failed.meta.logs
+            );
+        }
+    }
+    let supply = pool.state().lp_supply;
+    pool.withdraw(&lp, supply - MINIMUM_LIQUIDITY, 0, 0).unwrap();
+    let (reserve_x, reserve_y) = pool.state().reserves().unwrap();
+    assert!(reserve_x > 0 && reserve_y > 0);
+}
+
+#[test]
+fn transfer_fees_are_paid_on_top_of_the_curve() {
+    // Token-2022 X withholding 1% of every transfer, SPL Token Y
+    let mut pool = Pool::with_tokens(30, CurveType::ConstantProduct, 0, Token::TransferFee(100), Token::Spl);
+    let user = pool.user(20_000_000, 20_000_000);
+
+    // Reserves only count what reached the vaults
+    pool.deposit(&user, 1_000_000, 10_000_000, 10_000_000).unwrap();
+    assert_eq!((pool.state().vault_x, pool.state().vault_y), (9_900_000, 10_000_000));
+
+    // Selling X prices the 99% of the input the vault receives
+    let quote = pool.state().quote_swap(true, 99_000, 0, pool.now(), pool.slot()).unwrap();
+    assert_error(pool.swap(&user, true, 100_000, quote.amount_out + 1), AmmError::SlippageExceeded);
+    pool.swap(&user, true, 100_000, quote.amount_out).unwrap();
+    assert_eq!(pool.balance(&user, pool.mint_y), 10_000_000 + quote.amount_out);
+    assert_eq!(pool.state().vault_x, 9_900_000 + 99_000);
+
+    // Buying X checks slippage on what reaches the user
+    let quote = pool.state().quote_swap(false, 100_000, 0, pool.now(), pool.slot()).unwrap();
+    let received = quote.amount_out - quote.amount_out.div_ceil(100);
+    assert_error(pool.swap(&user, false, 100_000, received + 1), AmmError::SlippageExceeded);
+    let before = pool.balance(&user, pool.mint_x);
+    pool.swap(&user, false, 100_000, received).unwrap();
+    assert_eq!(pool.balance(&user, pool.mint_x) - before, received);
+
+    // Deposits take the transfer fee on top of the proportional amount
+    let supply = pool.state().lp_supply;
+    let quote = pool.state().quote_deposit(supply / 10, u64::MAX, u64::MAX, pool.now()).unwrap();
+    let vault_x = pool.state().vault_x;
+    let before = pool.balance(&user, pool.mint_x);
+    pool.deposit(&user, supply / 10, u64::MAX, u64::MAX).unwrap();
+    assert_eq!(pool.state().vault_x - vault_x, quote.amount_x);
+    assert!(before - pool.balance(&user, pool.mint_x) > quote.amount_x);
+}
+
+#[test]
+fn dynamic_fee_rises_with_price_moves_and_decays() {
+    let (mut pool, _) = seeded_pool(10_000_000, 10_000_000);
+    let user = pool.user(5_000_000, 5_000_000);
+
+    assert_error(
+        pool.update_config(amm::instruction::UpdateDynamicFee { min_fee: 50, max_fee: 10 }),
+        AmmError::InvalidFee,
+    );
+    pool.update_config(amm::instruction::UpdateDynamicFee { min_fee: 10, max_fee: 100 }).unwrap();
+    let fee_bps = |pool: &Pool| pool.state().quote_swap(true, 1_000, 0, pool.now(), pool.slot()).unwrap().fee_bps;
+    assert_eq!(fee_bps(&pool), 10);
+
+    // A 10% swap moves the price by about 17%, lifting the fee to the cap
+    pool.swap(&user, true, 1_000_000, 1).unwrap();
+    assert_eq!(fee_bps(&pool), 100);
+
+    // The surcharge halves every half-life until the fee is back at the minimum
+    pool.warp(VOLATILITY_HALF_LIFE * 4);
+    assert!(fee_bps(&pool) > 10 && fee_bps(&pool) < 100);
+    pool.warp(VOLATILITY_HALF_LIFE * 20);
+    assert_eq!(fee_bps(&pool), 10);
+
+    pool.update_config(amm::instruction::DisableDynamicFee {}).unwrap();
+    assert_eq!(fee_bps(&pool), 30);
+}
+
+#[test]
+fn farm_pays_rewards_pro_rata_to_stakers() {
+    let (mut pool, lp) = seeded_pool(10_000_000, 10_000_000);
+    let user = pool.user(3_000_000, 3_000_000);
+    pool.deposit(&user, 300_000, 3_000_000, 3_000_000).unwrap();
+    let farm = pool.farm(1_000, 15_000);
+
+    pool.stake(&farm, &lp, amm::instruction::Stake { amount: 100_000 }).unwrap();
+    pool.stake(&farm, &user, amm::instruction::Stake { amount: 300_000 }).unwrap();
+    assert_eq!(pool.balance(&lp, pool.mint_lp), 900_000);
+    assert_error(
+        pool.stake(&farm, &user, amm::instruction::Unstake { amount: 300_001 }),
+        AmmError::InsufficientBalance,
+    );
+
+    // 10 seconds at 1000 per second, shared 1:3
+    pool.warp(10);
+    pool.stake(&farm, &lp, amm::instruction::Harvest {}).unwrap();
+    pool.stake(&farm, &user, amm::instruction::Harvest {}).unwrap();
+    assert_eq!(pool.balance(&lp, farm.reward_mint), 2_500);
+    assert_eq!(pool.balance(&user, farm.reward_mint), 7_500);
+
+    // Emergency withdrawal returns the LP tokens, the other staker now earns everything
+    pool.stake(&farm, &user, amm::instruction::EmergencyWithdraw {}).unwrap();
+    assert_eq!(pool.balance(&user, pool.mint_lp), 300_000);
+    pool.warp(10);
+
+    // The vault only holds 5000 of the 10000 earned, the rest stays owed until topped up
+    pool.stake(&farm, &lp, amm::instruction::Unstake { amount: 100_000 }).unwrap();
+    assert_eq!(pool.balance(&lp, pool.mint_lp), 1_000_000);
+    assert_eq!(pool.balance(&lp, farm.reward_mint), 7_500);
+    pool.fund_farm(&farm, 10_000);
+    pool.stake(&farm, &lp, amm::instruction::Harvest {}).unwrap();
+    assert_eq!(pool.balance(&lp, farm.reward_mint), 12_500);
+    assert_eq!(pool.balance(&user, farm.reward_mint), 7_500);
+}
+
+#[test]
+fn empty_pool_can_be_closed_by_its_authority() {
+    let (mut pool, lp) = seeded_pool(2_000_000, 5_000_000);
+    assert_error(pool.close(&pool.admin.insecure_clone()), AmmError::PoolNotEmpty);
+
+    // Only the locked minimum liquidity is left once the LP withdraws
+    pool.withdraw(&lp, 1_000_000, 0, 0).unwrap();
+    let stranger = pool.user(0, 0);
+    assert_error(pool.close(&stranger), AmmError::InvalidAuthority);
+
+    // The oracle can't be left behind
+    let admin = pool.admin.insecure_clone();
+    let mut accounts = pool.close_accounts(&admin);
+    accounts.oracle = None;
+    let close = pool.instruction(accounts, amm::instruction::ClosePool {});
+    assert_error(pool.send(&[close], &admin), AmmError::MissingPoolAccount);
+
+    let lamports = pool.svm.get_account(&pool.admin.pubkey()).unwrap().lamports;
+    let (reserve_x, reserve_y) = (pool.state().vault_x, pool.state().vault_y);
+    pool.close(&pool.admin.insecure_clone()).unwrap();
+
+    for account in [pool.config, pool.vault_x, pool.vault_y, pool.locked_lp, pool.oracle] {
+        assert!(pool.svm.get_account(&account).is_none_or(|account| account.lamports == 0));
+    }
+    assert!(pool.svm.get_account(&pool.admin.pubkey()).unwrap().lamports > lamports);
+    assert_eq!(pool.balance(&pool.admin, pool.mint_x), reserve_x);
+    assert_eq!(pool.balance(&pool.admin, pool.mint_y), reserve_y);
+}
+
+#[test]
+fn withheld_transfer_fees_must_be_harvested_before_closing() {
+    // Token-2022 X withholding 1% of every transfer, SPL Token Y
+    let mut pool = Pool::with_tokens(30, CurveType::ConstantProduct, 0, Token::TransferFee(100), Token::Spl);
+    let lp = pool.user(10_000_000, 10_000_000);
+    pool.deposit(&lp, 1_000_000, 10_000_000, 10_000_000).unwrap();
+    pool.withdraw(&lp, 1_000_000, 0, 0).unwrap();
+
+    // The deposit left fees withheld in vault X, which Token-2022 won't close
+    let admin = pool.admin.insecure_clone();
+    assert!(pool.close(&admin).is_err());
+    assert!(pool.svm.get_account(&pool.config).is_some_and(|account| account.lamports > 0));
+
+    // Anyone can sweep them to the mint, after which the pool closes
+    let harvest = transfer_fee::instruction::harvest_withheld_tokens_to_mint(
+        &spl_token_2022::ID,
+        &pool.mint_x,
+        &[&pool.vault_x],
+    )
+    .unwrap();
+    pool.send(&[harvest], &admin).unwrap();
+    pool.close(&admin).unwrap();
+    assert!(pool.svm.get_account(&pool.config).is_none_or(|account| account.lamports == 0));
+}
+
+#[test]
+fn legacy_configs_are_migrated_in_place() {
+    let (mut pool, _) = seeded_pool(10_000_000, 10_000_000);
+    let user = pool.user(1_000_000, 1_000_000);
+
+    // Rewrite the config byte for byte as the first deployed program stored it:
+    // its discriminator and fields in an account sized without the discriminator
+    let mut account = pool.svm.get_account(&pool.config).unwrap();
+    let config = Config::try_deserialize(&mut &account.data[..]).unwrap();
+    let legacy = LegacyConfig {
+        seed: config.seed,
+        authority: None,
+        mint_x: config.mint_x,
+        mint_y: config.mint_y,
+        fee: 25,
+        locked: false,
+        config_bump: config.config_bump,
+        lp_bump: config.lp_bump,
+    };
+    let mut data = Config::DISCRIMINATOR.to_vec();
+    legacy.serialize(&mut data).unwrap();
+    assert_eq!(data.len(), 8 + 8 + 1 + 32 + 32 + 2 + 1 + 1 + 1);
+    data.resize(LegacyConfig::INIT_SPACE, 0);
+    account.lamports = pool.svm.minimum_balance_for_rent_exemption(data.len());
+    account.data = data;
+    pool.svm.set_account(pool.config, account).unwrap();
+    assert!(pool.swap(&user, true, 1_000, 0).is_err());
+
+    let migrate = |pool: &Pool, pool_index: Pubkey| {
+        let accounts = amm::accounts::MigrateConfig {
+            payer: user.pubkey(),
+            config: pool.config,
+            oracle: pool.oracle,
+            pool_index,
+            system_program: system_program::ID,
+        };
+        pool.instruction(accounts, amm::instruction::MigrateConfig {})
+    };
+    // The index is looked up at the address of the pool's pair and fee tier
+    let wrong_index = migrate(&pool, Pubkey::new_unique());
+    assert_error(pool.send(&[wrong_index], &user), AmmError::InvalidPoolIndex);
+    let migrate = migrate(&pool, amm_sdk::pda::pool_index_address(pool.mint_x, pool.mint_y, 25));
+    pool.send(std::slice::from_ref(&migrate), &user).unwrap();
+
+    let account = pool.svm.get_account(&pool.config).unwrap();
+    assert_eq!(account.data.len(), 8 + Config::INIT_SPACE);
+    assert_eq!(account.lamports, pool.svm.minimum_balance_for_rent_exemption(account.data.len()));
+    let migrated = Config::try_deserialize(&mut &account.data[..]).unwrap();
+    assert_eq!(migrated.version, CONFIG_VERSION);
+    assert_eq!((migrated.seed, migrated.authority, migrated.fee), (config.seed, None, 25));
+    assert_eq!((migrated.min_fee, migrated.max_fee, migrated.protocol_fee), (25, 25, 0));
+    // The payer isn't made creator of a pool it didn't pay for
+    assert_eq!((migrated.curve_type, migrated.creator), (CurveType::ConstantProduct, Pubkey::default()));
+    // Flags the old layout lacks come from the accounts: the oracle exists, no index does
+    assert_eq!((migrated.has_oracle, migrated.canonical), (true, false));
+    pool.swap(&user, true, 1_000, 0).unwrap();
+
+    // Current accounts have nothing to migrate
+    assert_error(pool.send(&[migrate], &user), AmmError::InvalidConfigVersion);
+}
+
+#[test]
+fn factory_creates_one_canonical_pool_per_pair_and_fee() {
+    let mut pool = Pool::new(30, CurveType::ConstantProduct, 0);
+    let admin = pool.admin.insecure_clone();
+    let factory = amm_sdk::pda::factory_address();
+    let initialize_factory = pool.instruction(
+        amm::accounts::InitializeFactory {
+            payer: admin.pubkey(),
+            factory,
+            system_program: system_program::ID,
+        },
+        amm::instruction::InitializeFactory {},
+    );
+    pool.send(&[initialize_factory], &admin).unwrap();
+
+    let (mint_x, mint_y) = amm_sdk::pda::sorted_mints(pool.mint_x, pool.mint_y);
+    let create_pool = |pool: &Pool, seed: u64, fee: u16, mint_x: Pubkey, mint_y: Pubkey, authority: Option<Pubkey>| {
+        let config = amm_sdk::pda::config_address(seed);
+        let pool_index = Pubkey::find_program_address(
+            &[b"pool", mint_x.as_ref(), mint_y.as_ref(), &fee.to_le_bytes()],
+            &amm::ID,
+        )
+        .0;
+        pool.instruction(
+            amm::accounts::CreatePool {
+                pool: amm::accounts::Initialize {
+                    admin: admin.pubkey(),
+                    mint_x,
+                    mint_y,
+                    config,
+                    mint_lp: Pubkey::find_program_address(&[b"lp", config.as_ref()], &amm::ID).0,
+                    vault_x: get_associated_token_address(&config, &mint_x),
+                    vault_y: get_associated_token_address(&config, &mint_y),
+                    oracle: amm_sdk::pda::oracle_address(config),
+                    token_program: spl_token::ID,
+                    token_program_x: spl_token::ID,
+                    token_program_y: spl_token::ID,
+                    associated_token_program: spl_associated_token_account::ID,
+                    system_program: system_program::ID,
+                },
+                factory,
+                pool_index,
+                system_program: system_program::ID,
+            },
+            amm::instruction::CreatePool {
+                seed,
+                fee,
+                authority,
+                curve_type: CurveType::ConstantProduct,
+                amp: 0,
+            },
+        )
+    };
+
+    let create = create_pool(&pool, 1, 30, mint_x, mint_y, None);
+    pool.send(&[create], &admin).unwrap();
+
+    // The index is found from the pair in either order
+    let index = pool
+        .svm
+        .get_account(&amm_sdk::pda::pool_index_address(pool.mint_y, pool.mint_x, 30))
+        .unwrap();
+    let index = PoolIndex::try_deserialize(&mut &index.data[..]).unwrap();
+    assert_eq!((index.config, index.seed), (amm_sdk::pda::config_address(1), 1));
+    // Canonical pools get their oracle like any other pool
+    let oracle = amm_sdk::pda::oracle_address(amm_sdk::pda::config_address(1));
+    assert!(pool.svm.get_account(&oracle).is_some());
+
+    // Same pair and fee under another seed is a duplicate
+    let duplicate = create_pool(&pool, 2, 30, mint_x, mint_y, None);
+    assert!(pool.send(&[duplicate], &admin).is_err());
+    // Another fee tier is a different pool
+    let other_tier = create_pool(&pool, 3, 5, mint_x, mint_y, None);
+    pool.send(&[other_tier], &admin).unwrap();
+    // Unsorted and identical mints are rejected
+    let unsorted = create_pool(&pool, 4, 100, mint_y, mint_x, None);
+    assert_error(pool.send(&[unsorted], &admin), AmmError::MintsNotSorted);
+    let identical = create_pool(&pool, 5, 100, mint_x, mint_x, None);
+    assert_error(pool.send(&[identical], &admin), AmmError::IdenticalMints);
+    // Canonical pools have no authority to retune them
+    let with_authority = create_pool(&pool, 6, 100, mint_x, mint_y, Some(admin.pubkey()));
+    assert_error(pool.send(&[with_authority], &admin), AmmError::CanonicalPoolAuthority);
+
+    // Their fee tier stays the one they are indexed under, even with an authority
+    let config = amm_sdk::pda::config_address(1);
+    let mut account = pool.svm.get_account(&config).unwrap();
+    let mut state = Config::try_deserialize(&mut &account.data[..]).unwrap();
+    assert!(state.canonical);
+    state.authority = Some(admin.pubkey());
+    let mut data = Vec::new();
+    state.try_serialize(&mut data).unwrap();
+    account.data[..data.len()].copy_from_slice(&data);
+    pool.svm.set_account(config, account).unwrap();
+    let accounts = amm::accounts::UpdateConfig { authority: admin.pubkey(), config };
+    let update_fee = pool.instruction(accounts, amm::instruction::UpdateFee { fee: 5 });
+    assert_error(pool.send(&[update_fee], &admin), AmmError::CanonicalPoolFee);
+
+    let factory = pool.svm.get_account(&factory).unwrap();
+    assert_eq!(Factory::try_deserialize(&mut &factory.data[..]).unwrap().pool_count, 2);
+
+    // A canonical pool written before versioning is still known to be one once migrated
+    let mut account = pool.svm.get_account(&config).unwrap();
+    account.data.truncate(Config::V0_SPACE);
+    pool.svm.set_account(config, account).unwrap();
+    let accounts = amm::accounts::MigrateConfig {
+        payer: admin.pubkey(),
+        config,
+        oracle: amm_sdk::pda::oracle_address(config),
+        pool_index: amm_sdk::pda::pool_index_address(mint_x, mint_y, 30),
+        system_program: system_program::ID,
+    };
+    let migrate = pool.instruction(accounts, amm::instruction::MigrateConfig {});
+    pool.send(&[migrate], &admin).unwrap();
+    let account = pool.svm.get_account(&config).unwrap();
+    let migrated = Config::try_deserialize(&mut &account.data[..]).unwrap();
+    assert_eq!((migrated.has_oracle, migrated.canonical), (true, true));
+
+    // Closing a canonical pool takes its index along, freeing the pair and fee tier
+    for mint in [mint_x, mint_y] {
+        let create = spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+            &admin.pubkey(),
+            &admin.pubkey(),
+            &mint,
+            &spl_token::ID,
+        );
+        pool.send(&[create], &admin).unwrap();
+    }
+    let index = amm_sdk::pda::pool_index_address(mint_x, mint_y, 30);
+    let close = |pool: &Pool, pool_index: Option<Pubkey>| {
+        let mut accounts = pool.close_accounts(&admin);
+        accounts.mint_x = mint_x;
+        accounts.mint_y = mint_y;
+        accounts.config = config;
+        accounts.mint_lp = Pubkey::find_program_address(&[b"lp", config.as_ref()], &amm::ID).0;
+        accounts.locked_lp = None;
+        accounts.vault_x = get_associated_token_address(&config, &mint_x);
+        accounts.vault_y = get_associated_token_address(&config, &mint_y);
+        accounts.creator_ata_x = pool.ata(&admin, mint_x);
+        accounts.creator_ata_y = pool.ata(&admin, mint_y);
+        accounts.oracle = Some(amm_sdk::pda::oracle_address(config));
+        accounts.pool_index = pool_index;
+        pool.instruction(accounts, amm::instruction::ClosePool {})
+    };
+    assert_error(pool.send(&[close(&pool, None)], &admin), AmmError::MissingPoolAccount);
+    pool.send(&[close(&pool, Some(index))], &admin).unwrap();
+    assert!(pool.svm.get_account(&index).is_none_or(|account| account.lamports == 0));
+    let recreate = create_pool(&pool, 7, 30, mint_x, mint_y, None);
+    pool.send(&[recreate], &admin).unwrap();
+}
+
+/// Ed25519 program instruction verifying `signer`'s signature over `message`
+fn ed25519_instruction(signer: &Keypair, message: &[u8]) -> Instruction {
+    // One signature: count and padding, its offsets, then key, signature and message
+    let (public_key, signature, data_start) = (16u16, 48u16, 112u16);
+    let mut data = vec![1, 0];
+    for offset in [signature, u16::MAX, public_key, u16::MAX, data_start, message.len() as u16, u16::MAX] {
+        data.extend_from_slice(&offset.to_le_bytes());
+    }
+    data.extend_from_slice(signer.pubkey().as_ref());
+    data.extend_from_slice(signer.sign_message(message).as_ref());
+    data.extend_from_slice(message);
+    Instruction::new_with_bytes(ed25519_program::ID, &data, vec![])
+}
+
+#[test]
+fn permissioned_pools_only_admit_allowlisted_or_attested_traders() {
+    let (mut pool, _) = seeded_pool(10_000_000, 10_000_000);
+    let gatekeeper = Keypair::new();
+    let (allowed, attested) = (pool.user(1_000_000, 1_000_000), pool.user(1_000_000, 1_000_000));
+    pool.update_config(amm::instruction::EnableAllowlist { gatekeeper: Some(gatekeeper.pubkey()) })
+        .unwrap();
+
+    // Nobody trades or deposits without being admitted, withdrawals stay open
+    assert_error(pool.swap(&allowed, true, 1_000, 0), AmmError::TraderNotAllowed);
+    assert_error(pool.deposit(&allowed, 1_000, u64::MAX, u64::MAX), AmmError::TraderNotAllowed);
+
+    // An allowlist entry admits its trader
+    let entry = pool.allow_trader(&allowed);
+    let mut accounts = pool.swap_accounts(&allowed);
+    accounts.allowlist_entry = Some(entry);
+    let expiration = pool.now() + 60;
+    let swap = pool.instruction(
+        accounts,
+        amm::instruction::Swap { is_x: true, amount_in: 1_000, min_amount_out: 0, expiration, native_sol: false },
+    );
+    pool.send(&[swap], &allowed).unwrap();
+    // Another trader's entry doesn't
+    let mut accounts = pool.swap_accounts(&attested);
+    accounts.allowlist_entry = Some(entry);
+    let borrowed = pool.instruction(
+        accounts,
+        amm::instruction::Swap { is_x: true, amount_in: 1_000, min_amount_out: 0, expiration, native_sol: false },
+    );
+    assert!(pool.send(&[borrowed], &attested).is_err());
+
+    // Revoked entries are closed
+    let revoke = pool.instruction(
+        amm::accounts::RevokeTrader { authority: pool.admin.pubkey(), config: pool.config, allowlist_entry: entry },
+        amm::instruction::RevokeTrader {},
+    );
+    let admin = pool.admin.insecure_clone();
+    pool.send(&[revoke], &admin).unwrap();
+    assert!(pool.svm.get_account(&entry).is_none_or(|account| account.lamports == 0));
+    assert_error(pool.swap(&allowed, true, 1_000, 0), AmmError::TraderNotAllowed);
+
+    // A gatekeeper attestation admits its trader until it expires
+    let attested_swap = |pool: &mut Pool, signer: &Keypair, valid_until: i64| {
+        let mut accounts = pool.swap_accounts(&attested);
+        accounts.instructions = Some(sysvar::instructions::ID);
+        let expiration = pool.now() + 60;
+        let swap = pool.instruction(
+            accounts,
+            amm::instruction::Swap { is_x: true, amount_in: 1_000, min_amount_out: 0, expiration, native_sol: false },
+        );
+        let message = attestation_message(&pool.config, &attested.pubkey(), valid_until);
+        pool.send(&[ed25519_instruction(signer, &message), swap], &attested)
+    };
+    let now = pool.now();
+    attested_swap(&mut pool, &gatekeeper, now + 60).unwrap();
+    pool.warp(1);
+    assert_error(attested_swap(&mut pool, &gatekeeper, now - 1), AmmError::TraderNotAllowed);
+    assert_error(attested_swap(&mut pool, &Keypair::new(), now + 60), AmmError::TraderNotAllowed);
+
+    // Opening the pool up again admits everyone
+    pool.update_config(amm::instruction::DisableAllowlist {}).unwrap();
+    pool.swap(&attested, true, 1_000, 0).unwrap();
+    pool.deposit(&attested, 1_000, u64::MAX, u64::MAX).unwrap();
+}
+
+#[test]
+fn native_sol_is_wrapped_and_unwrapped_within_instructions() {
+    let mut pool = Pool::with_tokens(30, CurveType::ConstantProduct, 0, Token::NativeSol, Token::Spl);
+    let lp = pool.user(1_000_000_000, 1_000_000_000);
+    pool.deposit(&lp, 1_000_000_000, 1_000_000_000, 1_000_000_000).unwrap();
+
+    // Every transaction below pays one signature's fee in SOL
+    const FEE: u64 = 5_000;
+    let trader = pool.user(0, 10_000_000);
+    let wsol = pool.ata(&trader, pool.mint_x);
+    let expiration = pool.now() + 60;
+
+    // Selling token Y pays out native SOL, closing the wrapped SOL account
+    let (lamports, vault_x) = (pool.lamports(&trader.pubkey()) + pool.lamports(&wsol), pool.state().vault_x);
+    let swap = pool.instruction(
+        pool.swap_accounts(&trader),
+        amm::instruction::Swap { is_x: false, amount_in: 1_000_000, min_amount_out: 0, expiration, native_sol: true },
+    );
+    pool.send(&[swap], &trader).unwrap();
+    let amount_out = vault_x - pool.state().vault_x;
+    assert!(amount_out > 0);
+    assert_eq!(pool.lamports(&wsol), 0);
+    assert_eq!(pool.lamports(&trader.pubkey()), lamports + amount_out - FEE);
+
+    // Buying token Y is paid in native SOL through a temporary wrapped SOL account
+    let (lamports, balance_y) = (pool.lamports(&trader.pubkey()), pool.balance(&trader, pool.mint_y));
+    let swap = pool.instruction(
+        pool.swap_accounts(&trader),
+        amm::instruction::Swap { is_x: true, amount_in: 500_000, min_amount_out: 0, expiration, native_sol: true },
+    );
+    pool.send(&[swap], &trader).unwrap();
+    assert_eq!(pool.lamports(&wsol), 0);
+    assert_eq!(pool.lamports(&trader.pubkey()), lamports - 500_000 - FEE);
+    assert!(pool.balance(&trader, pool.mint_y) > balance_y);
+
+    // Deposits and withdrawals move native SOL as well
+    let (lamports, vault_x) = (pool.lamports(&trader.pubkey()), pool.state().vault_x);
+    let deposit = pool.instruction(
+        pool.deposit_accounts(&trader),
+        amm::instruction::Deposit { amount: 1_000_000, max_x: u64::MAX, max_y: u64::MAX, expiration, native_sol: true },
+    );
+    pool.send(&[deposit], &trader).unwrap();
+    let deposited = pool.state().vault_x - vault_x;
+    let lp_account_rent = pool.lamports(&pool.ata(&trader, pool.mint_lp));
+    assert_eq!(pool.lamports(&wsol), 0);
+    assert_eq!(pool.lamports(&trader.pubkey()), lamports - deposited - FEE - lp_account_rent);
+
+    let (lamports, vault_x) = (pool.lamports(&trader.pubkey()), pool.state().vault_x);
+    let withdraw = pool.instruction(
+        pool.withdraw_accounts(&trader),
+        amm::instruction::Withdraw { amount: 1_000_000, min_x: 0, min_y: 0, expiration, native_sol: true },
+    );
+    pool.send(&[withdraw], &trader).unwrap();
+    assert_eq!(pool.lamports(&wsol), 0);
+    assert_eq!(pool.lamports(&trader.pubkey()), lamports + vault_x - pool.state().vault_x - FEE);
+
+    // Wrapped SOL the user keeps in their account is never unwrapped along
+    let holder = pool.user(1_000_000, 1_000_000);
+    let swap = pool.instruction(
+        pool.swap_accounts(&holder),
+        amm::instruction::Swap { is_x: false, amount_in: 1_000, min_amount_out: 0, expiration, native_sol: true },
+    );
+    assert_error(pool.send(&[swap], &holder), AmmError::WrappedSolNotEmpty);
+    assert_eq!(pool.balance(&holder, pool.mint_x), 1_000_000);
+
+    // Pools without wrapped SOL have no native side to pay in
+    let (mut other, _) = seeded_pool(10_000_000, 10_000_000);
+    let user = other.user(1_000_000, 1_000_000);
+    let expiration = other.now() + 60;
+    let swap = other.instruction(
+        other.swap_accounts(&user),
+        amm::instruction::Swap { is_x: true, amount_in: 1_000, min_amount_out: 0, expiration, native_sol: true },
+    );
+    assert_error(other.send(&[swap], &user), AmmError::NoWrappedSol);
+}
+
+#[test]
+fn referrers_get_a_share_of_the_fee_without_raising_it() {
+    let (mut plain, _) = seeded_pool(10_000_000, 10_000_000);
+    let (mut referred, _) = seeded_pool(10_000_000, 10_000_000);
+    for pool in [&mut plain, &mut referred] {
+        pool.update_config(amm::instruction::UpdateProtocolFee { protocol_fee: 2_500 }).unwrap();
+        pool.update_config(amm::instruction::UpdateReferralFee { referral_fee: 2_000 }).unwrap();
+    }
+    // The protocol and referral shares can't add up to more than the fee
+    assert_error(
+        referred.update_config(amm::instruction::UpdateReferralFee { referral_fee: 7_501 }),
+        AmmError::InvalidFee,
+    );
+    assert_error(
+        referred.update_config(amm::instruction::UpdateProtocolFee { protocol_fee: 8_001 }),
+        AmmError::InvalidFee,
+    );
+
+    let trader = plain.user(1_000_000, 0);
+    plain.swap(&trader, true, 100_000, 1).unwrap();
+
+    let trader = referred.user(1_000_000, 0);
+    let referrer = referred.user(0, 0);
+    let expiration = referred.now() + 60;
+    let swap = |pool: &Pool, referrer_token_account| {
+        let mut accounts = pool.swap_accounts(&trader);
+        accounts.referrer_token_account = Some(referrer_token_account);
+        pool.instruction(
+            accounts,
+            amm::instruction::Swap { is_x: true, amount_in: 100_000, min_amount_out: 1, expiration, native_sol: false },
+        )
+    };
+
+    // The referral share is paid in the input token
+    let wrong_mint = swap(&referred, referred.ata(&referrer, referred.mint_y));
+    assert_error(referred.send(&[wrong_mint], &trader), AmmError::InvalidReferrer);
+    let referred_swap = swap(&referred, referred.ata(&referrer, referred.mint_x));
+    referred.send(&[referred_swap], &trader).unwrap();
+
+    // Same trade for the user, 20% of the 300 fee for the referrer, out of the LP share
+    assert_eq!(referred.balance(&trader, referred.mint_y), plain.balance(&trader, plain.mint_y));
+    assert_eq!(referred.balance(&referrer, referred.mint_x), 60);
+    let (plain, referred) = (plain.state(), referred.state());
+    assert_eq!(referred.config.protocol_fees_x, plain.config.protocol_fees_x);
+    assert_eq!(referred.reserves().unwrap().0, plain.reserves().unwrap().0 - 60);
+}
+
+#[test]
+fn referrer_account_cant_be_owned_by_the_trader() {
+    let (mut pool, _) = seeded_pool(10_000_000, 10_000_000);
+    pool.update_config(amm::instruction::UpdateReferralFee { referral_fee: 2_000 }).unwrap();
+    let trader = pool.user(1_000_000, 0);
+
+    let mut accounts = pool.swap_accounts(&trader);
+    accounts.referrer_token_account = Some(pool.ata(&trader, pool.mint_x));
+    let expiration = pool.now() + 60;
+    let swap = pool.instruction(
+        accounts,
+        amm::instruction::Swap { is_x: true, amount_in: 100_000, min_amount_out: 1, expiration, native_sol: false },
+    );
+    assert_error(pool.send(&[swap], &trader), AmmError::SelfReferral);
+    assert_eq!(pool.balance(&trader, pool.mint_x), 1_000_000);
+}
+
+#[test]
+fn price_band_reverts_swaps_moving_the_price_too_far() {
+    let (mut pool, _) = seeded_pool(10_000_000, 10_000_000);
+    let user = pool.user(1_000_000, 0);
+    pool.update_config(amm::instruction::UpdatePriceBand { max_price_impact: 100, max_slot_deviation: 300 })
+        .unwrap();
+
+    // A 2% price move is over the 1% limit of a single swap, 0.8% is within it
+    assert_error(pool.swap(&user, true, 100_000, 1), AmmError::PriceBandExceeded);
+    pool.swap(&user, true, 40_000, 1).unwrap();
+
+    // Swaps of the same slot add up against the 3% band
+    pool.swap(&user, true, 40_000, 1).unwrap();
+    pool.swap(&user, true, 40_000, 1).unwrap();
+    assert_error(pool.swap(&user, true, 40_000, 1), AmmError::PriceBandExceeded);
+
+    // The next slot starts from the price it opens at
+    pool.next_slot();
+    pool.swap(&user, true, 40_000, 1).unwrap();
+
+    // Limits that would freeze trading are rejected
+    for (max_price_impact, max_slot_deviation) in [(1, 0), (0, 9), (300, 100)] {
+        assert_error(
+            pool.update_config(amm::instruction::UpdatePriceBand { max_price_impact, max_slot_deviation }),
+            AmmError::InvalidPriceBand,
+        );
+    }
+
+    // Zero turns the limits off
+    pool.update_config(amm::instruction::UpdatePriceBand { max_price_impact: 0, max_slot_deviation: 0 })
+        .unwrap();
+    pool.swap(&user, true, 400_000, 1).unwrap();
+}
+
+#[test]
+fn flash_loans_are_repaid_within_the_transaction() {
+    let (mut pool, _) = seeded_pool(10_000_000, 10_000_000);
+    const AMOUNT: u64 = 1_000_000;
+    let fee = amm::math::flash_loan_fee(AMOUNT).unwrap();
+    let user = pool.user(fee, 1_000_000);
+    let borrow = |pool: &Pool| {
+        pool.instruction(pool.flash_accounts(&user), amm::instruction::FlashBorrow { is_x: true, amount: AMOUNT })
+    };
+    let repay = |pool: &Pool| pool.instruction(pool.flash_accounts(&user), amm::instruction::FlashRepay {});
+    let vault_x = pool.state().vault_x;
+
+    // A borrow needs a repayment later in the same transaction
+    assert_error(pool.send(&[borrow(&pool)], &user), AmmError::FlashLoanNotRepaid);
+    assert_error(pool.send(&[repay(&pool)], &user), AmmError::NoFlashLoan);
+    let mut accounts = pool.flash_accounts(&user);
+    accounts.config = amm_sdk::pda::config_address(pool.seed + 1);
+    let repay_other_pool = pool.instruction(accounts, amm::instruction::FlashRepay {});
+    assert_error(pool.send(&[borrow(&pool), repay_other_pool], &user), AmmError::FlashLoanNotRepaid);
+
+    // One loan at a time, and the pool can't be traded against until it is repaid
+    assert_error(
+        pool.send(&[borrow(&pool), borrow(&pool), repay(&pool), repay(&pool)], &user),
+        AmmError::FlashLoanActive,
+    );
+    let expiration = pool.now() + 60;
+    let swap = pool.instruction(
+        pool.swap_accounts(&user),
+        amm::instruction::Swap { is_x: false, amount_in: 1_000, min_amount_out: 0, expiration, native_sol: false },
+    );
+    assert_error(pool.send(&[borrow(&pool), swap, repay(&pool)], &user), AmmError::FlashLoanActive);
+    assert_eq!(pool.state().vault_x, vault_x);
+
+    // The fee is owed in full: one unit short and the whole transaction fails
+    let other = pool.user(0, 0);
+    let transfer = spl_token::instruction::transfer(
+        &spl_token::ID,
+        &pool.ata(&user, pool.mint_x),
+        &pool.ata(&other, pool.mint_x),
+        &user.pubkey(),
+        &[],
+        1,
+    )
+    .unwrap();
+    assert!(pool.send(&[borrow(&pool), transfer, repay(&pool)], &user).is_err());
+    assert_eq!((pool.state().vault_x, pool.balance(&user, pool.mint_x)), (vault_x, fee));
+
+    // Repaid with the fee, which stays in the pool
+    pool.send(&[borrow(&pool), repay(&pool)], &user).unwrap();
+    assert_eq!((pool.state().vault_x, pool.balance(&user, pool.mint_x)), (vault_x + fee, 0));
+    assert!(!pool.state().config.flash_loan_active);
+}
+
+#[test]
+fn donations_after_the_last_trade_dont_move_the_twap() {
+    let (mut pool, _) = seeded_pool(1_000_000, 1_000_000);
+    let user = pool.user(1_000_000, 0);
+    pool.warp(600);
+    pool.swap(&user, true, 1_000, 0).unwrap();
+    pool.warp(600);
+    let twap = pool.twap(600);
+
+    // Tokens sent straight to a vault change its balance but not the recorded reserves
+    let donation = spl_token::instruction::transfer(
+        &spl_token::ID,
+        &pool.ata(&user, pool.mint_x),
+        &pool.vault_x,
+        &user.pubkey(),
+        &[],
+        500_000,
+    )
+    .unwrap();
+    pool.send(&[donation], &user).unwrap();
+    assert_eq!(pool.twap(600), twap);
+
+    // Until a trade records them, the time since keeps the last traded price
+    pool.warp(60);
+    let later = pool.twap(600);
+    assert_eq!(later.price_x, twap.price_x);
+}
+
+#[test]
+fn route_swaps_validate_every_leg_and_the_final_output() {
+    let (mut pool, _) = seeded_pool(10_000_000, 10_000_000);
+    let user = pool.user(1_000_000, 0);
+    let round_trip = [pool.route_leg(&user, true), pool.route_leg(&user, false)];
+
+    // Fees make a round trip return less than it put in, and only the final output is checked
+    assert_error(pool.route_swap(&user, &round_trip, 100_000, 100_000), AmmError::SlippageExceeded);
+    pool.route_swap(&user, &round_trip, 100_000, 0).unwrap();
+    let balance_x = pool.balance(&user, pool.mint_x);
+    assert!(balance_x > 1_000_000 - 100_000 && balance_x < 1_000_000);
+    assert_eq!(pool.balance(&user, pool.mint_y), 0);
+
+    // Each leg sells what the previous one bought
+    let same_direction = [pool.route_leg(&user, true), pool.route_leg(&user, true)];
+    assert_error(pool.route_swap(&user, &same_direction, 1_000, 0), AmmError::InvalidRoute);
+
+    // Vaults must be the pool's own
+    let other = pool.user(0, 1);
+    let mut wrong_vault = pool.route_leg(&user, true);
+    wrong_vault[5].pubkey = pool.ata(&other, pool.mint_y);
+    assert_error(pool.route_swap(&user, &[wrong_vault], 1_000, 0), AmmError::InvalidRoute);
+
+    // Configs must be PDAs of the program, not lookalike accounts it owns
+    let fake_config = Pubkey::new_unique();
+    let config = pool.svm.get_account(&pool.config).unwrap();
+    pool.svm.set_account(fake_config, config).unwrap();
+    let mut fake = pool.route_leg(&user, true);
+    fake[0].pubkey = fake_config;
+    assert_error(pool.route_swap(&user, &[fake], 1_000, 0), AmmError::InvalidRoute);
+    assert_eq!(pool.balance(&user, pool.mint_x), balance_x);
+}
+
+#[test]
+fn route_swaps_mix_spl_token_and_token_2022_legs() {
+    // Token-2022 X withholding 1% of every transfer, SPL Token Y
+    let mut pool = Pool::with_tokens(30, CurveType::ConstantProduct, 0, Token::TransferFee(100), Token::Spl);
+    let lp = pool.user(20_000_000, 20_000_000);
+    pool.deposit(&lp, 1_000_000, 10_000_000, 10_000_000).unwrap();
+    let user = pool.user(1_000_000, 0);
+
+    // Legs price what the vault receives, net of the transfer fee
+    let vault_x = pool.state().vault_x;
+    let quote = pool.state().quote_swap(true, 99_000, 0, pool.now(), pool.slot()).unwrap();
+    pool.route_swap(&user, &[pool.route_leg(&user, true)], 100_000, quote.amount_out).unwrap();
+    assert_eq!(pool.state().vault_x, vault_x + 99_000);
+    assert_eq!(pool.balance(&user, pool.mint_y), quote.amount_out);
+
+    // Through both token programs and back, X only passes through the user's account:
+    // the second leg sells exactly what reached it after the transfer fee
+    let (balance_x, balance_y) = (pool.balance(&user, pool.mint_x), pool.balance(&user, pool.mint_y));
+    let round_trip = [pool.route_leg(&user, false), pool.route_leg(&user, true)];
+    pool.route_swap(&user, &round_trip, balance_y, 0).unwrap();
+    assert_eq!(pool.balance(&user, pool.mint_x), balance_x);
+    let returned = pool.balance(&user, pool.mint_y);
+    assert!(returned > 0 && returned < balance_y);
+}
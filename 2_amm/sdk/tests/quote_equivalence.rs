@@ -0,0 +1,114 @@
+//! Checks `PoolState` quotes against the compiled program
+//!
+//! Every step quotes an operation off-chain from a snapshot of the accounts,
+//! then executes it: a quote must succeed exactly when the transaction does,
+//! and move exactly the quoted amounts.
+
+mod common;
+
+use amm::CurveType;
+use common::Pool;
+use proptest::prelude::*;
+
+#[derive(Clone, Debug)]
+enum Op {
+    Swap { is_x: bool, amount_in: u64 },
+    Deposit { amount: u64 },
+    Withdraw { amount: u64 },
+    NextSlot,
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        3 => (any::<bool>(), 1u64..50_000_000_000).prop_map(|(is_x, amount_in)| Op::Swap { is_x, amount_in }),
+        1 => (1u64..20_000_000_000).prop_map(|amount| Op::Deposit { amount }),
+        1 => (1u64..20_000_000_000).prop_map(|amount| Op::Withdraw { amount }),
+        1 => Just(Op::NextSlot),
+    ]
+}
+
+/// Price band limits `(max_price_impact, max_slot_deviation)`, off or valid
+fn band() -> impl Strategy<Value = (u16, u16)> {
+    prop_oneof![
+        Just((0, 0)),
+        (10u16..500, 0u16..1_000).prop_map(|(impact, extra)| (impact, impact + extra)),
+    ]
+}
+
+fn curve() -> impl Strategy<Value = (CurveType, u64)> {
+    prop_oneof![
+        Just((CurveType::ConstantProduct, 0)),
+        (1u64..5_000).prop_map(|amp| (CurveType::StableSwap, amp)),
+    ]
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn quotes_match_execution(
+        (curve_type, amp) in curve(),
+        fee in 0u16..1_000,
+        protocol_fee in 0u16..=10_000,
+        (max_price_impact, max_slot_deviation) in band(),
+        seed_x in 1_000u64..100_000_000_000,
+        seed_y in 1_000u64..100_000_000_000,
+        ops in prop::collection::vec(op(), 1..16),
+    ) {
+        let mut pool = Pool::new(fee, curve_type, amp);
+        pool.update_config(amm::instruction::UpdateProtocolFee { protocol_fee }).unwrap();
+        pool.update_config(amm::instruction::UpdatePriceBand { max_price_impact, max_slot_deviation }).unwrap();
+
+        let user = pool.user(u64::MAX / 2, u64::MAX / 2);
+        pool.deposit(&user, seed_x.min(seed_y), seed_x, seed_y).unwrap();
+
+        for op in ops {
+            let state = pool.state();
+            let (now, slot) = (pool.now(), pool.slot());
+            let (x, y, lp) = (
+                pool.balance(&user, pool.mint_x),
+                pool.balance(&user, pool.mint_y),
+                pool.balance(&user, pool.mint_lp),
+            );
+
+            match op {
+                Op::Swap { is_x, amount_in } => {
+                    let quote = state.quote_swap(is_x, amount_in, 0, now, slot);
+                    let result = pool.swap(&user, is_x, amount_in, 0);
+                    prop_assert_eq!(quote.is_ok(), result.is_ok(), "{:?} vs {:?}", quote, result);
+                    let Ok(quote) = quote else { continue };
+
+                    let (paid, received) = match is_x {
+                        true => (x - pool.balance(&user, pool.mint_x), pool.balance(&user, pool.mint_y) - y),
+                        false => (y - pool.balance(&user, pool.mint_y), pool.balance(&user, pool.mint_x) - x),
+                    };
+                    prop_assert_eq!((paid, received), (quote.amount_in, quote.amount_out));
+                    prop_assert_eq!(pool.state().reserves().unwrap(), (quote.reserve_x, quote.reserve_y));
+                }
+                Op::Deposit { amount } => {
+                    let quote = state.quote_deposit(amount, u64::MAX, u64::MAX, now);
+                    let result = pool.deposit(&user, amount, u64::MAX, u64::MAX);
+                    prop_assert_eq!(quote.is_ok(), result.is_ok(), "{:?} vs {:?}", quote, result);
+                    let Ok(quote) = quote else { continue };
+
+                    prop_assert_eq!(x - pool.balance(&user, pool.mint_x), quote.amount_x);
+                    prop_assert_eq!(y - pool.balance(&user, pool.mint_y), quote.amount_y);
+                    prop_assert_eq!(pool.balance(&user, pool.mint_lp) - lp, quote.liquidity);
+                }
+                Op::Withdraw { amount } => {
+                    // Only ever burn LP tokens the user holds, the rest is covered by the unit tests
+                    let amount = amount.min(lp);
+                    let quote = state.quote_withdraw(amount, 0, 0, now);
+                    let result = pool.withdraw(&user, amount, 0, 0);
+                    prop_assert_eq!(quote.is_ok(), result.is_ok(), "{:?} vs {:?}", quote, result);
+                    let Ok(quote) = quote else { continue };
+
+                    prop_assert_eq!(pool.balance(&user, pool.mint_x) - x, quote.amount_x);
+                    prop_assert_eq!(pool.balance(&user, pool.mint_y) - y, quote.amount_y);
+                    prop_assert_eq!(lp - pool.balance(&user, pool.mint_lp), quote.liquidity);
+                }
+                Op::NextSlot => pool.next_slot(),
+            }
+        }
+    }
+}
@@ -7,6 +7,7 @@ import {
   mintTo,
   getAssociatedTokenAddress,
 } from "@solana/spl-token";
+import { expect } from "chai";
 
 describe("amm initialize", () => {
   const provider = anchor.AnchorProvider.env();
@@ -21,6 +22,8 @@ describe("amm initialize", () => {
   let lpMint: anchor.web3.PublicKey;
 
   let configPda: anchor.web3.PublicKey;
+  let oracle: anchor.web3.PublicKey;
+  let lockedLp: anchor.web3.PublicKey;
   let vaultX: anchor.web3.PublicKey;
   let vaultY: anchor.web3.PublicKey;
 
@@ -31,6 +34,9 @@ describe("amm initialize", () => {
   const seed = new anchor.BN(42);
   const fee = 30;
 
+  // Deadline for deposit/withdraw/swap, 60 seconds from now
+  const expiration = () => new anchor.BN(Math.floor(Date.now() / 1000) + 60);
+
   it("Initializes the AMM pool", async () => {
     mintX = await createMint(connection, admin.payer, admin.publicKey, null, 6);
     mintY = await createMint(connection, admin.payer, admin.publicKey, null, 6);
@@ -48,8 +54,17 @@ describe("amm initialize", () => {
     vaultX = await getAssociatedTokenAddress(mintX, configPda, true);
     vaultY = await getAssociatedTokenAddress(mintY, configPda, true);
 
+    [oracle] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("oracle"), configPda.toBuffer()],
+      program.programId
+    );
+    [lockedLp] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("locked_lp"), configPda.toBuffer()],
+      program.programId
+    );
+
     const tx = await program.methods
-      .initialize(seed, fee, null)
+      .initialize(seed, fee, null, { constantProduct: {} }, new anchor.BN(0))
       .accounts({
         admin: admin.publicKey,
         mintX,
@@ -58,7 +73,10 @@ describe("amm initialize", () => {
         mintLp: lpMint,
         vaultX,
         vaultY,
+        oracle,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
         associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
@@ -86,17 +104,21 @@ describe("amm initialize", () => {
     const maxY = new anchor.BN(500_000);
 
     const tx = await program.methods
-      .deposit(depositAmount, maxX, maxY)
+      .deposit(depositAmount, maxX, maxY, expiration(), false)
       .accounts({
         user: admin.publicKey,
         userTokenX: userAtaX,
         userTokenY: userAtaY,
         userLp: userLpAta,
         config: configPda,
+        oracle,
+        lockedLp,
         vaultX,
         vaultY,
         lpMint,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
       })
       .rpc();
 
@@ -110,17 +132,20 @@ describe("amm initialize", () => {
     const minY = new anchor.BN(100_000);
 
     const tx = await program.methods
-      .withdraw(withdrawAmount, minX, minY)
+      .withdraw(withdrawAmount, minX, minY, expiration(), false)
       .accounts({
         user: admin.publicKey,
         userTokenX: userAtaX,
         userTokenY: userAtaY,
         userLp: userLpAta,
         config: configPda,
+        oracle,
         vaultX,
         vaultY,
         lpMint,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
       })
       .signers([admin.payer])
       .rpc();
@@ -129,24 +154,89 @@ describe("amm initialize", () => {
     console.log("✅ Withdrawn liquidity");
   });
 
+  it("Quotes a swap with the same math as the swap", async () => {
+    const amountIn = new anchor.BN(100_000);
+    const quote = await program.methods
+      .quoteSwap(true, amountIn)
+      .accounts({
+        mintX,
+        mintY,
+        config: configPda,
+        vaultX,
+        vaultY,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .view();
+
+    const before = await connection.getTokenAccountBalance(userAtaY);
+    await program.methods
+      .swap(true, amountIn, quote.amountOut, expiration(), false)
+      .accounts({
+        user: admin.publicKey,
+        userTokenIn: userAtaX,
+        userTokenOut: userAtaY,
+        config: configPda,
+        oracle,
+        vaultIn: vaultX,
+        vaultOut: vaultY,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+    const after = await connection.getTokenAccountBalance(userAtaY);
+
+    expect(new anchor.BN(after.value.amount).sub(new anchor.BN(before.value.amount)).toString())
+      .to.equal(quote.amountOut.toString());
+    console.log("✅ Swap matched its quote");
+  });
+
   it("Swaps token X for token Y", async () => {
     const amountIn = new anchor.BN(100_000);
     const minOut = new anchor.BN(50_000);
 
     const tx = await program.methods
-      .swap(true, amountIn, minOut)
+      .swap(true, amountIn, minOut, expiration(), false)
       .accounts({
         user: admin.publicKey,
         userTokenIn: userAtaX,
         userTokenOut: userAtaY,
         config: configPda,
+        oracle,
         vaultIn: vaultX,
         vaultOut: vaultY,
-        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
       })
       .rpc();
 
     console.log(`https://explorer.solana.com/tx/${tx}?cluster=devnet`);
     console.log("✅ Swapped X for Y");
   });
+
+  it("Rejects a swap past its expiration", async () => {
+    const expired = new anchor.BN(Math.floor(Date.now() / 1000) - 60);
+
+    try {
+      await program.methods
+        .swap(true, new anchor.BN(100_000), new anchor.BN(0), expired, false)
+        .accounts({
+          user: admin.publicKey,
+          userTokenIn: userAtaX,
+          userTokenOut: userAtaY,
+          config: configPda,
+          oracle,
+          vaultIn: vaultX,
+          vaultOut: vaultY,
+          tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+          tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      throw new Error("expired swap should have failed");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("OfferExpired");
+    }
+
+    console.log("✅ Expired swap rejected");
+  });
 });